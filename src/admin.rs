@@ -0,0 +1,140 @@
+//! Endpoints reserved to the operators of the linker, behind the [`Admin`] guard.
+
+use crate::{
+    auth::Admin,
+    config::ApiConfig,
+    db,
+    error::{ApiError, ApiResult},
+    format::Negotiated,
+    id::Snowflake,
+    params::{count, snowflake, timestamp, Bucket, OptionalCount, OptionalTimestamp},
+    pool::Pool,
+    webhook::Webhook,
+};
+use chrono::Utc;
+use rocket::{get, routes, serde::json::Json, Route, State};
+use serde::Serialize;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        admin_chan_guilds,
+        admin_config,
+        admin_recently_modified,
+        admin_slaps_growth
+    ]
+}
+
+/// `GET` the ids of the guilds whose admin channel is `channel`.
+///
+/// This is meant to clean up after a channel was deleted on Discord.
+#[get("/admin/admin_channel/<channel>/guilds")]
+async fn admin_chan_guilds(
+    _admin: Admin,
+    pool: Pool<'_>,
+    channel: u64,
+) -> ApiResult<Negotiated<Vec<Snowflake>>> {
+    let channel = snowflake("channel", channel)?;
+    Ok(Negotiated(
+        pool.retry(|pool| db::guilds_with_admin_chan(pool, channel))
+            .await?
+            .into_iter()
+            .map(Snowflake)
+            .collect(),
+    ))
+}
+
+/// `GET` the ids of the `number` guilds whose configuration was modified last, most recent first.
+///
+/// Creating a guild counts as modifying it. `number` is bounded like any other page.
+#[get("/admin/guilds/recently_modified?<number>")]
+async fn admin_recently_modified(
+    _admin: Admin,
+    settings: &State<ApiConfig>,
+    pool: Pool<'_>,
+    number: OptionalCount<'_>,
+) -> ApiResult<Negotiated<Vec<Snowflake>>> {
+    let number = count("number", number)?;
+    let page = settings.page(number, None);
+    Ok(Negotiated(
+        pool.retry(|pool| db::recently_modified_guilds(pool, page.limit))
+            .await?
+            .into_iter()
+            .map(Snowflake)
+            .collect(),
+    ))
+}
+
+/// Most buckets a time series may hold.
+const MAX_BUCKETS: i32 = 1000;
+
+/// `GET` the number of slaps created across all guilds for every `bucket` (defaults to `day`)
+/// between `since` and `until` (defaults to now).
+///
+/// Each bucket also holds the total number of slaps up to its end, which is what the growth is
+/// usually plotted from. Pardoned slaps are left out of both unless `include_removed` (or
+/// `include_pardoned`), like for the counts of a guild. Buckets are laid out in UTC, since the
+/// guilds don't share a timezone.
+///
+/// # Errors
+///
+/// Fails with `400` if `since` is missing, if either bound isn't a valid timestamp, if `since` is
+/// after `until` or if the range spans more than [`MAX_BUCKETS`].
+#[get("/admin/slaps/growth?<since>&<until>&<bucket>&<include_pardoned>&<include_removed>")]
+async fn admin_slaps_growth(
+    _admin: Admin,
+    pool: Pool<'_>,
+    since: OptionalTimestamp<'_>,
+    until: OptionalTimestamp<'_>,
+    bucket: Option<Bucket>,
+    include_pardoned: Option<bool>,
+    include_removed: Option<bool>,
+) -> ApiResult<Negotiated<Vec<db::SlapBucket>>> {
+    let include_pardoned = include_removed.or(include_pardoned).unwrap_or(false);
+    let since = timestamp("since", since)?.ok_or(ApiError::InvalidTimestamp("since"))?;
+    let until = timestamp("until", until)?.unwrap_or_else(Utc::now);
+    let bucket = bucket.unwrap_or(Bucket::Day);
+    if since > until {
+        return Err(ApiError::InvalidTimeRange("`since` is after `until`"));
+    }
+    if until - since > bucket.min_duration() * MAX_BUCKETS {
+        return Err(ApiError::InvalidTimeRange("too many buckets"));
+    }
+
+    Ok(Negotiated(
+        pool.retry(|pool| {
+            db::slaps_per_bucket(pool, None, since, until, bucket, "UTC", include_pardoned)
+        })
+        .await?,
+    ))
+}
+
+/// Stands for the value of a secret in [`EffectiveConfig`].
+const REDACTED: &str = "[redacted]";
+
+/// Settings the linker runs with, see [`admin_config()`].
+#[derive(Debug, Serialize)]
+struct EffectiveConfig<'a> {
+    #[serde(flatten)]
+    api: &'a ApiConfig,
+    /// `SLAP_WEBHOOK_URL`, [`REDACTED`] since it may hold a token. `null` when unset.
+    slap_webhook_url: Option<&'static str>,
+}
+
+/// `GET` the [`ApiConfig`] the linker was started with, along with whether the secrets are set.
+///
+/// Secrets themselves are never answered, only [`REDACTED`] in their place.
+#[get("/admin/config")]
+fn admin_config<'r>(
+    _admin: Admin,
+    settings: &'r State<ApiConfig>,
+    webhook: &State<Webhook>,
+) -> Json<EffectiveConfig<'r>> {
+    Json(EffectiveConfig {
+        api: settings.inner(),
+        slap_webhook_url: if webhook.is_set() {
+            Some(REDACTED)
+        } else {
+            None
+        },
+    })
+}
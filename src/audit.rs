@@ -0,0 +1,47 @@
+//! Trail of the changes made through the linker.
+
+use crate::{auth::AdminToken, db::AuditEntry};
+use rocket::request::{FromRequest, Outcome, Request};
+use std::convert::Infallible;
+
+/// Request guard describing who is calling which endpoint, for the entries of the audit log.
+///
+/// The only actor told apart for now is `admin`, for the requests bearing the [`AdminToken`]. Other
+/// requests are recorded without an actor.
+#[derive(Debug)]
+pub struct Audit {
+    endpoint: String,
+    actor: Option<&'static str>,
+}
+
+impl Audit {
+    /// Entry recording `summary` of a change to `guild`.
+    pub fn entry(&self, guild: u64, summary: impl Into<String>) -> AuditEntry<'_> {
+        AuditEntry {
+            guild,
+            endpoint: &self.endpoint,
+            actor: self.actor,
+            summary: summary.into(),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Audit {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let endpoint = match request.route() {
+            Some(route) => format!("{} {}", route.method, route.uri),
+            None => format!("{} {}", request.method(), request.uri().path()),
+        };
+        Outcome::Success(Audit {
+            endpoint,
+            actor: if AdminToken::authorizes(request) {
+                Some("admin")
+            } else {
+                None
+            },
+        })
+    }
+}
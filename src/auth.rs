@@ -0,0 +1,67 @@
+//! Guards for the endpoints that must not be open to anyone.
+
+use crate::error::ApiError;
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome, Request},
+};
+use std::env;
+
+/// Token expected by admin endpoints in the `Authorization: Bearer <token>` header.
+///
+/// It is read from `ADMIN_TOKEN`. When unset admin endpoints refuse every request.
+pub struct AdminToken(Option<String>);
+
+impl AdminToken {
+    pub fn new(token: Option<String>) -> Self {
+        AdminToken(token)
+    }
+
+    pub fn from_env() -> Self {
+        AdminToken::new(env::var("ADMIN_TOKEN").ok())
+    }
+
+    /// Whether `request` bears the token. Never when it is unset.
+    pub fn authorizes(request: &Request<'_>) -> bool {
+        let expected = request
+            .rocket()
+            .state::<AdminToken>()
+            .and_then(|token| token.0.as_deref());
+        expected.is_some() && bearer(request) == expected
+    }
+}
+
+/// Token of the `Authorization: Bearer <token>` header, if any.
+fn bearer<'r>(request: &'r Request<'_>) -> Option<&'r str> {
+    request
+        .headers()
+        .get_one("Authorization")
+        .and_then(|header| header.strip_prefix("Bearer "))
+}
+
+/// Request guard only letting through requests bearing the [`AdminToken`].
+///
+/// Others fail with [`ApiError::Unauthorized`], or [`ApiError::AdminDisabled`] when the token is
+/// unset.
+pub struct Admin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let expected = match request
+            .rocket()
+            .state::<AdminToken>()
+            .and_then(|token| token.0.as_deref())
+        {
+            Some(expected) => expected,
+            None => return Outcome::Failure((Status::Forbidden, ApiError::AdminDisabled)),
+        };
+
+        match bearer(request) {
+            Some(token) if token == expected => Outcome::Success(Admin),
+            _ => Outcome::Failure((Status::Unauthorized, ApiError::Unauthorized)),
+        }
+    }
+}
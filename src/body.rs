@@ -0,0 +1,73 @@
+//! Request bodies accepted as JSON as well as forms.
+
+use crate::error::ApiError;
+use rocket::{
+    data::{self, Data, FromData},
+    form::{Form, FromForm},
+    http::Status,
+    outcome::Outcome,
+    request::Request,
+    serde::json::Json,
+};
+use serde::Deserialize;
+
+/// Body of `T`, be it sent as `application/json` or `application/x-www-form-urlencoded`.
+///
+/// Any other content type, or none, is answered with `415 Unsupported Media Type` by the
+/// [`ApiError::UnsupportedMediaType`] catcher rather than left for no route to match. Bodies which
+/// can't be parsed are answered with [`ApiError::InvalidBody`], see [`BodyRejected`].
+#[derive(Debug)]
+pub struct Body<T>(pub T);
+
+impl<T> std::ops::Deref for Body<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T> FromData<'r> for Body<T>
+where
+    T: FromForm<'r> + Deserialize<'r> + Send + 'r,
+{
+    type Error = ApiError;
+
+    async fn from_data(request: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let content_type = request.content_type();
+        if content_type.map_or(false, |content_type| content_type.is_json()) {
+            return match Json::<T>::from_data(request, data).await {
+                Outcome::Success(Json(body)) => Outcome::Success(Body(body)),
+                Outcome::Failure((status, err)) => reject(request, status, err.to_string()),
+                Outcome::Forward(data) => Outcome::Forward(data),
+            };
+        }
+        if content_type.map_or(false, |content_type| content_type.is_form()) {
+            return match Form::<T>::from_data(request, data).await {
+                Outcome::Success(form) => Outcome::Success(Body(form.into_inner())),
+                Outcome::Failure((status, errors)) => reject(request, status, errors.to_string()),
+                Outcome::Forward(data) => Outcome::Forward(data),
+            };
+        }
+        Outcome::Failure((
+            Status::UnsupportedMediaType,
+            ApiError::UnsupportedMediaType(content_type.map(ToString::to_string)),
+        ))
+    }
+}
+
+/// Why the [`Body`] guard failed to parse the body, cached for the `400` and `422` catchers since
+/// Rocket only passes them the status.
+#[derive(Debug, Clone)]
+pub struct BodyRejected(pub String);
+
+/// Fails with [`ApiError::InvalidBody`], caching its `reason` for the catchers.
+fn reject<'r, T>(
+    request: &'r Request<'_>,
+    status: Status,
+    reason: String,
+) -> data::Outcome<'r, T, ApiError> {
+    request.local_cache(|| Some(BodyRejected(reason.clone())));
+    Outcome::Failure((status, ApiError::InvalidBody(reason)))
+}
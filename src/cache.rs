@@ -0,0 +1,124 @@
+//! Caching of the responses: conditional requests, so that clients can cheaply check whether what
+//! they cached is still valid, and the `Cache-Control` directives telling for how long it is.
+
+use crate::{config::ApiConfig, V1};
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{ContentType, Header, Method, Status},
+    request::Request,
+    response::{self, Responder, Response},
+};
+use serde::Serialize;
+use std::io::Cursor;
+
+/// JSON response bearing an `ETag` derived from its body.
+///
+/// When the request's `If-None-Match` holds that same tag the body is left out and the response is
+/// a `304 Not Modified` instead.
+#[derive(Debug)]
+pub struct Tagged<T>(pub T);
+
+/// Weak entity tag of `body`, its 64-bit FNV-1a hash.
+///
+/// Unlike the hashers of the standard library this one is fixed, so that tags stay valid across
+/// releases and between the instances of the linker. The tag is weak since the same body may be
+/// answered indented or compressed, which are equivalent but not byte for byte the same.
+pub fn etag(body: &str) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    let hash = body.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    });
+    format!("W/\"{:016x}\"", hash)
+}
+
+/// Whether `etag` is one of the tags of an `If-None-Match` header.
+fn matches(if_none_match: &str, etag: &str) -> bool {
+    // weak comparison, as mandated for `If-None-Match`: only the opaque tags are compared
+    let opaque = |tag: &str| tag.strip_prefix("W/").unwrap_or(tag).to_owned();
+    let etag = opaque(etag);
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || opaque(candidate) == etag)
+}
+
+impl<'r, 'o: 'r, T: Serialize> Responder<'r, 'o> for Tagged<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'o> {
+        let body = serde_json::to_string(&self.0).map_err(|err| {
+            log::error!("couldn't serialize a response: {}", err);
+            Status::InternalServerError
+        })?;
+        let etag = etag(&body);
+
+        let mut response = Response::build();
+        response.header(Header::new("ETag", etag.clone()));
+        if request
+            .headers()
+            .get("If-None-Match")
+            .any(|header| matches(header, &etag))
+        {
+            return response.status(Status::NotModified).ok();
+        }
+        response
+            .header(ContentType::JSON)
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
+
+/// Sets the `Cache-Control` of the responses lacking one, depending on the endpoint:
+/// - getters of the configuration of a guild (`/guild/<guild>/...`) may be reused for
+///   [`ApiConfig::cache_ttl`] seconds, since it rarely changes
+/// - other reads, such as listings, must be revalidated every time with `no-cache`
+/// - mutations, and any other method than `GET`, are never stored with `no-store`
+///
+/// Failed reads are revalidated too, so that a guild created right after being looked up for isn't
+/// missing for a while.
+pub struct CacheControl;
+
+/// `Cache-Control` of the responses to `request` answered with `status`.
+fn directive(request: &Request<'_>, status: Status, ttl: u64) -> String {
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        return "no-store".to_string();
+    }
+    let path = request.uri().path().as_str();
+    let mut segments = path
+        .strip_prefix(V1)
+        .unwrap_or(path)
+        .trim_start_matches('/')
+        .split('/');
+    let getter = match (segments.next(), segments.nth(1)) {
+        (Some("guild"), Some("audit")) => false,
+        (Some("guild"), _) => true,
+        _ => false,
+    };
+    let succeeded = status.class().is_success() || status == Status::NotModified;
+    if getter && succeeded {
+        format!("max-age={}", ttl)
+    } else {
+        "no-cache".to_string()
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for CacheControl {
+    fn info(&self) -> Info {
+        Info {
+            name: "Cache-Control",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if response.headers().contains("Cache-Control") {
+            return;
+        }
+        let ttl = request
+            .rocket()
+            .state::<ApiConfig>()
+            .map_or(ApiConfig::default().cache_ttl, |config| config.cache_ttl);
+        let directive = directive(request, response.status(), ttl);
+        response.set_header(Header::new("Cache-Control", directive));
+    }
+}
@@ -0,0 +1,130 @@
+//! Compression of the responses, for the clients accepting it.
+
+use crate::config::ApiConfig;
+use flate2::{
+    write::{DeflateEncoder, GzEncoder},
+    Compression,
+};
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::Header,
+    Request, Response,
+};
+use std::io::{self, Cursor, Write};
+
+/// Content coding the linker can compress responses with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+
+    fn compress(self, body: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            Encoding::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// The encoding to use given the `Accept-Encoding` headers, preferring gzip when both are accepted.
+fn negotiate<'a>(accept_encoding: impl Iterator<Item = &'a str>) -> Option<Encoding> {
+    let mut accepted = None;
+    for coding in accept_encoding.flat_map(|header| header.split(',')) {
+        let mut params = coding.split(';').map(str::trim);
+        let encoding = match params.next() {
+            Some(name) if name.eq_ignore_ascii_case("gzip") => Encoding::Gzip,
+            Some(name) if name.eq_ignore_ascii_case("deflate") => Encoding::Deflate,
+            _ => continue,
+        };
+        let refused = params.any(|param| {
+            param
+                .strip_prefix("q=")
+                .and_then(|q| q.parse::<f32>().ok())
+                .map_or(false, |q| q == 0.0)
+        });
+        if !refused && accepted != Some(Encoding::Gzip) {
+            accepted = Some(encoding);
+        }
+    }
+    accepted
+}
+
+/// Makes the `ETag` of `response` weak if it is strong, since the compressed body is equivalent to
+/// the uncompressed one but not the same bytes. Caches would otherwise mix up the two.
+fn weaken_etag(response: &mut Response<'_>) {
+    let strong = match response.headers().get_one("ETag") {
+        Some(etag) if !etag.starts_with("W/") => etag.to_string(),
+        _ => return,
+    };
+    response.set_header(Header::new("ETag", format!("W/{}", strong)));
+}
+
+/// Compresses the responses of at least [`ApiConfig::compression_threshold`] bytes whose size is
+/// known upfront.
+///
+/// Streamed responses (server-sent events, exports...) are left as is since compressing them would
+/// require buffering them whole.
+pub struct Compress;
+
+#[rocket::async_trait]
+impl Fairing for Compress {
+    fn info(&self) -> Info {
+        Info {
+            name: "Compression",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let threshold = request
+            .rocket()
+            .state::<ApiConfig>()
+            .map_or(ApiConfig::default().compression_threshold, |config| {
+                config.compression_threshold
+            });
+        if response.headers().contains("Content-Encoding") {
+            return;
+        }
+        match response.body().preset_size() {
+            Some(size) if size >= threshold => (),
+            _ => return,
+        }
+        // whether the response is compressed depends on the request from now on, so caches must
+        // tell them apart even when it isn't
+        response.adjoin_header(Header::new("Vary", "Accept-Encoding"));
+        let encoding = match negotiate(request.headers().get("Accept-Encoding")) {
+            Some(encoding) => encoding,
+            None => return,
+        };
+
+        let compressed = match response.body_mut().to_bytes().await {
+            Ok(body) => encoding.compress(&body),
+            Err(err) => Err(err),
+        };
+        match compressed {
+            Ok(body) => {
+                response.set_header(Header::new("Content-Encoding", encoding.name()));
+                weaken_etag(response);
+                response.set_sized_body(body.len(), Cursor::new(body));
+            }
+            Err(err) => log::error!("couldn't compress a response: {}", err),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Settings read from the environment at startup.
+//!
+//! Parsing is done through a `vars` lookup rather than [`std::env`] directly so that it can be
+//! tested without touching the process' environment.
+
+use crate::page::Page;
+use serde::{Deserialize, Serialize};
+use std::{env, str::FromStr, time::Duration};
+use thiserror;
+
+/// Setting found in the environment that couldn't be used.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("`{0}` must be set")]
+    Missing(&'static str),
+    #[error("`{var}` must be {expected}, found `{value}`")]
+    Invalid {
+        var: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+}
+
+/// Parses `var` if it is set, falling back to `default` otherwise.
+pub fn parse_var<T: FromStr>(
+    vars: &impl Fn(&str) -> Option<String>,
+    var: &'static str,
+    expected: &'static str,
+    default: T,
+) -> Result<T, ConfigError> {
+    match vars(var) {
+        Some(value) => value.parse().map_err(|_| ConfigError::Invalid {
+            var,
+            value,
+            expected,
+        }),
+        None => Ok(default),
+    }
+}
+
+/// How to connect to the database.
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    /// `DATABASE_URL`
+    pub url: String,
+    /// `DATABASE_MAX_CONNECTIONS`, 10 by default.
+    pub max_connections: u32,
+    /// `DATABASE_MIN_CONNECTIONS`, 0 by default.
+    pub min_connections: u32,
+    /// `DATABASE_ACQUIRE_TIMEOUT` in seconds, 30 by default.
+    pub acquire_timeout: Duration,
+    /// `DATABASE_MAX_QUEUE`, how many requests may wait for a connection. Unbounded by default.
+    pub max_queue: Option<u32>,
+    /// `DATABASE_RETRIES`, how many times reads failing transiently are retried. 2 by default.
+    pub retries: u32,
+    /// `DATABASE_RETRY_DELAY` in milliseconds, wait before the first retry. 50 by default.
+    pub retry_delay: Duration,
+    /// `DATABASE_BREAKER_THRESHOLD`, how many connections in a row may fail to be acquired before
+    /// requests are failed right away. 5 by default, 0 never fails them.
+    pub breaker_threshold: u32,
+    /// `DATABASE_BREAKER_COOLDOWN` in seconds, how long requests are failed right away for. 5 by
+    /// default.
+    pub breaker_cooldown: Duration,
+    /// `RUN_MIGRATIONS`, whether the schema is migrated at startup. Only when set to `1` or `true`,
+    /// startup fails if the schema is outdated otherwise.
+    pub run_migrations: bool,
+    /// `DATABASE_SCHEMA`, schema tables are looked up in before `public`. The search path of the
+    /// server by default.
+    pub schema: Option<String>,
+    /// `DATABASE_SELF_CHECK`, whether a query is run at startup to ensure the database answers. Unless
+    /// set to `0` or `false`.
+    pub self_check: bool,
+}
+
+impl PoolConfig {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_vars(|var| env::var(var).ok())
+    }
+
+    pub fn from_vars(vars: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        Ok(PoolConfig {
+            url: vars("DATABASE_URL").ok_or(ConfigError::Missing("DATABASE_URL"))?,
+            max_connections: parse_var(&vars, "DATABASE_MAX_CONNECTIONS", "a number", 10)?,
+            min_connections: parse_var(&vars, "DATABASE_MIN_CONNECTIONS", "a number", 0)?,
+            acquire_timeout: Duration::from_secs(parse_var(
+                &vars,
+                "DATABASE_ACQUIRE_TIMEOUT",
+                "a number of seconds",
+                30,
+            )?),
+            max_queue: vars("DATABASE_MAX_QUEUE")
+                .map(|_| parse_var(&vars, "DATABASE_MAX_QUEUE", "a number", 0))
+                .transpose()?,
+            retries: parse_var(&vars, "DATABASE_RETRIES", "a number", 2)?,
+            retry_delay: Duration::from_millis(parse_var(
+                &vars,
+                "DATABASE_RETRY_DELAY",
+                "a number of milliseconds",
+                50,
+            )?),
+            breaker_threshold: parse_var(&vars, "DATABASE_BREAKER_THRESHOLD", "a number", 5)?,
+            breaker_cooldown: Duration::from_secs(parse_var(
+                &vars,
+                "DATABASE_BREAKER_COOLDOWN",
+                "a number of seconds",
+                5,
+            )?),
+            run_migrations: vars("RUN_MIGRATIONS")
+                .map_or(false, |run| run == "1" || run.eq_ignore_ascii_case("true")),
+            schema: vars("DATABASE_SCHEMA"),
+            self_check: vars("DATABASE_SELF_CHECK").map_or(true, |check| {
+                check != "0" && !check.eq_ignore_ascii_case("false")
+            }),
+        })
+    }
+}
+
+/// Limits of the API.
+///
+/// Unlike [`PoolConfig`] these are part of rocket's configuration, so that they can be set through
+/// rocket's providers as well as `MAX_PAGE_SIZE`, `DEFAULT_PAGE_SIZE`, `MAX_MESSAGE_LEN`,
+/// `COMPRESSION_THRESHOLD`, `PRETTY_JSON`, `SLAP_RETENTION_DAYS`, `PRUNE_INTERVAL`, `CACHE_TTL`,
+/// `ADVERTISE_ENABLED`, `DEFAULT_WELCOME_MESSAGE`, `DEFAULT_GOODBYE_MESSAGE`, `MAX_SENTENCE` and
+/// `SECURITY_HEADERS`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Most items a listing answers at once, 1000 by default. Larger `number`s are clamped to it.
+    pub max_page_size: usize,
+    /// Items a listing answers when `number` is left out, 50 by default.
+    pub default_page_size: usize,
+    /// Most characters in a welcome or goodbye message, 2000 by default which is as much as a
+    /// Discord message can hold.
+    pub max_message_len: usize,
+    /// Fewest bytes in a response for it to be compressed, 1024 by default.
+    pub compression_threshold: usize,
+    /// Whether JSON responses are indented when requests don't tell, compact by default.
+    pub pretty_json: bool,
+    /// Days slaps are kept for before being pruned, forever by default.
+    pub slap_retention_days: Option<u32>,
+    /// Seconds between two prunings of the old slaps, an hour by default.
+    pub prune_interval: u64,
+    /// Seconds the configuration of a guild may be cached for, 30 by default.
+    pub cache_ttl: u64,
+    /// Whether guilds may advertise at all, true by default. When false every guild is answered as
+    /// not advertising, whatever its stored policy.
+    pub advertise_enabled: bool,
+    /// Welcome message of the guilds which didn't set one.
+    pub default_welcome_message: String,
+    /// Goodbye message of the guilds which didn't set one.
+    pub default_goodbye_message: String,
+    /// Largest sentence of a slap, as large as can be stored by default. Sentences of 0 are
+    /// allowed, for warnings.
+    pub max_sentence: u64,
+    /// Whether responses bear the usual security headers, true by default. Proxies setting their
+    /// own may disable them.
+    pub security_headers: bool,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        ApiConfig {
+            max_page_size: 1000,
+            default_page_size: 50,
+            max_message_len: 2000,
+            compression_threshold: 1024,
+            pretty_json: false,
+            slap_retention_days: None,
+            prune_interval: 3600,
+            cache_ttl: 30,
+            advertise_enabled: true,
+            default_welcome_message: "Welcome!".into(),
+            default_goodbye_message: "Goodbye!".into(),
+            max_sentence: i64::MAX as u64,
+            security_headers: true,
+        }
+    }
+}
+
+impl ApiConfig {
+    /// Variables of the environment the settings are read from.
+    pub const VARS: &'static [&'static str] = &[
+        "MAX_PAGE_SIZE",
+        "DEFAULT_PAGE_SIZE",
+        "MAX_MESSAGE_LEN",
+        "COMPRESSION_THRESHOLD",
+        "PRETTY_JSON",
+        "SLAP_RETENTION_DAYS",
+        "PRUNE_INTERVAL",
+        "CACHE_TTL",
+        "ADVERTISE_ENABLED",
+        "DEFAULT_WELCOME_MESSAGE",
+        "DEFAULT_GOODBYE_MESSAGE",
+        "MAX_SENTENCE",
+        "SECURITY_HEADERS",
+    ];
+
+    /// [`Page`] of `number` items (within the limits) from the `offset`.
+    pub fn page(&self, number: Option<usize>, offset: Option<usize>) -> Page {
+        Page::new(
+            number
+                .unwrap_or(self.default_page_size)
+                .min(self.max_page_size),
+            offset,
+        )
+    }
+
+    /// Largest sentence of a slap, which can't exceed what the database stores whatever
+    /// `max_sentence` is.
+    pub fn max_sentence(&self) -> u64 {
+        self.max_sentence.min(i64::MAX as u64)
+    }
+}
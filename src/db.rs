@@ -0,0 +1,1268 @@
+//! Queries the linker runs directly against the bot's database.
+//!
+//! `db-adapter` remains the owner of the schema and of the models. This module only holds the
+//! queries it doesn't provide (yet), written against the same tables.
+
+use crate::{
+    config::PoolConfig,
+    page::{Cursor, Page},
+    params::{Bucket, SlapOrder},
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use db_adapter::{guild::Privilege, PgPool};
+use serde::Serialize;
+use sqlx::{
+    postgres::{PgArguments, PgConnection, PgPoolOptions},
+    query::QueryAs,
+    Executor, Postgres,
+};
+use std::time::{Duration, Instant};
+use tokio_stream::Stream;
+
+/// Idempotent statements bringing the schema up to what the queries below expect.
+///
+/// Statements are only ever appended, so that how many were applied is the version of the schema.
+const SCHEMA: &[&str] = &[
+    "CREATE INDEX IF NOT EXISTS guilds_admin_chan_idx ON guilds (admin_chan)",
+    // slaps created before the column existed are all dated from the first startup
+    "ALTER TABLE slaps ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT now()",
+    "CREATE INDEX IF NOT EXISTS slaps_created_at_idx ON slaps (created_at)",
+    "ALTER TABLE guilds ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT now()",
+    "CREATE INDEX IF NOT EXISTS guilds_updated_at_idx ON guilds (updated_at)",
+    // db-adapter doesn't know about `updated_at` so it is kept up to date by the database itself
+    "CREATE OR REPLACE FUNCTION touch_updated_at() RETURNS TRIGGER AS $$
+    BEGIN
+        NEW.updated_at = now();
+        RETURN NEW;
+    END;
+    $$ LANGUAGE plpgsql",
+    "DROP TRIGGER IF EXISTS guilds_updated_at ON guilds",
+    "CREATE TRIGGER guilds_updated_at BEFORE UPDATE ON guilds
+    FOR EACH ROW EXECUTE PROCEDURE touch_updated_at()",
+    // append-only, entries are never updated nor deleted
+    "CREATE TABLE IF NOT EXISTS audit_log (
+        id BIGSERIAL PRIMARY KEY,
+        guild BIGINT NOT NULL,
+        endpoint TEXT NOT NULL,
+        actor TEXT,
+        summary TEXT NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    )",
+    "CREATE INDEX IF NOT EXISTS audit_log_guild_idx ON audit_log (guild, id)",
+    // pardoned slaps are kept, but left out of most listings and counts
+    "ALTER TABLE slaps ADD COLUMN IF NOT EXISTS pardoned_at TIMESTAMPTZ",
+    "ALTER TABLE slaps ADD COLUMN IF NOT EXISTS pardoned_by BIGINT",
+    "ALTER TABLE slaps ADD COLUMN IF NOT EXISTS pardon_reason TEXT",
+    // IANA name, guilds without one are in UTC
+    "ALTER TABLE guilds ADD COLUMN IF NOT EXISTS timezone TEXT",
+];
+
+/// Key of the advisory lock held while migrating.
+const MIGRATION_LOCK: i64 = 0x6c696e6b6572;
+
+/// Why the database couldn't be set up at startup.
+#[derive(Debug, thiserror::Error)]
+pub enum SetupError {
+    #[error("{0}")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("migrations couldn't be applied: {0}")]
+    Migration(#[source] sqlx::Error),
+    #[error(
+        "the schema is at version {found} while {expected} is expected, \
+        restart with `RUN_MIGRATIONS=1` to migrate it"
+    )]
+    Outdated { found: usize, expected: usize },
+    #[error("the database didn't answer: {0}")]
+    SelfCheck(#[source] sqlx::Error),
+}
+
+/// Connects to the database and runs the [`self_check`] if [`PoolConfig::self_check`], then
+/// [`prepare`]s it if [`PoolConfig::run_migrations`] or [`check`]s it's up to date otherwise.
+pub async fn connect(config: &PoolConfig) -> Result<PgPool, SetupError> {
+    let mut options = PgPoolOptions::new()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .connect_timeout(config.acquire_timeout);
+    if let Some(schema) = &config.schema {
+        let search_path = format!(
+            "SET search_path TO \"{}\", public",
+            schema.replace('"', "\"\"")
+        );
+        options = options.after_connect(move |conn| {
+            let search_path = search_path.clone();
+            Box::pin(async move {
+                conn.execute(search_path.as_str()).await?;
+                Ok(())
+            })
+        });
+    }
+    let pool = options.connect(&config.url).await?;
+    if config.self_check {
+        let elapsed = self_check(&pool).await.map_err(SetupError::SelfCheck)?;
+        log::info!("the database answered in {}ms", elapsed.as_millis());
+    }
+    if config.run_migrations {
+        prepare(&pool).await.map_err(SetupError::Migration)?;
+    } else {
+        check(&pool).await?;
+    }
+    Ok(pool)
+}
+
+/// Runs a trivial query, returning how long the database took to answer.
+///
+/// This also warms the pool up, so that the first requests don't wait for a connection.
+pub async fn self_check(pool: &PgPool) -> Result<Duration, sqlx::Error> {
+    let started = Instant::now();
+    sqlx::query("SELECT 1").execute(pool).await?;
+    Ok(started.elapsed())
+}
+
+/// Runs every statement of [`SCHEMA`] and records the version of the schema, all at once. Safe to
+/// call on every startup.
+pub async fn prepare(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    // linkers starting together migrate one after the other
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(MIGRATION_LOCK)
+        .execute(&mut tx)
+        .await?;
+    sqlx::query("CREATE TABLE IF NOT EXISTS linker_schema (version INTEGER NOT NULL)")
+        .execute(&mut tx)
+        .await?;
+    for statement in SCHEMA {
+        sqlx::query(statement).execute(&mut tx).await?;
+    }
+    sqlx::query("DELETE FROM linker_schema")
+        .execute(&mut tx)
+        .await?;
+    sqlx::query("INSERT INTO linker_schema (version) VALUES ($1)")
+        .bind(SCHEMA.len() as i32)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await
+}
+
+/// Ensures every statement of [`SCHEMA`] was applied, so that an outdated schema is caught at
+/// startup rather than by the queries.
+pub async fn check(pool: &PgPool) -> Result<(), SetupError> {
+    let migrated: bool = sqlx::query_scalar("SELECT to_regclass('linker_schema') IS NOT NULL")
+        .fetch_one(pool)
+        .await?;
+    let found = if migrated {
+        sqlx::query_scalar::<_, Option<i32>>("SELECT max(version) FROM linker_schema")
+            .fetch_one(pool)
+            .await?
+            .unwrap_or(0) as usize
+    } else {
+        0
+    };
+    if found == SCHEMA.len() {
+        Ok(())
+    } else {
+        Err(SetupError::Outdated {
+            found,
+            expected: SCHEMA.len(),
+        })
+    }
+}
+
+/// Ids of the guilds whose admin channel is `channel`, in ascending order.
+pub async fn guilds_with_admin_chan(pool: &PgPool, channel: u64) -> Result<Vec<u64>, sqlx::Error> {
+    Ok(
+        sqlx::query_scalar::<_, i64>("SELECT id FROM guilds WHERE admin_chan = $1 ORDER BY id")
+            .bind(channel as i64)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|id| id as u64)
+            .collect(),
+    )
+}
+
+/// Whether `guild` has an admin channel. Unknown guilds have none.
+pub async fn has_admin_chan(pool: &PgPool, guild: u64) -> Result<bool, sqlx::Error> {
+    Ok(
+        sqlx::query_scalar::<_, bool>("SELECT admin_chan IS NOT NULL FROM guilds WHERE id = $1")
+            .bind(guild as i64)
+            .fetch_optional(pool)
+            .await?
+            .unwrap_or(false),
+    )
+}
+
+/// Timezone of `guild`, if it has one. Unknown guilds have none.
+pub async fn timezone(pool: &PgPool, guild: u64) -> Result<Option<String>, sqlx::Error> {
+    Ok(
+        sqlx::query_scalar::<_, Option<String>>("SELECT timezone FROM guilds WHERE id = $1")
+            .bind(guild as i64)
+            .fetch_optional(pool)
+            .await?
+            .flatten(),
+    )
+}
+
+/// Sets the timezone of `guild`, or unsets it, returning whether the guild exists.
+pub async fn set_timezone(
+    pool: &PgPool,
+    guild: u64,
+    timezone: Option<&str>,
+    audit: &AuditEntry<'_>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let set = sqlx::query("UPDATE guilds SET timezone = $2 WHERE id = $1")
+        .bind(guild as i64)
+        .bind(timezone)
+        .execute(&mut tx)
+        .await?
+        .rows_affected()
+        > 0;
+    if set {
+        record_audit(&mut tx, audit).await?;
+        tx.commit().await?;
+    }
+    Ok(set)
+}
+
+/// Whole configuration of a guild, privileges aside, see [`replace_guild`] and [`create_guild`].
+#[derive(Debug, Clone, Copy)]
+pub struct GuildReplacement<'a> {
+    pub admin_chan: Option<u64>,
+    pub advertise: bool,
+    pub welcome_message: Option<&'a str>,
+    pub goodbye_message: Option<&'a str>,
+    /// IANA name of the timezone, see [`set_timezone`].
+    pub timezone: Option<&'a str>,
+}
+
+/// Replaces the configuration of `guild` in one statement, returning whether there is such a guild.
+pub async fn replace_guild(
+    pool: &PgPool,
+    guild: u64,
+    replacement: GuildReplacement<'_>,
+    audit: &AuditEntry<'_>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let replaced = sqlx::query(
+        "UPDATE guilds
+        SET admin_chan = $2, advertise = $3, welcome_message = $4, goodbye_message = $5,
+            timezone = $6
+        WHERE id = $1",
+    )
+    .bind(guild as i64)
+    .bind(replacement.admin_chan.map(|id| id as i64))
+    .bind(replacement.advertise)
+    .bind(replacement.welcome_message)
+    .bind(replacement.goodbye_message)
+    .bind(replacement.timezone)
+    .execute(&mut tx)
+    .await?
+    .rows_affected()
+        > 0;
+    if replaced {
+        record_audit(&mut tx, audit).await?;
+        tx.commit().await?;
+    }
+    Ok(replaced)
+}
+
+/// Creates `guild` with the configuration of `replacement` in one transaction along with its audit
+/// entry, returning whether it was created. Nothing is audited if it already existed.
+pub async fn create_guild(
+    pool: &PgPool,
+    guild: u64,
+    replacement: GuildReplacement<'_>,
+    audit: &AuditEntry<'_>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let created = sqlx::query(
+        "INSERT INTO guilds (id, admin_chan, advertise, welcome_message, goodbye_message, timezone)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (id) DO NOTHING",
+    )
+    .bind(guild as i64)
+    .bind(replacement.admin_chan.map(|id| id as i64))
+    .bind(replacement.advertise)
+    .bind(replacement.welcome_message)
+    .bind(replacement.goodbye_message)
+    .bind(replacement.timezone)
+    .execute(&mut tx)
+    .await?
+    .rows_affected()
+        > 0;
+    if created {
+        record_audit(&mut tx, audit).await?;
+        tx.commit().await?;
+    }
+    Ok(created)
+}
+
+/// What goes along with a guild when it is deleted, see [`delete_guild`].
+#[derive(Debug, Clone, Copy, Serialize, sqlx::FromRow)]
+pub struct GuildDeletion {
+    /// Slaps of the guild, pardoned ones included.
+    pub slaps: i64,
+    /// Privileges granted in the guild, one per role and privilege.
+    pub privileges: i64,
+}
+
+/// What deleting `guild` would delete, `None` if it wasn't created.
+pub async fn guild_deletion(
+    pool: &PgPool,
+    guild: u64,
+) -> Result<Option<GuildDeletion>, sqlx::Error> {
+    count_deletion(&mut *pool.acquire().await?, guild).await
+}
+
+/// Deletes `guild` along with all its slaps and privileges in one transaction, returning how many
+/// were deleted. `None` if it wasn't created, in which case nothing is audited.
+///
+/// The audit log of the guild is kept.
+pub async fn delete_guild(
+    pool: &PgPool,
+    guild: u64,
+    audit: &AuditEntry<'_>,
+) -> Result<Option<GuildDeletion>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    // only locks the row of the guild, so that nothing is granted to it meanwhile. Slaps aren't tied
+    // to that row and may still be created concurrently, hence counted as they are deleted rather
+    // than beforehand: those committed once the deletion ran survive it
+    let mut deletion = match count_deletion(&mut tx, guild).await? {
+        Some(deletion) => deletion,
+        None => return Ok(None),
+    };
+    deletion.slaps = sqlx::query("DELETE FROM slaps WHERE guild = $1")
+        .bind(guild as i64)
+        .execute(&mut tx)
+        .await?
+        .rows_affected() as i64;
+    sqlx::query("DELETE FROM guilds WHERE id = $1")
+        .bind(guild as i64)
+        .execute(&mut tx)
+        .await?;
+    record_audit(&mut tx, audit).await?;
+    tx.commit().await?;
+    Ok(Some(deletion))
+}
+
+/// [`GuildDeletion`] of `guild` through `conn`, whose row is locked until the end of the
+/// transaction if there is one.
+async fn count_deletion(
+    conn: &mut PgConnection,
+    guild: u64,
+) -> Result<Option<GuildDeletion>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT (SELECT count(*) FROM slaps WHERE guild = $1) AS slaps,
+            (coalesce(cardinality(priv_admin), 0) + coalesce(cardinality(priv_manager), 0)
+                + coalesce(cardinality(priv_event), 0))::BIGINT AS privileges
+        FROM guilds WHERE id = $1
+        FOR UPDATE",
+    )
+    .bind(guild as i64)
+    .fetch_optional(&mut *conn)
+    .await
+}
+
+/// Column of the `guilds` table holding the roles with `privilege`.
+fn privilege_column(privilege: &Privilege) -> &'static str {
+    match privilege {
+        Privilege::Admin => "priv_admin",
+        Privilege::Manager => "priv_manager",
+        Privilege::Event => "priv_event",
+    }
+}
+
+/// Whether `guild` was created, locking its row through `conn` until the end of the transaction so
+/// that it isn't deleted meanwhile.
+async fn lock_guild(conn: &mut PgConnection, guild: u64) -> Result<bool, sqlx::Error> {
+    Ok(sqlx::query("SELECT 1 FROM guilds WHERE id = $1 FOR UPDATE")
+        .bind(guild as i64)
+        .fetch_optional(&mut *conn)
+        .await?
+        .is_some())
+}
+
+/// Grants every one of `privileges` to `role` in `guild`, all at once or not at all, returning
+/// whether the guild exists. Nothing is audited otherwise.
+///
+/// Privileges the role already holds are left as is.
+pub async fn grant_privileges(
+    pool: &PgPool,
+    guild: u64,
+    role: u64,
+    privileges: &[Privilege],
+    audit: &AuditEntry<'_>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    if !lock_guild(&mut tx, guild).await? {
+        return Ok(false);
+    }
+    grant(&mut tx, guild, role, privileges).await?;
+    record_audit(&mut tx, audit).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Grants every one of the privileges paired with each role in `guild`, to all the roles at once or
+/// to none, returning whether the guild exists. Nothing is audited otherwise.
+pub async fn grant_privileges_to_roles(
+    pool: &PgPool,
+    guild: u64,
+    grants: &[(u64, Vec<Privilege>)],
+    audit: &[AuditEntry<'_>],
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    if !lock_guild(&mut tx, guild).await? {
+        return Ok(false);
+    }
+    for (role, privileges) in grants {
+        grant(&mut tx, guild, *role, privileges).await?;
+    }
+    for entry in audit {
+        record_audit(&mut tx, entry).await?;
+    }
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Grants `privileges` to `role` through `conn`, see [`grant_privileges`].
+async fn grant(
+    conn: &mut PgConnection,
+    guild: u64,
+    role: u64,
+    privileges: &[Privilege],
+) -> Result<(), sqlx::Error> {
+    for privilege in privileges {
+        // roles may be NULL rather than empty, for which `ANY` would be NULL too
+        sqlx::query(&format!(
+            "UPDATE guilds SET {0} = array_append(coalesce({0}, '{{}}'), $1)
+            WHERE id = $2 AND NOT $1 = ANY(coalesce({0}, '{{}}'))",
+            privilege_column(privilege)
+        ))
+        .bind(role as i64)
+        .bind(guild as i64)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Every privilege held by at least one of `roles` in `guild`. Unknown guilds grant none.
+pub async fn resolve_privileges(
+    pool: &PgPool,
+    guild: u64,
+    roles: &[u64],
+) -> Result<Vec<Privilege>, sqlx::Error> {
+    let held = sqlx::query_as::<_, (bool, bool, bool)>(
+        // a NULL column holds no role, rather than making the overlap NULL
+        "SELECT coalesce(priv_admin && $2, false), coalesce(priv_manager && $2, false),
+            coalesce(priv_event && $2, false)
+        FROM guilds WHERE id = $1",
+    )
+    .bind(guild as i64)
+    .bind(roles.iter().map(|&role| role as i64).collect::<Vec<_>>())
+    .fetch_optional(pool)
+    .await?;
+    Ok(match held {
+        Some((admin, manager, event)) => vec![
+            (admin, Privilege::Admin),
+            (manager, Privilege::Manager),
+            (event, Privilege::Event),
+        ]
+        .into_iter()
+        .filter_map(|(held, privilege)| if held { Some(privilege) } else { None })
+        .collect(),
+        None => Vec::new(),
+    })
+}
+
+/// Takes every privilege `role` holds in `guild` away from it, returning whether the guild exists.
+/// Nothing is audited otherwise.
+pub async fn clear_privileges(
+    pool: &PgPool,
+    guild: u64,
+    role: u64,
+    audit: &AuditEntry<'_>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    if !clear(&mut tx, guild, role).await? {
+        return Ok(false);
+    }
+    record_audit(&mut tx, audit).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Leaves `role` with exactly `privileges` in `guild`, taking away those it held but which aren't
+/// among them, all at once or not at all. Returns whether the guild exists, nothing is audited
+/// otherwise.
+pub async fn set_privileges(
+    pool: &PgPool,
+    guild: u64,
+    role: u64,
+    privileges: &[Privilege],
+    audit: &AuditEntry<'_>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    if !clear(&mut tx, guild, role).await? {
+        return Ok(false);
+    }
+    grant(&mut tx, guild, role, privileges).await?;
+    record_audit(&mut tx, audit).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Takes every privilege of `role` away through `conn`, see [`clear_privileges`]. Returns whether
+/// the guild exists.
+async fn clear(conn: &mut PgConnection, guild: u64, role: u64) -> Result<bool, sqlx::Error> {
+    Ok(sqlx::query(
+        "UPDATE guilds SET priv_admin = array_remove(priv_admin, $1),
+            priv_manager = array_remove(priv_manager, $1),
+            priv_event = array_remove(priv_event, $1)
+        WHERE id = $2",
+    )
+    .bind(role as i64)
+    .bind(guild as i64)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected()
+        > 0)
+}
+
+/// Ids of the guilds in the [`Page`], in ascending order, only those whose advertising policy is
+/// `advertise` if provided.
+pub async fn guilds(
+    pool: &PgPool,
+    advertise: Option<bool>,
+    page: Page,
+) -> Result<Vec<u64>, sqlx::Error> {
+    Ok(sqlx::query_scalar::<_, i64>(
+        "SELECT id FROM guilds WHERE ($1::BOOLEAN IS NULL OR advertise = $1)
+        ORDER BY id
+        LIMIT $2 OFFSET $3",
+    )
+    .bind(advertise)
+    .bind(to_bigint(page.limit))
+    .bind(to_bigint(page.offset))
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|id| id as u64)
+    .collect())
+}
+
+/// Number of guilds whose advertising policy is `advertise`, or of all guilds.
+pub async fn count_guilds(pool: &PgPool, advertise: Option<bool>) -> Result<u64, sqlx::Error> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT count(*) FROM guilds WHERE ($1::BOOLEAN IS NULL OR advertise = $1)",
+    )
+    .bind(advertise)
+    .fetch_one(pool)
+    .await?;
+    Ok(count as u64)
+}
+
+/// Ids of the `limit` guilds whose configuration was modified last, most recent first.
+pub async fn recently_modified_guilds(
+    pool: &PgPool,
+    limit: usize,
+) -> Result<Vec<u64>, sqlx::Error> {
+    Ok(sqlx::query_scalar::<_, i64>(
+        "SELECT id FROM guilds ORDER BY updated_at DESC, id DESC LIMIT $1",
+    )
+    .bind(to_bigint(limit))
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|id| id as u64)
+    .collect())
+}
+
+/// Number of slaps created during one time bucket.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SlapBucket {
+    /// Start of the bucket.
+    pub start: DateTime<Utc>,
+    /// Slaps created during the bucket.
+    pub count: i64,
+    /// Slaps created up to the end of the bucket, including those before the series starts.
+    pub total: i64,
+}
+
+/// Slaps of `guild` (or of every guild) created between `since` and `until`, grouped by `bucket`
+/// in `timezone` (a name postgres knows).
+///
+/// Every bucket of the range is present, those without any slap have a `count` of `0`. Pardoned
+/// slaps are left out unless `include_pardoned`.
+pub async fn slaps_per_bucket(
+    pool: &PgPool,
+    guild: Option<u64>,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    bucket: Bucket,
+    timezone: &str,
+    include_pardoned: bool,
+) -> Result<Vec<SlapBucket>, sqlx::Error> {
+    // buckets are laid out in local time, rather than in the time zone of the session, so that they
+    // start at midnight (or on Monday, or on the 1st) there whatever the offset of the day
+    sqlx::query_as(
+        "SELECT buckets.start, count(slaps.id) AS count,
+            ((SELECT count(*) FROM slaps WHERE created_at < $1 AND ($5::BIGINT IS NULL OR guild = $5)
+                AND ($6 OR pardoned_at IS NULL))
+                + sum(count(slaps.id)) OVER (ORDER BY buckets.start))::BIGINT AS total
+        FROM generate_series(
+            date_trunc($3, $1 AT TIME ZONE $7),
+            $2 AT TIME ZONE $7,
+            $4::INTERVAL
+        ) AS local(start)
+        CROSS JOIN LATERAL (
+            SELECT local.start AT TIME ZONE $7 AS start,
+                (local.start + $4::INTERVAL) AT TIME ZONE $7 AS stop
+        ) AS buckets
+        LEFT JOIN slaps ON slaps.created_at >= greatest(buckets.start, $1)
+            AND slaps.created_at < least(buckets.stop, $2)
+            AND ($5::BIGINT IS NULL OR slaps.guild = $5)
+            AND ($6 OR slaps.pardoned_at IS NULL)
+        GROUP BY buckets.start
+        ORDER BY buckets.start",
+    )
+    .bind(since)
+    .bind(until)
+    .bind(bucket.unit())
+    .bind(bucket.interval())
+    .bind(guild.map(|id| id as i64))
+    .bind(include_pardoned)
+    .bind(timezone)
+    .fetch_all(pool)
+    .await
+}
+
+/// Slaps of a guild created during one day, see [`slaps_per_day`].
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct DayCount {
+    /// Day in the timezone of the guild.
+    pub date: NaiveDate,
+    /// Slaps created during the day.
+    pub count: i64,
+    /// Sum of their sentences.
+    pub total_sentence: i64,
+}
+
+/// Slaps of `guild` created during each of the last `days` in `timezone` (a name postgres knows),
+/// today included, oldest first. Pardoned slaps are left out unless `include_pardoned`.
+///
+/// Every day is present, those without any slap have a `count` of `0`.
+pub async fn slaps_per_day(
+    pool: &PgPool,
+    guild: u64,
+    timezone: &str,
+    days: u32,
+    include_pardoned: bool,
+) -> Result<Vec<DayCount>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT days.date::DATE AS date, count(slaps.id) AS count,
+            coalesce(sum(slaps.sentence), 0)::BIGINT AS total_sentence
+        FROM generate_series(
+            date_trunc('day', now() AT TIME ZONE $2) - make_interval(days => $3 - 1),
+            date_trunc('day', now() AT TIME ZONE $2),
+            INTERVAL '1 day'
+        ) AS days(date)
+        LEFT JOIN slaps ON date_trunc('day', slaps.created_at AT TIME ZONE $2) = days.date
+            AND slaps.guild = $1
+            AND ($4 OR slaps.pardoned_at IS NULL)
+        GROUP BY days.date
+        ORDER BY days.date",
+    )
+    .bind(guild as i64)
+    .bind(timezone)
+    .bind(days as i32)
+    .bind(include_pardoned)
+    .fetch_all(pool)
+    .await
+}
+
+/// Whether the total sentence of `member` in `guild` is strictly greater than `threshold`.
+pub async fn member_exceeds(
+    pool: &PgPool,
+    guild: u64,
+    member: u64,
+    threshold: u64,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT coalesce(sum(sentence), 0) > $3 FROM slaps
+        WHERE guild = $1 AND offender = $2 AND pardoned_at IS NULL",
+    )
+    .bind(guild as i64)
+    .bind(member as i64)
+    .bind(threshold as i64)
+    .fetch_one(pool)
+    .await
+}
+
+/// Sum of the sentences of every slap of `member` in `guild`, but the pardoned ones.
+pub async fn member_sentence(pool: &PgPool, guild: u64, member: u64) -> Result<u64, sqlx::Error> {
+    let sentence: i64 = sqlx::query_scalar(
+        "SELECT coalesce(sum(sentence), 0)::BIGINT FROM slaps
+        WHERE guild = $1 AND offender = $2 AND pardoned_at IS NULL",
+    )
+    .bind(guild as i64)
+    .bind(member as i64)
+    .fetch_one(pool)
+    .await?;
+    Ok(sentence as u64)
+}
+
+/// Whether `member` has at least one slap in `guild` which wasn't pardoned.
+pub async fn member_active(pool: &PgPool, guild: u64, member: u64) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (
+            SELECT 1 FROM slaps WHERE guild = $1 AND offender = $2 AND pardoned_at IS NULL
+        )",
+    )
+    .bind(guild as i64)
+    .bind(member as i64)
+    .fetch_one(pool)
+    .await
+}
+
+/// Row of the `slaps` table.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SlapRow {
+    pub id: i64,
+    pub sentence: i64,
+    pub offender: i64,
+    pub enforcer: Option<i64>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub pardoned_at: Option<DateTime<Utc>>,
+    pub pardoned_by: Option<i64>,
+    pub pardon_reason: Option<String>,
+}
+
+/// Columns of [`SlapRow`] as a literal, for the queries which must be `'static` such as those of
+/// [`stream_slaps`]. Use [`SLAP_COLUMNS`] otherwise.
+macro_rules! slap_columns {
+    () => {
+        "id, sentence, offender, enforcer, reason, created_at, pardoned_at, pardoned_by, \
+        pardon_reason"
+    };
+}
+
+/// Columns of [`SlapRow`], to select them.
+const SLAP_COLUMNS: &str = slap_columns!();
+
+/// Slap about to be inserted, see [`insert_slap`].
+#[derive(Debug, Clone, Copy, Hash)]
+pub struct NewSlap<'a> {
+    pub guild: u64,
+    pub sentence: u64,
+    pub offender: u64,
+    /// Stored as `NULL` when `None`.
+    pub enforcer: Option<u64>,
+    pub reason: Option<&'a str>,
+}
+
+/// Inserts `slap`, returning the row it became.
+///
+/// The id of the report is drawn from the sequence of the table by the insertion itself, so that
+/// concurrent slaps never end up with the same one.
+pub async fn insert_slap<'e, E>(executor: E, slap: NewSlap<'_>) -> Result<SlapRow, sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let query = format!(
+        "INSERT INTO slaps (guild, sentence, offender, enforcer, reason)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING {}",
+        SLAP_COLUMNS
+    );
+    sqlx::query_as::<_, SlapRow>(&query)
+        .bind(slap.guild as i64)
+        .bind(slap.sentence as i64)
+        .bind(slap.offender as i64)
+        .bind(slap.enforcer.map(|id| id as i64))
+        .bind(slap.reason)
+        .fetch_one(executor)
+        .await
+}
+
+/// Inserts every one of `slaps` in one transaction, all of them or none.
+pub async fn insert_slaps(
+    pool: &PgPool,
+    slaps: &[NewSlap<'_>],
+    audit: &[AuditEntry<'_>],
+) -> Result<Vec<SlapRow>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut rows = Vec::with_capacity(slaps.len());
+    for slap in slaps {
+        rows.push(insert_slap(&mut tx, *slap).await?);
+    }
+    for entry in audit {
+        record_audit(&mut tx, entry).await?;
+    }
+    tx.commit().await?;
+    Ok(rows)
+}
+
+/// Inserts every one of `slaps`, created at the time they are paired with, in one transaction.
+/// Returns how many there were.
+pub async fn import_slaps(
+    pool: &PgPool,
+    slaps: &[(NewSlap<'_>, DateTime<Utc>)],
+    audit: &AuditEntry<'_>,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for (slap, created_at) in slaps {
+        sqlx::query(
+            "INSERT INTO slaps (guild, sentence, offender, enforcer, reason, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(slap.guild as i64)
+        .bind(slap.sentence as i64)
+        .bind(slap.offender as i64)
+        .bind(slap.enforcer.map(|id| id as i64))
+        .bind(slap.reason)
+        .bind(created_at)
+        .execute(&mut tx)
+        .await?;
+    }
+    record_audit(&mut tx, audit).await?;
+    tx.commit().await?;
+    Ok(slaps.len() as u64)
+}
+
+/// Restricts which slaps of a guild are listed. The default lets every slap through but the
+/// pardoned ones.
+#[derive(Debug, Default, Clone)]
+pub struct SlapFilter {
+    /// Only the slaps of this offender.
+    pub member: Option<u64>,
+    /// Only the slaps whose sentence is at least this.
+    pub min_sentence: Option<u64>,
+    /// Only the slaps whose sentence is at most this.
+    pub max_sentence: Option<u64>,
+    /// Only the slaps whose reason contains this, whatever the case.
+    pub reason: Option<String>,
+    /// Whether pardoned slaps are let through as well.
+    pub include_pardoned: bool,
+}
+
+/// Condition on the `slaps` table applying a [`SlapFilter`], see [`bind_filter`].
+const SLAP_FILTER: &str = "guild = $1
+    AND ($2::BIGINT IS NULL OR offender = $2)
+    AND sentence BETWEEN coalesce($3, 0) AND coalesce($4, $5)
+    AND ($6::TEXT IS NULL OR reason ILIKE $6)
+    AND ($7 OR pardoned_at IS NULL)";
+
+/// Binds the parameters of [`SLAP_FILTER`], which must be the first of the query.
+fn bind_filter<'q, O>(
+    query: QueryAs<'q, Postgres, O, PgArguments>,
+    guild: u64,
+    filter: &SlapFilter,
+) -> QueryAs<'q, Postgres, O, PgArguments> {
+    query
+        .bind(guild as i64)
+        .bind(filter.member.map(|id| id as i64))
+        .bind(filter.min_sentence.map(|sentence| sentence as i64))
+        .bind(filter.max_sentence.map(|sentence| sentence as i64))
+        .bind(i64::MAX)
+        .bind(filter.reason.as_deref().map(contains_pattern))
+        .bind(filter.include_pardoned)
+}
+
+/// `ILIKE` pattern matching the texts containing `text`, whose wildcards are matched literally.
+fn contains_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// The slaps of `guild` passing `filter` in the [`Page`], in the given `order`.
+///
+/// The page starts `after` the cursor if there is one, whose order must be `order`.
+pub async fn slaps(
+    pool: &PgPool,
+    guild: u64,
+    filter: &SlapFilter,
+    order: SlapOrder,
+    page: Page,
+    after: Option<&Cursor>,
+) -> Result<Vec<SlapRow>, sqlx::Error> {
+    // the clauses are a few constants, nothing from the request ends up in the query
+    let query = format!(
+        "SELECT {} FROM slaps WHERE {}
+        AND ($10::BIGINT IS NULL OR {})
+        ORDER BY {}
+        LIMIT $8 OFFSET $9",
+        SLAP_COLUMNS,
+        SLAP_FILTER,
+        order.after(),
+        order.order_by()
+    );
+    bind_filter(sqlx::query_as::<_, SlapRow>(&query), guild, filter)
+        .bind(to_bigint(page.limit))
+        .bind(to_bigint(page.offset))
+        .bind(after.map(|cursor| cursor.id))
+        .bind(after.map(|cursor| cursor.created_at))
+        .bind(after.map(|cursor| cursor.sentence))
+        .fetch_all(pool)
+        .await
+}
+
+/// The slap `report` of `guild`, if there is one.
+pub async fn slap(pool: &PgPool, guild: u64, report: i64) -> Result<Option<SlapRow>, sqlx::Error> {
+    let query = format!(
+        "SELECT {} FROM slaps WHERE guild = $1 AND id = $2",
+        SLAP_COLUMNS
+    );
+    sqlx::query_as::<_, SlapRow>(&query)
+        .bind(guild as i64)
+        .bind(report)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Whether `guild` has the slap `report`, without fetching it.
+pub async fn slap_exists(pool: &PgPool, guild: u64, report: i64) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM slaps WHERE guild = $1 AND id = $2)")
+        .bind(guild as i64)
+        .bind(report)
+        .fetch_one(pool)
+        .await
+}
+
+/// Why and by whom a slap is pardoned, see [`pardon_slap`].
+#[derive(Debug, Clone, Copy)]
+pub struct NewPardon<'a> {
+    /// Stored as `NULL` when `None`.
+    pub by: Option<u64>,
+    pub reason: &'a str,
+}
+
+/// Pardons the slap `report` of `guild`, returning the row it became.
+///
+/// Gives `None` if there is no such slap or if it was already pardoned, in which case nothing
+/// changes.
+pub async fn pardon_slap(
+    pool: &PgPool,
+    guild: u64,
+    report: i64,
+    pardon: NewPardon<'_>,
+    audit: &AuditEntry<'_>,
+) -> Result<Option<SlapRow>, sqlx::Error> {
+    let query = format!(
+        "UPDATE slaps SET pardoned_at = now(), pardoned_by = $3, pardon_reason = $4
+        WHERE guild = $1 AND id = $2 AND pardoned_at IS NULL
+        RETURNING {}",
+        SLAP_COLUMNS
+    );
+    let mut tx = pool.begin().await?;
+    let row = sqlx::query_as::<_, SlapRow>(&query)
+        .bind(guild as i64)
+        .bind(report)
+        .bind(pardon.by.map(|id| id as i64))
+        .bind(pardon.reason)
+        .fetch_optional(&mut tx)
+        .await?;
+    if row.is_some() {
+        record_audit(&mut tx, audit).await?;
+        tx.commit().await?;
+    }
+    Ok(row)
+}
+
+/// Every slap of `guild` in the order they were created, fetched as the stream is consumed.
+///
+/// Rows are read off the connection as they are polled, so nothing is buffered ahead of the consumer
+/// but what the socket holds.
+pub fn stream_slaps(
+    pool: &PgPool,
+    guild: u64,
+) -> impl Stream<Item = Result<SlapRow, sqlx::Error>> + '_ {
+    // the stream outlives this function, hence a literal rather than a formatted query
+    sqlx::query_as::<_, SlapRow>(concat!(
+        "SELECT ",
+        slap_columns!(),
+        " FROM slaps WHERE guild = $1 ORDER BY id"
+    ))
+    .bind(guild as i64)
+    .fetch(pool)
+}
+
+/// Deletes every slap of `guild` in one transaction, returning how many there were.
+pub async fn clear_slaps(
+    pool: &PgPool,
+    guild: u64,
+    audit: &AuditEntry<'_>,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let deleted = sqlx::query("DELETE FROM slaps WHERE guild = $1")
+        .bind(guild as i64)
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+    record_audit(&mut tx, audit).await?;
+    tx.commit().await?;
+    Ok(deleted)
+}
+
+/// Reassigns every slap of `from` in `guild` to `to`, returning how many there were. `None` if the
+/// guild wasn't created, in which case nothing is audited.
+pub async fn transfer_slaps(
+    pool: &PgPool,
+    guild: u64,
+    from: u64,
+    to: u64,
+    audit: &AuditEntry<'_>,
+) -> Result<Option<u64>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    if !lock_guild(&mut tx, guild).await? {
+        return Ok(None);
+    }
+    let moved = sqlx::query("UPDATE slaps SET offender = $3 WHERE guild = $1 AND offender = $2")
+        .bind(guild as i64)
+        .bind(from as i64)
+        .bind(to as i64)
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+    record_audit(&mut tx, audit).await?;
+    tx.commit().await?;
+    Ok(Some(moved))
+}
+
+/// Changes the enforcer of every slap `from` gave in `guild` to `to`, returning how many there were.
+/// `None` if the guild wasn't created, in which case nothing is audited.
+pub async fn reassign_enforcer(
+    pool: &PgPool,
+    guild: u64,
+    from: u64,
+    to: u64,
+    audit: &AuditEntry<'_>,
+) -> Result<Option<u64>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    if !lock_guild(&mut tx, guild).await? {
+        return Ok(None);
+    }
+    let changed = sqlx::query("UPDATE slaps SET enforcer = $3 WHERE guild = $1 AND enforcer = $2")
+        .bind(guild as i64)
+        .bind(from as i64)
+        .bind(to as i64)
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+    record_audit(&mut tx, audit).await?;
+    tx.commit().await?;
+    Ok(Some(changed))
+}
+
+/// Deletes the slaps of every guild created before `before`, returning how many there were.
+pub async fn prune_slaps(pool: &PgPool, before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+    Ok(sqlx::query("DELETE FROM slaps WHERE created_at < $1")
+        .bind(before)
+        .execute(pool)
+        .await?
+        .rows_affected())
+}
+
+/// Ids of the members of `guild` slapped at least once in the [`Page`], in ascending order.
+/// Pardoned slaps don't count.
+pub async fn offenders(pool: &PgPool, guild: u64, page: Page) -> Result<Vec<u64>, sqlx::Error> {
+    Ok(sqlx::query_scalar::<_, i64>(
+        "SELECT DISTINCT offender FROM slaps WHERE guild = $1 AND pardoned_at IS NULL
+        ORDER BY offender
+        LIMIT $2 OFFSET $3",
+    )
+    .bind(guild as i64)
+    .bind(to_bigint(page.limit))
+    .bind(to_bigint(page.offset))
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|id| id as u64)
+    .collect())
+}
+
+/// Slaps of an offender of a guild, summed up.
+#[derive(Debug, sqlx::FromRow)]
+pub struct OffenderRow {
+    pub offender: i64,
+    pub slap_count: i64,
+    pub total_sentence: i64,
+    pub last_slap_at: DateTime<Utc>,
+}
+
+/// [`OffenderRow`]s of the members of `guild` slapped at least once in the [`Page`], ordered like
+/// [`offenders`].
+pub async fn offender_rows(
+    pool: &PgPool,
+    guild: u64,
+    page: Page,
+) -> Result<Vec<OffenderRow>, sqlx::Error> {
+    sqlx::query_as::<_, OffenderRow>(
+        "SELECT offender, count(*) AS slap_count, sum(sentence)::BIGINT AS total_sentence,
+            max(created_at) AS last_slap_at
+        FROM slaps WHERE guild = $1 AND pardoned_at IS NULL
+        GROUP BY offender
+        ORDER BY offender
+        LIMIT $2 OFFSET $3",
+    )
+    .bind(guild as i64)
+    .bind(to_bigint(page.limit))
+    .bind(to_bigint(page.offset))
+    .fetch_all(pool)
+    .await
+}
+
+/// The `limit` members of `guild` who slapped the most along with how many slaps they gave, most
+/// first. Members who gave as many slaps are ordered by id.
+///
+/// Slaps without an enforcer aren't anyone's, they are left out along with the pardoned ones.
+pub async fn top_enforcers(
+    pool: &PgPool,
+    guild: u64,
+    limit: usize,
+) -> Result<Vec<(u64, u64)>, sqlx::Error> {
+    Ok(sqlx::query_as::<_, (i64, i64)>(
+        "SELECT enforcer, count(*) FROM slaps
+        WHERE guild = $1 AND enforcer IS NOT NULL AND pardoned_at IS NULL
+        GROUP BY enforcer
+        ORDER BY count(*) DESC, enforcer
+        LIMIT $2",
+    )
+    .bind(guild as i64)
+    .bind(to_bigint(limit))
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(enforcer, count)| (enforcer as u64, count as u64))
+    .collect())
+}
+
+/// Number of slaps of `guild` created from `since` (inclusive) until `until` (exclusive), but the
+/// pardoned ones unless `include_pardoned`.
+///
+/// Either bound may be left out to count from the first slap or up to now.
+pub async fn count_slaps_between(
+    pool: &PgPool,
+    guild: u64,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    include_pardoned: bool,
+) -> Result<u64, sqlx::Error> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT count(*) FROM slaps WHERE guild = $1 AND ($4 OR pardoned_at IS NULL)
+            AND ($2::TIMESTAMPTZ IS NULL OR created_at >= $2)
+            AND ($3::TIMESTAMPTZ IS NULL OR created_at < $3)",
+    )
+    .bind(guild as i64)
+    .bind(since)
+    .bind(until)
+    .bind(include_pardoned)
+    .fetch_one(pool)
+    .await?;
+    Ok(count as u64)
+}
+
+/// Number of slaps of `guild` created in the last `minutes`, but the pardoned ones unless
+/// `include_pardoned`.
+pub async fn count_recent_slaps(
+    pool: &PgPool,
+    guild: u64,
+    minutes: u32,
+    include_pardoned: bool,
+) -> Result<u64, sqlx::Error> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT count(*) FROM slaps WHERE guild = $1 AND ($3 OR pardoned_at IS NULL)
+            AND created_at > now() - make_interval(mins => $2)",
+    )
+    .bind(guild as i64)
+    .bind(minutes as i32)
+    .bind(include_pardoned)
+    .fetch_one(pool)
+    .await?;
+    Ok(count as u64)
+}
+
+/// Number of slaps of `guild` passing `filter`.
+pub async fn count_slaps(
+    pool: &PgPool,
+    guild: u64,
+    filter: &SlapFilter,
+) -> Result<u64, sqlx::Error> {
+    let query = format!("SELECT count(*) FROM slaps WHERE {}", SLAP_FILTER);
+    let (count,) = bind_filter(sqlx::query_as::<_, (i64,)>(&query), guild, filter)
+        .fetch_one(pool)
+        .await?;
+    Ok(count as u64)
+}
+
+/// Saturating conversion of a number of rows to postgres' `BIGINT`.
+fn to_bigint(rows: usize) -> i64 {
+    rows.min(i64::MAX as usize) as i64
+}
+
+/// Change to record in the audit log, see [`crate::audit::Audit`].
+#[derive(Debug)]
+pub struct AuditEntry<'a> {
+    pub guild: u64,
+    /// Method and route of the request making the change, such as `POST /guild/<guild>/advertise`.
+    pub endpoint: &'a str,
+    pub actor: Option<&'a str>,
+    pub summary: String,
+}
+
+/// Appends `entry` to the audit log. Run it in the transaction of the change whenever there is one.
+pub async fn record_audit<'e, E: Executor<'e, Database = Postgres>>(
+    executor: E,
+    entry: &AuditEntry<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO audit_log (guild, endpoint, actor, summary) VALUES ($1, $2, $3, $4)")
+        .bind(entry.guild as i64)
+        .bind(entry.endpoint)
+        .bind(entry.actor)
+        .bind(&entry.summary)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Entry of the audit log, as answered to clients.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AuditRecord {
+    pub id: i64,
+    pub endpoint: String,
+    pub actor: Option<String>,
+    pub summary: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Entries of the audit log of `guild` in the [`Page`], most recent first.
+pub async fn audit_log(
+    pool: &PgPool,
+    guild: u64,
+    page: Page,
+) -> Result<Vec<AuditRecord>, sqlx::Error> {
+    sqlx::query_as::<_, AuditRecord>(
+        "SELECT id, endpoint, actor, summary, created_at FROM audit_log WHERE guild = $1
+        ORDER BY id DESC
+        LIMIT $2 OFFSET $3",
+    )
+    .bind(guild as i64)
+    .bind(to_bigint(page.limit))
+    .bind(to_bigint(page.offset))
+    .fetch_all(pool)
+    .await
+}
+
+/// Number of entries in the audit log of `guild`.
+pub async fn count_audit(pool: &PgPool, guild: u64) -> Result<u64, sqlx::Error> {
+    let count: i64 = sqlx::query_scalar("SELECT count(*) FROM audit_log WHERE guild = $1")
+        .bind(guild as i64)
+        .fetch_one(pool)
+        .await?;
+    Ok(count as u64)
+}
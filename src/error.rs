@@ -0,0 +1,355 @@
+//! Errors of the API and how they are rendered.
+//!
+//! Every error is answered with a JSON body of the form `{"type": ..., "reason": ...}` where
+//! `type` is either:
+//! - `validation`: the request itself is at fault (4xx). Retrying it as is won't help.
+//! - `internal`: something went wrong on our side (5xx). The request may be retried later.
+//!
+//! Errors about a resource, such as a guild which already exists or a report which doesn't, also give
+//! its `id`. Those about a missing one give its kind as `resource` too.
+//!
+//! Bodies whose fields are all checked at once are answered with every invalid field in `fields`,
+//! each with its `field` and `error`.
+//!
+//! Errors after which the request can be retried as is, once some time passed, bear a
+//! `Retry-After` header telling how many seconds to wait.
+
+use crate::idempotency::KeyReused;
+use db_adapter::{guild::GuildConfigError, AdapterError};
+use rocket::{
+    data::ByteUnit,
+    http::{ContentType, Header, Status},
+    request::Request,
+    response::{self, Responder, Response},
+};
+use serde::Serialize;
+use std::io::Cursor;
+use thiserror;
+
+/// Wrapper around [`AdapterError`]
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("We couldn't process your request: {reason}. Error: {source}")]
+    AdapterError {
+        status: Status,
+        reason: String,
+        #[source]
+        source: AdapterError,
+    },
+    #[error("expected on of: `admin`, `event` or `manager` found {0}")]
+    UnrecognizedPrivilege(String),
+    #[error("invalid time range: {0}")]
+    InvalidTimeRange(&'static str),
+    #[error(
+        "invalid sentence range: `min_sentence` ({min}) is greater than `max_sentence` ({max})"
+    )]
+    InvalidSentenceRange { min: u64, max: u64 },
+    #[error("too many requests are waiting for the database, try again later")]
+    Saturated,
+    #[error("the report {0} was already pardoned")]
+    AlreadyPardoned(i64),
+    #[error("the `Idempotency-Key` `{0}` was already used for a different request")]
+    IdempotencyKeyReused(String),
+    #[error("there is no {resource} {id}")]
+    NotFound { resource: String, id: String },
+    #[error("`{field}` can't be longer than {max} characters")]
+    MessageTooLong { field: &'static str, max: usize },
+    #[error(
+        "the stored `{field}` predates the limit of {max} characters, it can be read but not set again"
+    )]
+    StoredMessageTooLong { field: &'static str, max: usize },
+    #[error("`sentence` can't be greater than {max}, found {sentence}")]
+    SentenceTooLarge { sentence: u64, max: u64 },
+    #[error("`{param}` must be a Discord id, found {id}")]
+    InvalidId { param: &'static str, id: u64 },
+    #[error("`{0}` must be an RFC 3339 timestamp, such as `2021-09-14T12:00:00Z`")]
+    InvalidTimestamp(&'static str),
+    #[error("`{0}` must be a non-negative integer")]
+    InvalidNumber(&'static str),
+    #[error("`{param}` can't be greater than {max} on this platform")]
+    NumberTooLarge { param: &'static str, max: usize },
+    #[error("`{param}` can't be greater than {max}")]
+    IntegerTooLarge { param: &'static str, max: u64 },
+    #[error(
+        "expected an IANA timezone such as `Europe/Paris`, or nothing to unset it, found `{0}`"
+    )]
+    InvalidTimezone(String),
+    #[error("expected a channel id, or nothing to unset it, found `{0}`")]
+    InvalidChannel(String),
+    #[error("unknown field `{field}`, expected some of: {}", .expected.join(", "))]
+    UnknownField {
+        field: String,
+        expected: &'static [&'static str],
+    },
+    #[error("`{0}` must be one of `true`, `false`, `1`, `0`, `yes` or `no`")]
+    InvalidFlag(&'static str),
+    #[error(
+        "invalid fields: {}",
+        .fields
+            .iter()
+            .map(|field| format!("`{}` ({})", field.field, field.error))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )]
+    ValidationError { fields: Vec<FieldError> },
+    #[error("`{0}` can't be empty")]
+    EmptyParam(&'static str),
+    #[error(
+        "invalid cursor, it must be the `next_cursor` of a previous page with the same `sort`"
+    )]
+    InvalidCursor,
+    #[error("invalid body: {0}")]
+    InvalidBody(String),
+    #[error("the request is malformed")]
+    Malformed,
+    #[error(
+        "unsupported content type {}, expected `application/json` or `application/x-www-form-urlencoded`",
+        .0.as_deref().map_or("(none)".to_owned(), |content_type| format!("`{}`", content_type))
+    )]
+    UnsupportedMediaType(Option<String>),
+    #[error("invalid CSV: {0}")]
+    InvalidCsv(String),
+    #[error("the body can't be larger than {0}")]
+    TooLarge(ByteUnit),
+    #[error("this endpoint is restricted to admins, who send `Authorization: Bearer <token>`")]
+    Unauthorized,
+    #[error("admin endpoints are disabled since no admin token is set")]
+    AdminDisabled,
+    #[error("too many requests, try again in {retry_after} seconds")]
+    RateLimited { retry_after: u64 },
+    #[error("the service is unavailable, try again in {retry_after} seconds")]
+    Unavailable { retry_after: u64 },
+}
+
+/// Error of one field of a body, see [`ApiError::ValidationError`].
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub error: String,
+}
+
+/// Errors of every field of a body, collected so that they are all answered at once rather than
+/// one request at a time.
+#[derive(Debug, Default)]
+pub struct Validation(Vec<FieldError>);
+
+impl Validation {
+    /// The value of `field` if `result` is valid, otherwise its error is kept for
+    /// [`Validation::finish`].
+    pub fn check<T>(&mut self, field: &'static str, result: ApiResult<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.0.push(FieldError {
+                    field,
+                    error: err.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    /// Fails with [`ApiError::ValidationError`] if any field was invalid.
+    pub fn finish(self) -> ApiResult<()> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::ValidationError { fields: self.0 })
+        }
+    }
+}
+
+/// Who is at fault for an [`ApiError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorKind {
+    Validation,
+    Internal,
+}
+
+/// Body of every error response.
+#[derive(Debug, Serialize)]
+struct ErrorBody<'a> {
+    #[serde(rename = "type")]
+    kind: ErrorKind,
+    reason: &'a str,
+    /// Kind of the missing resource, for [`ApiError::NotFound`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resource: Option<&'a str>,
+    /// Id of the resource the error is about, if there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    /// Every invalid field, for [`ApiError::ValidationError`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<&'a [FieldError]>,
+}
+
+impl ApiError {
+    /// [`ApiError::NotFound`] of the `resource` whose id is `id`, such as `not_found("guild", guild)`.
+    pub fn not_found(resource: impl Into<String>, id: impl ToString) -> Self {
+        ApiError::NotFound {
+            resource: resource.into(),
+            id: id.to_string(),
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::AdapterError { status, .. } => *status,
+            ApiError::UnrecognizedPrivilege(_)
+            | ApiError::InvalidTimeRange(_)
+            | ApiError::InvalidSentenceRange { .. }
+            | ApiError::InvalidId { .. }
+            | ApiError::InvalidTimestamp(_)
+            | ApiError::InvalidNumber(_)
+            | ApiError::NumberTooLarge { .. }
+            | ApiError::IntegerTooLarge { .. }
+            | ApiError::InvalidChannel(_)
+            | ApiError::InvalidFlag(_)
+            | ApiError::EmptyParam(_)
+            | ApiError::InvalidCsv(_)
+            | ApiError::InvalidCursor
+            | ApiError::InvalidBody(_)
+            | ApiError::Malformed
+            | ApiError::UnknownField { .. } => Status::BadRequest,
+            ApiError::MessageTooLong { .. }
+            | ApiError::StoredMessageTooLong { .. }
+            | ApiError::InvalidTimezone(_)
+            | ApiError::IdempotencyKeyReused(_)
+            | ApiError::ValidationError { .. }
+            | ApiError::SentenceTooLarge { .. } => Status::UnprocessableEntity,
+            ApiError::Unauthorized => Status::Unauthorized,
+            ApiError::AdminDisabled => Status::Forbidden,
+            ApiError::TooLarge(_) => Status::PayloadTooLarge,
+            ApiError::UnsupportedMediaType(_) => Status::UnsupportedMediaType,
+            ApiError::RateLimited { .. } => Status::TooManyRequests,
+            ApiError::AlreadyPardoned(_) => Status::Conflict,
+            ApiError::NotFound { .. } => Status::NotFound,
+            ApiError::Saturated | ApiError::Unavailable { .. } => Status::ServiceUnavailable,
+        }
+    }
+
+    /// Reason of the error as answered to the client.
+    pub fn reason(&self) -> String {
+        // database errors are kept to their summary, the details are none of the client's business
+        match self {
+            ApiError::AdapterError { reason, .. } => reason.clone(),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Seconds after which the request may be retried, sent as `Retry-After`.
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            ApiError::Saturated => Some(1),
+            ApiError::RateLimited { retry_after } | ApiError::Unavailable { retry_after } => {
+                Some(*retry_after)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the error is unrelated to the request, such that retrying it may succeed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ApiError::AdapterError {
+                source: AdapterError::SqlxError(err),
+                ..
+            } => match err {
+                sqlx::Error::Io(_) => true,
+                // serialization failures, deadlocks and connection exceptions
+                sqlx::Error::Database(err) => err.code().map_or(false, |code| {
+                    code == "40001" || code == "40P01" || code.starts_with("08")
+                }),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        if self.status().code < 500 {
+            ErrorKind::Validation
+        } else {
+            ErrorKind::Internal
+        }
+    }
+}
+
+impl<'a> From<AdapterError> for ApiError {
+    fn from(err: AdapterError) -> Self {
+        // no connection could be acquired in time, the pool is exhausted rather than broken
+        if let AdapterError::SqlxError(sqlx::Error::PoolTimedOut) = &err {
+            return ApiError::Unavailable { retry_after: 1 };
+        }
+        let (status, reason) = match &err {
+            AdapterError::SqlxError(_) => (
+                Status::InternalServerError,
+                "sqlx driver failed to query the database",
+            ),
+            AdapterError::GuildError(guild_error) => match guild_error {
+                GuildConfigError::AlreadyExists(_) => (Status::Conflict, "guild already exists"),
+                _ => (Status::UnprocessableEntity, "invalid guild configuration"),
+            },
+        };
+
+        ApiError::AdapterError {
+            status,
+            reason: reason.to_string(),
+            source: err,
+        }
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        AdapterError::SqlxError(err).into()
+    }
+}
+
+impl From<KeyReused> for ApiError {
+    fn from(KeyReused(key): KeyReused) -> Self {
+        ApiError::IdempotencyKeyReused(key)
+    }
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for ApiError {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'o> {
+        let reason = self.reason();
+        // ids are answered as strings, like snowflakes
+        let (resource, id) = match &self {
+            ApiError::AdapterError {
+                source: AdapterError::GuildError(GuildConfigError::AlreadyExists(id)),
+                ..
+            } => (None, Some(u64::from(*id).to_string())),
+            ApiError::NotFound { resource, id } => (Some(resource.as_str()), Some(id.clone())),
+            _ => (None, None),
+        };
+        let body = serde_json::to_string(&ErrorBody {
+            kind: self.kind(),
+            reason: &reason,
+            resource,
+            id,
+            fields: match &self {
+                ApiError::ValidationError { fields } => Some(fields.as_slice()),
+                _ => None,
+            },
+        })
+        .expect("error bodies are always serializable");
+
+        let mut response = Response::build();
+        if let Some(retry_after) = self.retry_after() {
+            response.header(Header::new("Retry-After", retry_after.to_string()));
+        }
+        if let ApiError::Unauthorized = self {
+            response.header(Header::new("WWW-Authenticate", "Bearer"));
+        }
+        response
+            .status(self.status())
+            .header(ContentType::JSON)
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
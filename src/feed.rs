@@ -0,0 +1,27 @@
+//! Live feed of the slaps created through the linker.
+
+use crate::slaps::Slap;
+use rocket::tokio::sync::broadcast::{self, Receiver, Sender};
+
+/// How many reports a subscriber may lag behind before it starts missing some.
+const CAPACITY: usize = 64;
+
+/// Broadcasts every new [`Slap`] along with the id of its guild.
+///
+/// Subscribers are just [`Receiver`]s so dropping one is all it takes to unsubscribe.
+pub struct SlapFeed(Sender<(u64, Slap)>);
+
+impl SlapFeed {
+    pub fn new() -> Self {
+        SlapFeed(broadcast::channel(CAPACITY).0)
+    }
+
+    pub fn publish(&self, guild: u64, report: Slap) {
+        // failing only means nobody is listening right now
+        let _ = self.0.send((guild, report));
+    }
+
+    pub fn subscribe(&self) -> Receiver<(u64, Slap)> {
+        self.0.subscribe()
+    }
+}
@@ -0,0 +1,97 @@
+//! Content negotiation of the listings, which can get large enough for JSON to be a burden, and
+//! indentation of JSON responses for those reading them by hand.
+
+use crate::config::ApiConfig;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{ContentType, MediaType, Status},
+    request::Request,
+    response::{self, Responder, Response},
+    serde::json::Json,
+};
+use serde::Serialize;
+use std::io::Cursor;
+
+/// Answers `T` as MessagePack when it is the preferred type of the `Accept` header, as JSON
+/// otherwise.
+///
+/// MessagePack maps keep the names of the fields so that both formats decode to the same data.
+#[derive(Debug)]
+pub struct Negotiated<T>(pub T);
+
+/// Whether `media_type` is one of the names MessagePack goes by.
+fn is_msgpack(media_type: &MediaType) -> bool {
+    media_type.top() == "application"
+        && (media_type.sub() == "msgpack" || media_type.sub() == "x-msgpack")
+}
+
+impl<'r, 'o: 'r, T: Serialize> Responder<'r, 'o> for Negotiated<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'o> {
+        let msgpack = request
+            .accept()
+            .map_or(false, |accept| is_msgpack(accept.preferred().media_type()));
+        if !msgpack {
+            return Json(self.0).respond_to(request);
+        }
+
+        let body = rmp_serde::to_vec_named(&self.0).map_err(|err| {
+            log::error!("couldn't serialize a response: {}", err);
+            Status::InternalServerError
+        })?;
+        Response::build()
+            .header(ContentType::new("application", "msgpack"))
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
+
+/// Whether the JSON answered to `request` should be indented, as asked by its `pretty` query
+/// parameter or by `default` if it has none. `?pretty=false` and `?pretty=0` opt out.
+fn wants_pretty(request: &Request<'_>, default: bool) -> bool {
+    request
+        .uri()
+        .query()
+        .and_then(|query| query.segments().find(|(name, _)| *name == "pretty"))
+        .map_or(default, |(_, value)| value != "false" && value != "0")
+}
+
+/// Indents JSON responses when [`wants_pretty`]. [`ApiConfig::pretty_json`] indents them by
+/// default.
+///
+/// Streamed responses are left as is.
+pub struct Pretty;
+
+#[rocket::async_trait]
+impl Fairing for Pretty {
+    fn info(&self) -> Info {
+        Info {
+            name: "Pretty JSON",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let default = request
+            .rocket()
+            .state::<ApiConfig>()
+            .map_or(false, |config| config.pretty_json);
+        if !wants_pretty(request, default)
+            || response.content_type() != Some(ContentType::JSON)
+            || response.body().preset_size().is_none()
+        {
+            return;
+        }
+
+        let body = match response.body_mut().to_bytes().await {
+            Ok(body) => body,
+            Err(err) => {
+                log::error!("couldn't read a response to indent it: {}", err);
+                return;
+            }
+        };
+        let body = serde_json::from_slice::<serde_json::Value>(&body)
+            .and_then(|json| serde_json::to_vec_pretty(&json))
+            .unwrap_or(body);
+        response.set_sized_body(body.len(), Cursor::new(body));
+    }
+}
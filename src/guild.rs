@@ -0,0 +1,1341 @@
+//! Endpoints about the configuration of guilds.
+
+use crate::{
+    audit::Audit,
+    auth::Admin,
+    body::Body,
+    cache::Tagged,
+    config::ApiConfig,
+    db::{self, AuditEntry},
+    error::{ApiError, ApiResult, Validation},
+    format::Negotiated,
+    id::Snowflake,
+    page::Paginated,
+    params::{self, count, flag, snowflake, Flag, FlagField, OptionalCount},
+    pool::Pool,
+};
+use chrono_tz::Tz;
+use db_adapter::guild::{GuildConfig, GuildConfigBuilder, Privilege};
+use rocket::{
+    delete, form::FromForm, get, http::Status, post, put, response::status::Created, routes,
+    serde::json::Json, Route, State,
+};
+use serde::{de::IntoDeserializer, Deserialize, Serialize};
+use serenity::model::id::RoleId;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        guilds,
+        guilds_count,
+        guild_settings,
+        guild_audit,
+        guild_admin_chan,
+        guild_admin_chan_configured,
+        guild_advertise,
+        guild_exists,
+        guild_goodbye_message,
+        guild_effective_goodbye_message,
+        guild_has_privileges,
+        guild_has_all_privileges,
+        guild_has_any_privilege,
+        guild_privileges,
+        guild_privileges_for,
+        guild_grant_privileges,
+        guild_grant_roles,
+        guild_clear_privileges,
+        guild_set_privileges,
+        guild_resolve_privileges,
+        guild_privileges_diff,
+        guild_roles_with,
+        guild_roles_with_all,
+        guild_roles_with_any,
+        guild_welcome_message,
+        guild_effective_welcome_message,
+        guild_timezone,
+        guild_have_privilege,
+        guild_new,
+        guild_put,
+        guild_set_config,
+        guild_delete,
+        guild_set_admin_chan,
+        guild_set_advertise,
+        guild_set_welcome_message,
+        guild_set_goodbye_message,
+        guild_set_timezone,
+    ]
+}
+
+/// `GET` up to `number` guild ids ([`Snowflake`]) in ascending order, skipping the first `offset` ones.
+///
+/// `number` defaults to [`ApiConfig::default_page_size`] and can't exceed
+/// [`ApiConfig::max_page_size`].
+///
+/// Only the guilds whose advertising policy is `advertise` are listed when it is provided. This is
+/// the stored policy, whether [`ApiConfig::advertise_enabled`] is on or not.
+#[get("/guilds?<advertise>&<number>&<offset>")]
+async fn guilds(
+    pool: Pool<'_>,
+    settings: &State<ApiConfig>,
+    advertise: Option<bool>,
+    number: OptionalCount<'_>,
+    offset: OptionalCount<'_>,
+) -> ApiResult<Negotiated<Paginated<Snowflake>>> {
+    let number = count("number", number)?;
+    let offset = count("offset", offset)?;
+    let page = settings.page(number, offset);
+    Ok(Negotiated(Paginated::new(
+        pool.retry(|pool| db::guilds(pool, advertise, page))
+            .await?
+            .into_iter()
+            .map(Snowflake)
+            .collect(),
+        pool.retry(|pool| db::count_guilds(pool, advertise)).await?,
+        page,
+    )))
+}
+
+/// `GET` how many guilds were created, only those whose advertising policy is `advertise` if it is
+/// provided.
+#[get("/guilds/count?<advertise>")]
+async fn guilds_count(pool: Pool<'_>, advertise: Option<bool>) -> ApiResult<Json<usize>> {
+    Ok(Json(
+        pool.retry(|pool| db::count_guilds(pool, advertise)).await? as usize,
+    ))
+}
+
+/// `GET` up to `number` entries of the audit log of the guild, most recent first, skipping the first
+/// `offset` ones.
+///
+/// Every change made to the guild through the linker has an entry, naming the endpoint it was made
+/// through, the actor which made it if known and a summary of the change.
+#[get("/guild/<guild>/audit?<number>&<offset>")]
+async fn guild_audit(
+    pool: Pool<'_>,
+    settings: &State<ApiConfig>,
+    guild: u64,
+    number: OptionalCount<'_>,
+    offset: OptionalCount<'_>,
+) -> ApiResult<Negotiated<Paginated<db::AuditRecord>>> {
+    let guild = snowflake("guild", guild)?;
+    let number = count("number", number)?;
+    let offset = count("offset", offset)?;
+    let page = settings.page(number, offset);
+    Ok(Negotiated(Paginated::new(
+        pool.retry(|pool| db::audit_log(pool, guild, page)).await?,
+        pool.retry(|pool| db::count_audit(pool, guild)).await?,
+        page,
+    )))
+}
+
+/// Whole configuration of a guild.
+#[derive(Debug, Serialize)]
+struct GuildSettings {
+    id: Snowflake,
+    admin_channel: Option<Snowflake>,
+    advertise: bool,
+    welcome_message: Option<String>,
+    goodbye_message: Option<String>,
+    /// Same as [`guild_timezone()`].
+    timezone: Option<String>,
+}
+
+/// `GET` the [`GuildSettings`] of the guild at once.
+///
+/// Like every getter of the configuration, this fails with `404` if the guild wasn't created.
+/// `advertise` is the same as that of [`guild_advertise()`].
+///
+/// The response bears an `ETag`. Sending it back in `If-None-Match` yields a bodyless
+/// `304 Not Modified` for as long as the configuration is unchanged. `HEAD` is supported as well.
+#[get("/guild/<guild>")]
+async fn guild_settings(
+    pool: Pool<'_>,
+    settings: &State<ApiConfig>,
+    guild: u64,
+) -> ApiResult<Tagged<GuildSettings>> {
+    let guild = snowflake("guild", guild)?;
+    ensure_exists(&pool, guild).await?;
+    let config = GuildConfig(guild.into());
+    let config = &config;
+    Ok(Tagged(GuildSettings {
+        id: Snowflake(guild),
+        admin_channel: pool
+            .retry(|pool| async move { config.get_admin_chan(pool).await })
+            .await?
+            .map(|chan_id| Snowflake(chan_id.into())),
+        advertise: pool
+            .retry(|pool| async move { config.get_advertise(pool).await })
+            .await?
+            && settings.advertise_enabled,
+        welcome_message: pool
+            .retry(|pool| async move { config.get_welcome_message(pool).await })
+            .await?,
+        goodbye_message: pool
+            .retry(|pool| async move { config.get_goodbye_message(pool).await })
+            .await?,
+        timezone: pool.retry(|pool| db::timezone(pool, guild)).await?,
+    }))
+}
+
+/// Ensures the guild was created, so that getters don't answer defaults for unknown guilds.
+async fn ensure_exists(pool: &Pool<'_>, guild: u64) -> ApiResult<()> {
+    let exists = pool
+        .retry(|pool| async move { GuildConfig(guild.into()).exists(pool).await })
+        .await?;
+    if exists {
+        Ok(())
+    } else {
+        Err(ApiError::not_found("guild", guild))
+    }
+}
+
+/// `GET` whether the guild was created. Unlike the other getters this never fails with `404`.
+#[get("/guild/<guild>/exists")]
+async fn guild_exists(pool: Pool<'_>, guild: u64) -> ApiResult<Json<bool>> {
+    let guild = snowflake("guild", guild)?;
+    Ok(Json(
+        pool.retry(|pool| async move { GuildConfig(guild.into()).exists(pool).await })
+            .await?,
+    ))
+}
+
+#[get("/guild/<guild>/admin_channel")]
+async fn guild_admin_chan(pool: Pool<'_>, guild: u64) -> ApiResult<Json<Option<Snowflake>>> {
+    let guild = snowflake("guild", guild)?;
+    ensure_exists(&pool, guild).await?;
+    Ok(Json(
+        pool.retry(|pool| async move { GuildConfig(guild.into()).get_admin_chan(pool).await })
+            .await?
+            .map(|chan_id| Snowflake(chan_id.into())),
+    ))
+}
+
+/// `GET` whether the guild has an admin channel, without the channel itself.
+#[get("/guild/<guild>/admin_channel/configured")]
+async fn guild_admin_chan_configured(pool: Pool<'_>, guild: u64) -> ApiResult<Json<bool>> {
+    let guild = snowflake("guild", guild)?;
+    ensure_exists(&pool, guild).await?;
+    Ok(Json(
+        pool.retry(|pool| db::has_admin_chan(pool, guild)).await?,
+    ))
+}
+
+/// `GET` whether the guild advertises, which is always `false` while [`ApiConfig::advertise_enabled`]
+/// is off. The stored policy is kept regardless.
+#[get("/guild/<guild>/advertise")]
+async fn guild_advertise(
+    pool: Pool<'_>,
+    settings: &State<ApiConfig>,
+    guild: u64,
+) -> ApiResult<Json<bool>> {
+    let guild = snowflake("guild", guild)?;
+    ensure_exists(&pool, guild).await?;
+    let advertise = pool
+        .retry(|pool| async move { GuildConfig(guild.into()).get_advertise(pool).await })
+        .await?;
+    Ok(Json(advertise && settings.advertise_enabled))
+}
+
+#[get("/guild/<guild>/goodbye_message")]
+async fn guild_goodbye_message(pool: Pool<'_>, guild: u64) -> ApiResult<Json<Option<String>>> {
+    let guild = snowflake("guild", guild)?;
+    ensure_exists(&pool, guild).await?;
+    Ok(Json(
+        pool.retry(|pool| async move { GuildConfig(guild.into()).get_goodbye_message(pool).await })
+            .await?,
+    ))
+}
+
+#[get("/guild/<guild>/welcome_message")]
+async fn guild_welcome_message(pool: Pool<'_>, guild: u64) -> ApiResult<Json<Option<String>>> {
+    let guild = snowflake("guild", guild)?;
+    ensure_exists(&pool, guild).await?;
+    Ok(Json(
+        pool.retry(|pool| async move { GuildConfig(guild.into()).get_welcome_message(pool).await })
+            .await?,
+    ))
+}
+
+/// `GET` the IANA timezone of the guild, `null` if it has none in which case it is in UTC.
+///
+/// Exports render their timestamps in this timezone.
+#[get("/guild/<guild>/timezone")]
+async fn guild_timezone(pool: Pool<'_>, guild: u64) -> ApiResult<Json<Option<String>>> {
+    let guild = snowflake("guild", guild)?;
+    ensure_exists(&pool, guild).await?;
+    Ok(Json(pool.retry(|pool| db::timezone(pool, guild)).await?))
+}
+
+/// `GET` the goodbye message of the guild, or [`ApiConfig::default_goodbye_message`] if it has
+/// none.
+#[get("/guild/<guild>/goodbye_message/effective")]
+async fn guild_effective_goodbye_message(
+    pool: Pool<'_>,
+    settings: &State<ApiConfig>,
+    guild: u64,
+) -> ApiResult<Json<String>> {
+    let Json(message) = guild_goodbye_message(pool, guild).await?;
+    Ok(Json(message.unwrap_or_else(|| {
+        settings.default_goodbye_message.clone()
+    })))
+}
+
+/// `GET` the welcome message of the guild, or [`ApiConfig::default_welcome_message`] if it has
+/// none.
+#[get("/guild/<guild>/welcome_message/effective")]
+async fn guild_effective_welcome_message(
+    pool: Pool<'_>,
+    settings: &State<ApiConfig>,
+    guild: u64,
+) -> ApiResult<Json<String>> {
+    let Json(message) = guild_welcome_message(pool, guild).await?;
+    Ok(Json(message.unwrap_or_else(|| {
+        settings.default_welcome_message.clone()
+    })))
+}
+
+/// Privileges held by a role.
+#[derive(Debug, Serialize)]
+struct RolePrivileges {
+    role: Snowflake,
+    privileges: Vec<PrivilegeName>,
+}
+
+/// `GET` the privileges of every role of the guild holding at least one, ordered by role id.
+#[get("/guild/<guild>/privileges")]
+async fn guild_privileges(
+    pool: Pool<'_>,
+    guild: u64,
+) -> ApiResult<Negotiated<Vec<RolePrivileges>>> {
+    let guild = snowflake("guild", guild)?;
+    Ok(Negotiated(
+        role_privileges(&pool, guild)
+            .await?
+            .into_iter()
+            .map(|(role, privileges)| RolePrivileges {
+                role: Snowflake(role),
+                privileges,
+            })
+            .collect(),
+    ))
+}
+
+/// The privileges of every role of `guild` holding at least one, by role id.
+async fn role_privileges(
+    pool: &Pool<'_>,
+    guild: u64,
+) -> ApiResult<BTreeMap<u64, Vec<PrivilegeName>>> {
+    let config = GuildConfig(guild.into());
+    let config = &config;
+    let mut roles = BTreeMap::<u64, Vec<PrivilegeName>>::new();
+    for name in PrivilegeName::ALL {
+        let holders = pool
+            .retry(|pool| async move { config.get_roles_with(pool, name.into()).await })
+            .await?;
+        for role in holders {
+            roles.entry(role.into()).or_default().push(name);
+        }
+    }
+    Ok(roles)
+}
+
+/// Privileges to grant to and revoke from a role to reach what it should hold.
+#[derive(Debug, Serialize)]
+struct PrivilegesDiff {
+    role: Snowflake,
+    add: Vec<PrivilegeName>,
+    remove: Vec<PrivilegeName>,
+}
+
+/// `POST` a JSON object mapping roles to the privileges they should hold, to get the
+/// [`PrivilegesDiff`] of every role whose privileges differ, ordered by role id.
+///
+/// Nothing is changed, this is meant to preview what granting and clearing privileges would do.
+/// Only the roles of the object are compared: a role missing from it is left out while a role
+/// mapped to `[]` should hold no privilege.
+///
+/// # Errors
+///
+/// Fails with `UnrecognizedPrivilege` if any of the privileges isn't one.
+#[post("/guild/<guild>/privileges/diff", data = "<desired>", format = "json")]
+async fn guild_privileges_diff(
+    pool: Pool<'_>,
+    guild: u64,
+    desired: Json<BTreeMap<Snowflake, Vec<String>>>,
+) -> ApiResult<Json<Vec<PrivilegesDiff>>> {
+    let guild = snowflake("guild", guild)?;
+    let mut wanted = Vec::with_capacity(desired.len());
+    for (role, privileges) in desired.iter() {
+        let role = snowflake("role", role.0)?;
+        let privileges = privileges
+            .iter()
+            .map(|name| str_to_priv(name).map(PrivilegeName::from))
+            .collect::<ApiResult<BTreeSet<PrivilegeName>>>()?;
+        wanted.push((role, privileges));
+    }
+    let held = role_privileges(&pool, guild).await?;
+
+    Ok(Json(
+        wanted
+            .into_iter()
+            .filter_map(|(role, wanted)| {
+                let held = held.get(&role).map_or(&[][..], Vec::as_slice);
+                let add: Vec<PrivilegeName> = wanted
+                    .iter()
+                    .filter(|privilege| !held.contains(privilege))
+                    .cloned()
+                    .collect();
+                let remove: Vec<PrivilegeName> = held
+                    .iter()
+                    .filter(|privilege| !wanted.contains(*privilege))
+                    .cloned()
+                    .collect();
+                if add.is_empty() && remove.is_empty() {
+                    None
+                } else {
+                    Some(PrivilegesDiff {
+                        role: Snowflake(role),
+                        add,
+                        remove,
+                    })
+                }
+            })
+            .collect(),
+    ))
+}
+
+#[get("/guild/<guild>/privileges/for_role/<role>")]
+async fn guild_privileges_for(
+    pool: Pool<'_>,
+    guild: u64,
+    role: u64,
+) -> ApiResult<Json<Vec<PrivilegeName>>> {
+    let guild = snowflake("guild", guild)?;
+    let role = snowflake("role", role)?;
+    let privs = pool
+        .retry(|pool| async move {
+            GuildConfig(guild.into())
+                .get_privileges_for(pool, role.into())
+                .await
+        })
+        .await?
+        .into_iter()
+        .map(PrivilegeName::from)
+        .collect();
+    Ok(Json(privs))
+}
+
+/// `POST` a JSON array of roles to get every privilege at least one of them holds, such as those of
+/// a member with all these roles.
+#[post("/guild/<guild>/privileges/resolve", data = "<roles>", format = "json")]
+async fn guild_resolve_privileges(
+    pool: Pool<'_>,
+    guild: u64,
+    roles: Json<Vec<Snowflake>>,
+) -> ApiResult<Json<Vec<PrivilegeName>>> {
+    let guild = snowflake("guild", guild)?;
+    let roles = roles
+        .iter()
+        .map(|role| snowflake("role", role.0))
+        .collect::<ApiResult<Vec<u64>>>()?;
+    let privileges = pool
+        .retry(|pool| db::resolve_privileges(pool, guild, &roles))
+        .await?;
+    Ok(Json(
+        privileges.into_iter().map(PrivilegeName::from).collect(),
+    ))
+}
+
+#[derive(Debug, FromForm, Deserialize)]
+struct PrivilegesForm {
+    privileges: Vec<String>,
+}
+
+/// `POST` several `privileges` to grant to `role` at once, as a form or a JSON object, returning
+/// every privilege it now holds.
+///
+/// # Errors
+///
+/// Fails with `UnrecognizedPrivilege` if any of the privileges isn't one, in which case none of them
+/// is granted, and with `404` if the guild wasn't created.
+#[post("/guild/<guild>/privileges/grant/<role>", data = "<form>")]
+async fn guild_grant_privileges(
+    pool: Pool<'_>,
+    audit: Audit,
+    guild: u64,
+    role: u64,
+    form: Body<PrivilegesForm>,
+) -> ApiResult<Json<Vec<PrivilegeName>>> {
+    let guild = snowflake("guild", guild)?;
+    let role = snowflake("role", role)?;
+    let privileges = form
+        .privileges
+        .iter()
+        .map(|string| str_to_priv(string))
+        .collect::<ApiResult<Vec<Privilege>>>()?;
+    let entry = audit.entry(
+        guild,
+        format!("granted {} to role {}", form.privileges.join(", "), role),
+    );
+    if !db::grant_privileges(pool.inner(), guild, role, &privileges, &entry).await? {
+        return Err(ApiError::not_found("guild", guild));
+    }
+    guild_privileges_for(pool, guild, role).await
+}
+
+/// Privileges to grant to a role, see [`guild_grant_roles()`].
+#[derive(Debug, Deserialize)]
+struct RoleGrant {
+    role: Snowflake,
+    privileges: Vec<String>,
+}
+
+/// Outcome of a [`RoleGrant`]: its `status` is `200` once granted, otherwise that of the `error`.
+#[derive(Debug, Serialize)]
+struct GrantOutcome {
+    role: Snowflake,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl GrantOutcome {
+    fn failed(grant: &RoleGrant, err: &ApiError) -> Self {
+        GrantOutcome {
+            role: grant.role,
+            status: err.status().code,
+            error: Some(err.reason()),
+        }
+    }
+}
+
+/// `POST` a JSON array of [`RoleGrant`]s to grant privileges to several roles at once, returning the
+/// [`GrantOutcome`] of each in the same order.
+///
+/// Either every grant is applied or none is. If `partial` however, the valid grants are applied
+/// while the others are answered with their error, be it because they are invalid or because the
+/// database failed to apply them.
+///
+/// # Errors
+///
+/// Unless `partial`, fails like [`guild_grant_privileges()`] if any role or privilege is invalid.
+/// Grants to a guild which wasn't created fail with `404`, every one of them if `partial`.
+#[post(
+    "/guild/<guild>/privileges/grant?<partial>",
+    data = "<grants>",
+    format = "json"
+)]
+async fn guild_grant_roles(
+    pool: Pool<'_>,
+    audit: Audit,
+    guild: u64,
+    partial: Option<bool>,
+    grants: Json<Vec<RoleGrant>>,
+) -> ApiResult<Json<Vec<GrantOutcome>>> {
+    let guild = snowflake("guild", guild)?;
+    let checked = grants
+        .iter()
+        .map(|grant| {
+            let role = snowflake("role", grant.role.0)?;
+            let privileges = grant
+                .privileges
+                .iter()
+                .map(|string| str_to_priv(string))
+                .collect::<ApiResult<Vec<Privilege>>>()?;
+            Ok((role, privileges))
+        })
+        .collect::<Vec<ApiResult<_>>>();
+    let entry = |role: u64, grant: &RoleGrant| {
+        audit.entry(
+            guild,
+            format!("granted {} to role {}", grant.privileges.join(", "), role),
+        )
+    };
+
+    if !partial.unwrap_or(false) {
+        let valid = checked.into_iter().collect::<ApiResult<Vec<_>>>()?;
+        let entries = valid
+            .iter()
+            .zip(grants.iter())
+            .map(|((role, _), grant)| entry(*role, grant))
+            .collect::<Vec<_>>();
+        if !db::grant_privileges_to_roles(pool.inner(), guild, &valid, &entries).await? {
+            return Err(ApiError::not_found("guild", guild));
+        }
+        return Ok(Json(
+            valid
+                .into_iter()
+                .map(|(role, _)| GrantOutcome {
+                    role: Snowflake(role),
+                    status: Status::Ok.code,
+                    error: None,
+                })
+                .collect(),
+        ));
+    }
+
+    let mut outcomes = Vec::with_capacity(grants.len());
+    for (checked, grant) in checked.into_iter().zip(grants.iter()) {
+        outcomes.push(match checked {
+            Ok((role, privileges)) => {
+                let record = entry(role, grant);
+                // the earlier grants are already applied, so a failure is only that of its role
+                match db::grant_privileges(pool.inner(), guild, role, &privileges, &record).await {
+                    Ok(true) => GrantOutcome {
+                        role: Snowflake(role),
+                        status: Status::Ok.code,
+                        error: None,
+                    },
+                    Ok(false) => GrantOutcome::failed(grant, &ApiError::not_found("guild", guild)),
+                    Err(err) => GrantOutcome::failed(grant, &err.into()),
+                }
+            }
+            Err(err) => GrantOutcome::failed(grant, &err),
+        });
+    }
+    Ok(Json(outcomes))
+}
+
+/// `PUT` a JSON array of the only privileges `role` should hold, returning those it now holds.
+///
+/// Privileges missing from the role are granted and those it held but which aren't listed are taken
+/// away, all at once. An empty array clears them like [`guild_clear_privileges()`].
+///
+/// # Errors
+///
+/// Fails with `UnrecognizedPrivilege` if any of the privileges isn't one, in which case the role is
+/// left as is, and with `404` if the guild wasn't created.
+#[put(
+    "/guild/<guild>/privileges/for_role/<role>",
+    data = "<privileges>",
+    format = "json"
+)]
+async fn guild_set_privileges(
+    pool: Pool<'_>,
+    audit: Audit,
+    guild: u64,
+    role: u64,
+    privileges: Json<Vec<String>>,
+) -> ApiResult<Json<Vec<PrivilegeName>>> {
+    let guild = snowflake("guild", guild)?;
+    let role = snowflake("role", role)?;
+    let parsed = privileges
+        .iter()
+        .map(|string| str_to_priv(string))
+        .collect::<ApiResult<Vec<Privilege>>>()?;
+    let summary = if privileges.is_empty() {
+        format!("cleared the privileges of role {}", role)
+    } else {
+        format!(
+            "set the privileges of role {} to {}",
+            role,
+            privileges.join(", ")
+        )
+    };
+    let entry = audit.entry(guild, summary);
+    if !db::set_privileges(pool.inner(), guild, role, &parsed, &entry).await? {
+        return Err(ApiError::not_found("guild", guild));
+    }
+    guild_privileges_for(pool, guild, role).await
+}
+
+/// `DELETE` every privilege of `role`, returning those it now holds (none).
+///
+/// # Errors
+///
+/// Fails with `404` if the guild wasn't created.
+#[delete("/guild/<guild>/privileges/for_role/<role>")]
+async fn guild_clear_privileges(
+    pool: Pool<'_>,
+    audit: Audit,
+    guild: u64,
+    role: u64,
+) -> ApiResult<Json<Vec<PrivilegeName>>> {
+    let guild = snowflake("guild", guild)?;
+    let role = snowflake("role", role)?;
+    let entry = audit.entry(guild, format!("cleared the privileges of role {}", role));
+    if !db::clear_privileges(pool.inner(), guild, role, &entry).await? {
+        return Err(ApiError::not_found("guild", guild));
+    }
+    guild_privileges_for(pool, guild, role).await
+}
+
+/// A [`Privilege`] as the API answers and accepts it: `admin`, `manager` or `event`.
+///
+/// db-adapter's type can't derive serde's traits from here, so this mirrors it instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivilegeName {
+    Admin,
+    Manager,
+    Event,
+}
+
+impl PrivilegeName {
+    /// Every privilege, in the order roles are listed with them.
+    pub const ALL: [PrivilegeName; 3] = [
+        PrivilegeName::Admin,
+        PrivilegeName::Manager,
+        PrivilegeName::Event,
+    ];
+}
+
+impl From<Privilege> for PrivilegeName {
+    fn from(privilege: Privilege) -> Self {
+        match privilege {
+            Privilege::Admin => PrivilegeName::Admin,
+            Privilege::Manager => PrivilegeName::Manager,
+            Privilege::Event => PrivilegeName::Event,
+        }
+    }
+}
+
+impl From<PrivilegeName> for Privilege {
+    fn from(name: PrivilegeName) -> Self {
+        match name {
+            PrivilegeName::Admin => Privilege::Admin,
+            PrivilegeName::Manager => Privilege::Manager,
+            PrivilegeName::Event => Privilege::Event,
+        }
+    }
+}
+
+/// The privilege named `src`, spelled as [`PrivilegeName`] serializes it.
+fn str_to_priv(src: &str) -> ApiResult<Privilege> {
+    PrivilegeName::deserialize(src.into_deserializer())
+        .map(Privilege::from)
+        .map_err(|_: serde::de::value::Error| ApiError::UnrecognizedPrivilege(src.into()))
+}
+
+#[get("/guild/<guild>/privileges/roles_with/<privilege_str>")]
+async fn guild_roles_with(
+    pool: Pool<'_>,
+    guild: u64,
+    privilege_str: &str,
+) -> ApiResult<Negotiated<Vec<Snowflake>>> {
+    let guild = snowflake("guild", guild)?;
+    let privilege = PrivilegeName::from(str_to_priv(privilege_str)?);
+    Ok(Negotiated(
+        pool.retry(|pool| async move {
+            GuildConfig(guild.into())
+                .get_roles_with(pool, privilege.into())
+                .await
+        })
+        .await?
+        .iter()
+        .map(|role| Snowflake(u64::from(*role)))
+        .collect::<Vec<Snowflake>>(),
+    ))
+}
+
+/// The roles of `guild` holding each of `privileges`, of which there must be at least one.
+async fn roles_with_each(
+    pool: &Pool<'_>,
+    guild: u64,
+    privileges: &[String],
+) -> ApiResult<Vec<BTreeSet<u64>>> {
+    if privileges.is_empty() {
+        return Err(ApiError::EmptyParam("privileges"));
+    }
+    let privileges = privileges
+        .iter()
+        .map(|privilege| str_to_priv(privilege).map(PrivilegeName::from))
+        .collect::<ApiResult<Vec<_>>>()?;
+    let config = GuildConfig(guild.into());
+    let config = &config;
+    let mut roles = Vec::with_capacity(privileges.len());
+    for privilege in privileges {
+        let holders = pool
+            .retry(|pool| async move { config.get_roles_with(pool, privilege.into()).await })
+            .await?;
+        roles.push(holders.into_iter().map(u64::from).collect());
+    }
+    Ok(roles)
+}
+
+/// `GET` the roles holding every one of `privileges`, in ascending order.
+///
+/// # Errors
+///
+/// Fails with `UnrecognizedPrivilege` if any of `privileges` isn't a privilege, and with `EmptyParam`
+/// if none is given.
+#[get("/guild/<guild>/privileges/roles_with_all?<privileges>")]
+async fn guild_roles_with_all(
+    pool: Pool<'_>,
+    guild: u64,
+    privileges: Vec<String>,
+) -> ApiResult<Negotiated<Vec<Snowflake>>> {
+    let guild = snowflake("guild", guild)?;
+    let mut roles = roles_with_each(&pool, guild, &privileges)
+        .await?
+        .into_iter();
+    let first = roles.next().unwrap_or_default();
+    let all = roles.fold(first, |all, holders| &all & &holders);
+    Ok(Negotiated(all.into_iter().map(Snowflake).collect()))
+}
+
+/// `GET` the roles holding at least one of `privileges`, in ascending order.
+///
+/// # Errors
+///
+/// Same as [`guild_roles_with_all()`].
+#[get("/guild/<guild>/privileges/roles_with_any?<privileges>")]
+async fn guild_roles_with_any(
+    pool: Pool<'_>,
+    guild: u64,
+    privileges: Vec<String>,
+) -> ApiResult<Negotiated<Vec<Snowflake>>> {
+    let guild = snowflake("guild", guild)?;
+    let any = roles_with_each(&pool, guild, &privileges)
+        .await?
+        .into_iter()
+        .flatten()
+        .collect::<BTreeSet<_>>();
+    Ok(Negotiated(any.into_iter().map(Snowflake).collect()))
+}
+
+/// `GET` whether `role` holds every one of `privileges_str`, which it does when none is given.
+///
+/// Unless `strict` is `false`, this fails with `UnrecognizedPrivilege` if any of them isn't a
+/// privilege. Otherwise those are skipped, such that asking only for unknown privileges checks the
+/// role against an empty set of privileges.
+#[get("/guild/<guild>/privileges/has_all/<role>?<privileges_str>&<strict>")]
+async fn guild_has_all_privileges(
+    pool: Pool<'_>,
+    guild: u64,
+    role: u64,
+    privileges_str: Vec<String>,
+    strict: Option<bool>,
+) -> ApiResult<Json<bool>> {
+    let guild = snowflake("guild", guild)?;
+    let role = snowflake("role", role)?;
+    let privileges = parse_privileges(&privileges_str, strict)?;
+    let privileges = privileges.as_slice();
+    Ok(Json(
+        pool.retry(|pool| async move {
+            GuildConfig(guild.into())
+                .has_privileges(pool, role.into(), privileges)
+                .await
+        })
+        .await?,
+    ))
+}
+
+/// `GET` whether `role` holds at least one of `privileges_str`, which it doesn't when none is
+/// given.
+///
+/// Unknown privileges are handled like in [`guild_has_all_privileges()`], except that asking only
+/// for unknown ones is always `false` when not `strict`.
+#[get("/guild/<guild>/privileges/has_any/<role>?<privileges_str>&<strict>")]
+async fn guild_has_any_privilege(
+    pool: Pool<'_>,
+    guild: u64,
+    role: u64,
+    privileges_str: Vec<String>,
+    strict: Option<bool>,
+) -> ApiResult<Json<bool>> {
+    let guild = snowflake("guild", guild)?;
+    let role = snowflake("role", role)?;
+    let wanted = parse_privileges(&privileges_str, strict)?;
+    let held = pool
+        .retry(|pool| async move {
+            GuildConfig(guild.into())
+                .get_privileges_for(pool, role.into())
+                .await
+        })
+        .await?
+        .into_iter()
+        .map(PrivilegeName::from)
+        .collect::<BTreeSet<_>>();
+    Ok(Json(wanted.into_iter().any(|privilege| {
+        held.contains(&PrivilegeName::from(privilege))
+    })))
+}
+
+/// `GET` whether `role` holds every one of `privileges_str`, kept as an alias of
+/// [`guild_has_all_privileges()`].
+#[get("/guild/<guild>/privileges/has/<role>?<privileges_str>&<strict>")]
+async fn guild_has_privileges(
+    pool: Pool<'_>,
+    guild: u64,
+    role: u64,
+    privileges_str: Vec<String>,
+    strict: Option<bool>,
+) -> ApiResult<Json<bool>> {
+    guild_has_all_privileges(pool, guild, role, privileges_str, strict).await
+}
+
+/// The privileges named `strings`, the unknown ones being skipped unless `strict` (the default).
+fn parse_privileges(strings: &[String], strict: Option<bool>) -> ApiResult<Vec<Privilege>> {
+    let strict = strict.unwrap_or(true);
+    let mut privileges = Vec::with_capacity(strings.len());
+    for string in strings {
+        match str_to_priv(string) {
+            Ok(privilege) => privileges.push(privilege),
+            Err(_) if !strict => (),
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(privileges)
+}
+
+/// `GET` whether at least one of `roles` holds `privilege_str`.
+///
+/// Unless `strict` is `false`, this fails with `UnrecognizedPrivilege` if it isn't a privilege.
+/// Otherwise the answer is `false` since no role can hold it.
+#[get("/guild/<guild>/privileges/have/<privilege_str>?<roles>&<strict>")]
+async fn guild_have_privilege(
+    pool: Pool<'_>,
+    guild: u64,
+    roles: Vec<u64>,
+    privilege_str: &str,
+    strict: Option<bool>,
+) -> ApiResult<Json<bool>> {
+    let guild = snowflake("guild", guild)?;
+    let roles = roles
+        .into_iter()
+        .map(|role| snowflake("roles", role).map(RoleId))
+        .collect::<ApiResult<Vec<RoleId>>>()?;
+    let strict = strict.unwrap_or(true);
+    let privilege = match str_to_priv(privilege_str) {
+        Ok(privilege) => PrivilegeName::from(privilege),
+        Err(_) if !strict => return Ok(Json(false)),
+        Err(err) => return Err(err),
+    };
+    let roles = roles.as_slice();
+    Ok(Json(
+        pool.retry(|pool| async move {
+            GuildConfig(guild.into())
+                .have_privilege(pool, roles, privilege.into())
+                .await
+        })
+        .await?,
+    ))
+}
+
+/// Ensures the `field` message isn't longer than [`ApiConfig::max_message_len`], before it reaches
+/// the database.
+fn check_message(config: &ApiConfig, field: &'static str, message: &str) -> ApiResult<()> {
+    if message.chars().count() > config.max_message_len {
+        return Err(ApiError::MessageTooLong {
+            field,
+            max: config.max_message_len,
+        });
+    }
+    Ok(())
+}
+
+/// Checks the welcome and goodbye messages of a body, if any, into `validation`.
+fn check_messages(
+    validation: &mut Validation,
+    settings: &ApiConfig,
+    welcome: Option<&str>,
+    goodbye: Option<&str>,
+) {
+    for (field, message) in [("welcome_message", welcome), ("goodbye_message", goodbye)] {
+        if let Some(message) = message {
+            validation.check(field, check_message(settings, field, message));
+        }
+    }
+}
+
+/// `err` of a `message` too long to be set, told apart when the message is the `stored` one.
+///
+/// Messages stored before [`ApiConfig::max_message_len`] was lowered are still answered as is, they
+/// merely can't be set again.
+fn predating(err: ApiError, message: &str, stored: Option<&str>) -> ApiError {
+    match err {
+        ApiError::MessageTooLong { field, max } if stored == Some(message) => {
+            ApiError::StoredMessageTooLong { field, max }
+        }
+        err => err,
+    }
+}
+
+#[derive(Debug, FromForm, Deserialize)]
+struct NewGuildForm<'r> {
+    id: Snowflake,
+    welcome_message: Option<String>,
+    goodbye_message: Option<String>,
+    #[serde(
+        deserialize_with = "params::flag_field",
+        default = "params::missing_flag"
+    )]
+    advertise: FlagField<'r>,
+}
+
+/// `POST` a new guild, as a form or a JSON object. `advertise` accepts every spelling of a
+/// [`crate::params::Flag`], or a JSON boolean.
+///
+/// Answers `201 Created` with the `Location` of the guild under the current version of the API,
+/// whichever version it was created through.
+///
+/// # Errors
+///
+/// Fails with `422` listing every invalid field if any is, and with `409 Conflict` if the guild
+/// already exists, whose `id` is given in the error.
+#[post("/guild/new", data = "<config>")]
+async fn guild_new<'a>(
+    pool: Pool<'_>,
+    audit: Audit,
+    settings: &State<ApiConfig>,
+    config: Body<NewGuildForm<'_>>,
+) -> ApiResult<Created<()>> {
+    //consider moving some of this code into an `TryFrom` impl and call `into_inner` instead
+    let mut validation = Validation::default();
+    let id = validation.check("id", snowflake("id", config.id.0));
+    let advertise = validation.check("advertise", flag("advertise", &config.advertise));
+    check_messages(
+        &mut validation,
+        settings,
+        config.welcome_message.as_deref(),
+        config.goodbye_message.as_deref(),
+    );
+    validation.finish()?;
+    // every field is valid once the validation is finished
+    let id = id.unwrap_or_default();
+
+    let mut builder = GuildConfigBuilder::new(id.into());
+    builder.advertise(advertise.unwrap_or_default());
+    if let Some(welcome) = &config.welcome_message {
+        builder.welcome_message(welcome.as_str())?;
+    }
+    if let Some(goodbye) = &config.goodbye_message {
+        builder.goodbye_message(goodbye.as_str())?;
+    }
+
+    GuildConfig::new(pool.inner(), builder).await?;
+    record(&pool, audit.entry(id, "created the guild")).await?;
+    Ok(Created::new(format!("{}/guild/{}", crate::V1, id)))
+}
+
+/// Records `entry` of a change made through db-adapter, which can't make it in a transaction of ours.
+async fn record(pool: &Pool<'_>, entry: AuditEntry<'_>) -> ApiResult<()> {
+    Ok(db::record_audit(pool.inner(), &entry).await?)
+}
+
+#[derive(Debug, FromForm, Deserialize)]
+struct GuildForm<'r> {
+    admin_channel: Option<Snowflake>,
+    welcome_message: Option<String>,
+    goodbye_message: Option<String>,
+    timezone: Option<String>,
+    #[serde(
+        deserialize_with = "params::flag_field",
+        default = "params::missing_flag"
+    )]
+    advertise: FlagField<'r>,
+}
+
+impl GuildForm<'_> {
+    /// The advertising policy, the admin channel and the timezone of the form, once every field was
+    /// checked.
+    ///
+    /// Fails with `422` listing every invalid field if any is.
+    fn check(&self, settings: &ApiConfig) -> ApiResult<(bool, Option<u64>, Option<Tz>)> {
+        let mut validation = Validation::default();
+        let advertise = validation.check("advertise", flag("advertise", &self.advertise));
+        let chan = validation.check(
+            "admin_channel",
+            self.admin_channel
+                .map(|chan| snowflake("admin_channel", chan.0))
+                .transpose(),
+        );
+        check_messages(
+            &mut validation,
+            settings,
+            self.welcome_message.as_deref(),
+            self.goodbye_message.as_deref(),
+        );
+        let timezone = validation.check("timezone", parse_timezone(self.timezone.as_deref()));
+        validation.finish()?;
+        // every field is valid once the validation is finished
+        Ok((
+            advertise.unwrap_or_default(),
+            chan.flatten(),
+            timezone.flatten(),
+        ))
+    }
+}
+
+/// `PUT` the whole configuration of the guild, as a form or a JSON object, creating it if needed.
+///
+/// Every field left out is unset, the timezone included, so that the configuration ends up as sent
+/// whether the guild existed or not. An existing configuration is replaced all at once, like
+/// [`guild_set_config()`]. Privileges are left as they are.
+///
+/// Answers `201 Created` if the guild was created, `200 OK` if its configuration was replaced.
+#[put("/guild/<guild>", data = "<config>")]
+async fn guild_put(
+    pool: Pool<'_>,
+    audit: Audit,
+    settings: &State<ApiConfig>,
+    guild: u64,
+    config: Body<GuildForm<'_>>,
+) -> ApiResult<Status> {
+    let guild = snowflake("guild", guild)?;
+    let (advertise, chan, timezone) = config.check(settings)?;
+    let replacement = db::GuildReplacement {
+        admin_chan: chan,
+        advertise,
+        welcome_message: config.welcome_message.as_deref(),
+        goodbye_message: config.goodbye_message.as_deref(),
+        timezone: timezone.map(|timezone| timezone.name()),
+    };
+    let entry = audit.entry(guild, "replaced the configuration");
+    if db::replace_guild(pool.inner(), guild, replacement, &entry).await? {
+        return Ok(Status::Ok);
+    }
+    let created = audit.entry(guild, "created the guild");
+    if db::create_guild(pool.inner(), guild, replacement, &created).await? {
+        return Ok(Status::Created);
+    }
+    // created concurrently, replace it instead
+    if db::replace_guild(pool.inner(), guild, replacement, &entry).await? {
+        Ok(Status::Ok)
+    } else {
+        Err(ApiError::not_found("guild", guild))
+    }
+}
+
+/// `POST` the whole configuration of the guild at once, in the same form as [`guild_put()`], to get
+/// the resulting [`GuildSettings`].
+///
+/// Every field left out is unset, the timezone included. Unlike [`guild_put()`] the guild must exist,
+/// and either every field is changed or none is.
+#[post("/guild/<guild>/config", data = "<config>")]
+async fn guild_set_config(
+    pool: Pool<'_>,
+    audit: Audit,
+    settings: &State<ApiConfig>,
+    guild: u64,
+    config: Body<GuildForm<'_>>,
+) -> ApiResult<Tagged<GuildSettings>> {
+    let guild = snowflake("guild", guild)?;
+    let (advertise, chan, timezone) = config.check(settings)?;
+    let replacement = db::GuildReplacement {
+        admin_chan: chan,
+        advertise,
+        welcome_message: config.welcome_message.as_deref(),
+        goodbye_message: config.goodbye_message.as_deref(),
+        timezone: timezone.map(|timezone| timezone.name()),
+    };
+    let entry = audit.entry(guild, "replaced the configuration");
+    if !db::replace_guild(pool.inner(), guild, replacement, &entry).await? {
+        return Err(ApiError::not_found("guild", guild));
+    }
+    guild_settings(pool, settings, guild).await
+}
+
+/// `DELETE` the guild along with all its slaps and privileges at once, returning how many of them
+/// went with it. The audit log of the guild is kept, with an entry for the deletion.
+///
+/// With `dry_run` nothing is deleted (nor audited), what would be is returned instead.
+///
+/// # Errors
+///
+/// Fails with `404` if the guild wasn't created, which is also the case once deleted.
+#[delete("/guild/<guild>?<dry_run>")]
+async fn guild_delete(
+    _admin: Admin,
+    pool: Pool<'_>,
+    audit: Audit,
+    guild: u64,
+    dry_run: Option<bool>,
+) -> ApiResult<Json<db::GuildDeletion>> {
+    let guild = snowflake("guild", guild)?;
+    let deletion = if dry_run.unwrap_or(false) {
+        pool.retry(|pool| db::guild_deletion(pool, guild)).await?
+    } else {
+        let entry = audit.entry(guild, "deleted the guild");
+        db::delete_guild(pool.inner(), guild, &entry).await?
+    };
+    deletion
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("guild", guild))
+}
+
+/// `POST` the admin channel of the guild, as a form or a JSON string. An empty body, an empty value
+/// or `null` unsets it.
+///
+/// # Errors
+///
+/// Fails with `400` if the channel is neither a Discord id nor one of the above, and with `404` if the
+/// guild wasn't created.
+#[post("/guild/<guild>/admin_channel", data = "<chan>")]
+async fn guild_set_admin_chan(
+    pool: Pool<'_>,
+    audit: Audit,
+    guild: u64,
+    chan: Body<Option<String>>,
+) -> ApiResult<()> {
+    let guild = snowflake("guild", guild)?;
+    let chan = match chan.as_deref().map(str::trim) {
+        None | Some("") | Some("null") => None,
+        Some(chan) => Some(
+            chan.parse()
+                .map_err(|_| ApiError::InvalidChannel(chan.into()))
+                .and_then(|chan| snowflake("admin_channel", chan))?,
+        ),
+    };
+    // writing to an unknown guild would do nothing, yet still be audited
+    ensure_exists(&pool, guild).await?;
+    GuildConfig(guild.into())
+        .set_admin_chan(pool.inner(), chan.map(|int| int.into()))
+        .await?;
+    let summary = match chan {
+        Some(chan) => format!("set the admin channel to {}", chan),
+        None => "unset the admin channel".into(),
+    };
+    record(&pool, audit.entry(guild, summary)).await
+}
+
+/// `POST` the advertising policy of the guild, any spelling of a [`crate::params::Flag`] in a form or
+/// a JSON boolean.
+///
+/// # Errors
+///
+/// Fails with `400` if the policy isn't a flag, and with `404` if the guild wasn't created.
+#[post("/guild/<guild>/advertise", data = "<policy>")]
+async fn guild_set_advertise(
+    pool: Pool<'_>,
+    audit: Audit,
+    guild: u64,
+    policy: Body<Option<Flag>>,
+) -> ApiResult<()> {
+    let guild = snowflake("guild", guild)?;
+    let policy = policy.ok_or(ApiError::InvalidFlag("policy"))?.0;
+    // writing to an unknown guild would do nothing, yet still be audited
+    ensure_exists(&pool, guild).await?;
+    GuildConfig(guild.into())
+        .set_advertise(pool.inner(), policy)
+        .await?;
+    record(
+        &pool,
+        audit.entry(guild, format!("set advertise to {}", policy)),
+    )
+    .await
+}
+
+/// `POST` the welcome message of the guild, as a form or a JSON string. Leaving it out, or `null`,
+/// unsets it.
+///
+/// # Errors
+///
+/// Fails with `422` if the message is longer than [`ApiConfig::max_message_len`]. Resending a
+/// message stored before the limit was lowered fails the same way though with
+/// `StoredMessageTooLong`, while [`guild_welcome_message()`] still answers it as is. Fails with `404`
+/// if the guild wasn't created.
+#[post("/guild/<guild>/welcome_message", data = "<message>")]
+async fn guild_set_welcome_message(
+    pool: Pool<'_>,
+    audit: Audit,
+    settings: &State<ApiConfig>,
+    guild: u64,
+    message: Body<Option<String>>,
+) -> ApiResult<()> {
+    let guild = snowflake("guild", guild)?;
+    let message = message.as_deref();
+    if let Some(message) = message {
+        if let Err(err) = check_message(settings, "welcome_message", message) {
+            let config = &GuildConfig(guild.into());
+            let stored = pool
+                .retry(|pool| async move { config.get_welcome_message(pool).await })
+                .await
+                .ok()
+                .flatten();
+            return Err(predating(err, message, stored.as_deref()));
+        }
+    }
+    // writing to an unknown guild would do nothing, yet still be audited
+    ensure_exists(&pool, guild).await?;
+    GuildConfig(guild.into())
+        .set_welcome_message(pool.inner(), message)
+        .await?;
+    let summary = match message {
+        Some(_) => "set the welcome message",
+        None => "unset the welcome message",
+    };
+    record(&pool, audit.entry(guild, summary)).await
+}
+
+/// `POST` the goodbye message of the guild, as a form or a JSON string. Leaving it out, or `null`,
+/// unsets it.
+///
+/// # Errors
+///
+/// Fails with `422` if the message is longer than [`ApiConfig::max_message_len`]. Resending a
+/// message stored before the limit was lowered fails the same way though with
+/// `StoredMessageTooLong`, while [`guild_goodbye_message()`] still answers it as is. Fails with `404`
+/// if the guild wasn't created.
+#[post("/guild/<guild>/goodbye_message", data = "<message>")]
+async fn guild_set_goodbye_message(
+    pool: Pool<'_>,
+    audit: Audit,
+    settings: &State<ApiConfig>,
+    guild: u64,
+    message: Body<Option<String>>,
+) -> ApiResult<()> {
+    let guild = snowflake("guild", guild)?;
+    let message = message.as_deref();
+    if let Some(message) = message {
+        if let Err(err) = check_message(settings, "goodbye_message", message) {
+            let config = &GuildConfig(guild.into());
+            let stored = pool
+                .retry(|pool| async move { config.get_goodbye_message(pool).await })
+                .await
+                .ok()
+                .flatten();
+            return Err(predating(err, message, stored.as_deref()));
+        }
+    }
+    // writing to an unknown guild would do nothing, yet still be audited
+    ensure_exists(&pool, guild).await?;
+    GuildConfig(guild.into())
+        .set_goodbye_message(pool.inner(), message)
+        .await?;
+    let summary = match message {
+        Some(_) => "set the goodbye message",
+        None => "unset the goodbye message",
+    };
+    record(&pool, audit.entry(guild, summary)).await
+}
+
+/// The IANA timezone named `timezone`, `None` if it is left out, empty or `null`.
+fn parse_timezone(timezone: Option<&str>) -> ApiResult<Option<Tz>> {
+    match timezone.map(str::trim) {
+        None | Some("") | Some("null") => Ok(None),
+        Some(timezone) => timezone
+            .parse::<Tz>()
+            .map(Some)
+            .map_err(|_| ApiError::InvalidTimezone(timezone.into())),
+    }
+}
+
+/// `POST` the IANA timezone of the guild, such as `Europe/Paris`, as a form or a JSON string.
+/// Leaving it out, an empty value or `null` unsets it, the guild is then in UTC.
+///
+/// # Errors
+///
+/// Fails with `422` if the timezone isn't known, and with `404` if the guild wasn't created.
+#[post("/guild/<guild>/timezone", data = "<timezone>")]
+async fn guild_set_timezone(
+    pool: Pool<'_>,
+    audit: Audit,
+    guild: u64,
+    timezone: Body<Option<String>>,
+) -> ApiResult<()> {
+    let guild = snowflake("guild", guild)?;
+    let timezone = parse_timezone(timezone.as_deref())?;
+    let summary = match timezone {
+        Some(timezone) => format!("set the timezone to {}", timezone.name()),
+        None => "unset the timezone".into(),
+    };
+    let entry = audit.entry(guild, summary);
+    let name = timezone.map(|timezone| timezone.name());
+    if db::set_timezone(pool.inner(), guild, name, &entry).await? {
+        Ok(())
+    } else {
+        Err(ApiError::not_found("guild", guild))
+    }
+}
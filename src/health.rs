@@ -0,0 +1,86 @@
+//! Health of the linker, for load balancers and operators.
+//!
+//! Neither endpoint goes through the [`crate::pool::Pool`] guard, so that a saturated pool is
+//! reported rather than answered with `503` by the guard.
+
+use crate::db;
+use db_adapter::PgPool;
+use rocket::{get, http::Status, routes, serde::json::Json, Route, State};
+use serde::Serialize;
+
+pub fn routes() -> Vec<Route> {
+    routes![health, health_detailed]
+}
+
+/// Connections of the pool when the health was checked.
+#[derive(Debug, Serialize)]
+struct PoolHealth {
+    /// Connections currently open, idle or not.
+    size: u32,
+    /// Open connections waiting for a request.
+    idle: usize,
+    /// Open connections serving a request.
+    in_use: usize,
+}
+
+impl PoolHealth {
+    fn of(pool: &PgPool) -> Self {
+        let (size, idle) = (pool.size(), pool.num_idle());
+        PoolHealth {
+            size,
+            idle,
+            in_use: (size as usize).saturating_sub(idle),
+        }
+    }
+}
+
+/// Health of the linker, answered with `503` unless the database is up.
+#[derive(Debug, Serialize)]
+struct Health {
+    /// Whether the database answered a `SELECT 1`.
+    database: bool,
+    /// Only given by [`health_detailed()`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pool: Option<PoolHealth>,
+}
+
+impl Health {
+    async fn check(pool: &PgPool) -> Self {
+        let database = match db::self_check(pool).await {
+            Ok(_) => true,
+            Err(err) => {
+                log::warn!("the database is down: {}", err);
+                false
+            }
+        };
+        Health {
+            database,
+            pool: None,
+        }
+    }
+
+    fn status(&self) -> Status {
+        if self.database {
+            Status::Ok
+        } else {
+            Status::ServiceUnavailable
+        }
+    }
+}
+
+/// `GET` whether the linker can reach the database.
+#[get("/health")]
+async fn health(pool: &State<PgPool>) -> (Status, Json<Health>) {
+    let health = Health::check(pool).await;
+    (health.status(), Json(health))
+}
+
+/// `GET` the same as [`health()`] along with the utilization of the pool, to spot saturation
+/// before requests fail.
+#[get("/health/detailed")]
+async fn health_detailed(pool: &State<PgPool>) -> (Status, Json<Health>) {
+    let mut health = Health::check(pool).await;
+    // after the check, which may have opened a connection
+    health.pool = Some(PoolHealth::of(pool));
+    (health.status(), Json(health))
+}
@@ -0,0 +1,63 @@
+//! Discord ids as they are exchanged with clients.
+
+use rocket::form::{self, FromFormField, ValueField};
+use serde::{
+    de::{self, Deserializer, Visitor},
+    Deserialize, Serialize, Serializer,
+};
+use std::fmt;
+
+/// Discord snowflake, answered as a JSON string.
+///
+/// Snowflakes span 64 bits while JavaScript numbers are floats which silently round any integer above
+/// 2^53. Strings and numbers are both accepted in requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Snowflake(pub u64);
+
+impl From<u64> for Snowflake {
+    fn from(id: u64) -> Self {
+        Snowflake(id)
+    }
+}
+
+impl<'v> FromFormField<'v> for Snowflake {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        field
+            .value
+            .parse()
+            .map(Snowflake)
+            .map_err(|err| form::Error::from(err).into())
+    }
+}
+
+impl Serialize for Snowflake {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Snowflake {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SnowflakeVisitor;
+
+        impl<'de> Visitor<'de> for SnowflakeVisitor {
+            type Value = Snowflake;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a Discord id, as a number or a string")
+            }
+
+            fn visit_u64<E: de::Error>(self, id: u64) -> Result<Snowflake, E> {
+                Ok(Snowflake(id))
+            }
+
+            fn visit_str<E: de::Error>(self, id: &str) -> Result<Snowflake, E> {
+                id.parse()
+                    .map(Snowflake)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(id), &self))
+            }
+        }
+
+        deserializer.deserialize_any(SnowflakeVisitor)
+    }
+}
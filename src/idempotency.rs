@@ -0,0 +1,127 @@
+//! Replay protection for the endpoints that aren't naturally idempotent.
+//!
+//! Clients opt in by sending an `Idempotency-Key` header. The first request bearing a key is
+//! processed as usual and its result is remembered for a while, repeated ones get that result back.
+
+use crate::config::{parse_var, ConfigError};
+use rocket::{
+    request::{FromRequest, Outcome, Request},
+    tokio::sync::Mutex,
+};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    convert::Infallible,
+    env,
+    future::Future,
+    hash::{Hash, Hasher},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// How long results are remembered when `IDEMPOTENCY_TTL` (in seconds) is unset: a day.
+const DEFAULT_TTL: u64 = 24 * 60 * 60;
+
+/// Value of the `Idempotency-Key` header, if any.
+pub struct IdempotencyKey(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IdempotencyKey {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(IdempotencyKey(
+            request
+                .headers()
+                .get_one("Idempotency-Key")
+                .map(String::from),
+        ))
+    }
+}
+
+/// A key was sent again along with a request other than the one it was first used for.
+#[derive(Debug)]
+pub struct KeyReused(pub String);
+
+/// Fingerprint of `request`, telling apart the requests sent with the same key.
+///
+/// Results are only stored in memory, so the fingerprint needn't be stable across restarts.
+pub fn fingerprint(request: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    request.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Result of a keyed request once produced, with when it was stored and the [`fingerprint`] of the
+/// request.
+type Slot<T> = Arc<Mutex<Option<(Instant, u64, T)>>>;
+
+/// Results of keyed requests, scoped by guild.
+///
+/// Each key has its own lock, so that requests bearing different keys never wait for one another.
+pub struct IdempotencyStore<T> {
+    ttl: Duration,
+    results: Mutex<HashMap<(u64, String), Slot<T>>>,
+}
+
+impl<T: Clone> IdempotencyStore<T> {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_vars(|var| env::var(var).ok())
+    }
+
+    /// Builds a store whose keys expire after `IDEMPOTENCY_TTL` seconds, a day by default.
+    pub fn from_vars(vars: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let ttl = parse_var(&vars, "IDEMPOTENCY_TTL", "a number of seconds", DEFAULT_TTL)?;
+        Ok(IdempotencyStore {
+            ttl: Duration::from_secs(ttl),
+            results: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the result stored for `key` in `guild`, or stores the one produced by `create`.
+    ///
+    /// The key stays locked while `create` runs so that concurrent retries can't both go through.
+    /// Failures aren't stored: the request may be retried with the same key. A stored result is only
+    /// returned to the request it was produced for, others with the same key fail with
+    /// [`KeyReused`].
+    pub async fn get_or_try_insert<F, Fut, E>(
+        &self,
+        guild: u64,
+        key: String,
+        fingerprint: u64,
+        create: F,
+    ) -> Result<T, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: From<KeyReused>,
+    {
+        let now = Instant::now();
+        let ttl = self.ttl;
+        let fresh = |stored_at: &Instant| now.duration_since(*stored_at) < ttl;
+        let slot = {
+            let mut results = self.results.lock().await;
+            // slots locked by a request are in use, as are the empty ones some request holds
+            results.retain(|_, slot| match slot.try_lock() {
+                Ok(result) => match &*result {
+                    Some((stored_at, ..)) => fresh(stored_at),
+                    None => Arc::strong_count(slot) > 1,
+                },
+                Err(_) => true,
+            });
+            results.entry((guild, key.clone())).or_default().clone()
+        };
+
+        let mut result = slot.lock().await;
+        if let Some((stored_at, stored_fingerprint, stored)) = &*result {
+            if fresh(stored_at) {
+                if *stored_fingerprint != fingerprint {
+                    return Err(KeyReused(key).into());
+                }
+                return Ok(stored.clone());
+            }
+        }
+        let created = create().await?;
+        *result = Some((now, fingerprint, created.clone()));
+        Ok(created)
+    }
+}
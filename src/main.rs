@@ -3,403 +3,293 @@
 //! Because all endpoints make use of the underlying database they can all fail due to it.
 //! As such errors caused by the database are mostly undocumented. Instead endpoints will only provide
 //! an `Error` section if APi-specific errors can occur.
-
+//!
+//! Errors are answered with a JSON body whose `type` tells whether the client (`validation`) or the
+//! server (`internal`) is at fault. See [`error`] for the details.
+
+mod admin;
+mod audit;
+mod auth;
+mod body;
+mod cache;
+mod compression;
+mod config;
+mod db;
+mod error;
+mod feed;
+mod format;
+mod guild;
+mod health;
+mod id;
+mod idempotency;
+mod page;
+mod params;
+mod pool;
+mod retention;
+mod security;
+mod slaps;
 #[cfg(test)]
 mod tests;
-
-use db_adapter::{
-    establish_connection,
-    guild::{GuildConfig, GuildConfigBuilder, GuildConfigError, Privilege},
-    slap::{GuildSlapRecord, MemberSlapRecord, SlapReport},
-    AdapterError, PgPool,
-};
+mod webhook;
+
+use auth::AdminToken;
+use body::BodyRejected;
+use cache::CacheControl;
+use compression::Compress;
+use config::{ApiConfig, PoolConfig};
+use db_adapter::PgPool;
 use dotenv::dotenv;
+use error::ApiError;
+use feed::SlapFeed;
+use format::Pretty;
+use idempotency::IdempotencyStore;
+use pool::{BreakerOpen, CircuitBreaker, PoolGate, RetryPolicy};
+use retention::Prune;
 use rocket::{
-    form::{Form, FromForm},
+    catch, catchers,
+    fairing::AdHoc,
+    figment::providers::Env,
     get,
-    http::ContentType,
-    http::Status,
-    post,
-    request::Request,
-    response::{self, Responder, Response},
+    http::{uri::Origin, Status},
     routes,
     serde::json::Json,
-    State,
+    Build, Request, Rocket, Route,
 };
-use serenity::model::id::{GuildId, RoleId, UserId};
-use std::{io::Cursor, u64};
-use thiserror;
-use tokio_stream::StreamExt;
+use serde::Serialize;
+use slaps::Slap;
+use webhook::Webhook;
 
-type Pool = State<PgPool>;
-
-/// Wrapper around [`AdapterError`]
-#[derive(Debug, thiserror::Error)]
-enum ApiError {
-    #[error("We couldn't process your request: {reason}. Error: {source}")]
-    AdapterError {
-        status: Status,
-        reason: String,
-        #[source]
-        source: AdapterError,
-    },
-    #[error("expected on of: `admin`, `event` or `manager` found {0}")]
-    UnrecognizedPrivilege(String),
-}
-
-impl<'a> From<AdapterError> for ApiError {
-    fn from(err: AdapterError) -> Self {
-        let (status, reason) = match &err {
-            AdapterError::SqlxError(_) => (
-                Status::InternalServerError,
-                "sqlx driver failed to query the database",
-            ),
-            AdapterError::GuildError(guild_error) => match guild_error {
-                GuildConfigError::AlreadyExists(_id) => {
-                    (Status::BadRequest, "guild already exists")
-                }
-                _ => todo!(),
-            },
-        };
+/// Prefix of every route of the current version of the API.
+const V1: &str = "/v1";
 
-        ApiError::AdapterError {
-            status,
-            reason: reason.to_string(),
-            source: err,
-        }
-    }
-}
-
-impl<'r, 'o: 'r> Responder<'r, 'o> for ApiError {
-    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'o> {
-        let mut response = Response::build();
-        response.header(ContentType::Plain);
-        match self {
-            ApiError::AdapterError { status, reason, .. } => response
-                .status(status)
-                .sized_body(reason.len(), Cursor::new(reason)),
-            ApiError::UnrecognizedPrivilege(_) => response.status(Status::BadRequest),
-        };
-
-        response.ok()
-    }
-}
+/// Seconds in-flight requests are given to complete once shutdown is requested, unless
+/// `SHUTDOWN_GRACE` says otherwise.
+const DEFAULT_SHUTDOWN_GRACE: u32 = 5;
 
-type ApiResult<T> = Result<T, ApiError>;
-
-#[rocket::main]
-async fn main() {
+fn rocket() -> Rocket<Build> {
     dotenv().ok();
-    rocket::build()
+    let figment = rocket::Config::figment()
+        .merge(("shutdown.grace", DEFAULT_SHUTDOWN_GRACE))
+        .merge(
+            Env::raw()
+                .only(&["SHUTDOWN_GRACE"])
+                .map(|_| "shutdown.grace".into()),
+        )
+        // the variables are lowercased, which matches the fields of `ApiConfig`
+        .merge(Env::raw().only(ApiConfig::VARS));
+    rocket::custom(figment)
         //TODO: try and optimise this since every call only requires &PgPool (ie: references)
-        .manage(establish_connection().await)
-        .mount(
+        .attach(AdHoc::try_on_ignite("Database", |rocket| async {
+            // tests manage the configuration of their own database beforehand
+            let config = match rocket.state::<PoolConfig>() {
+                Some(config) => Ok(config.clone()),
+                None => PoolConfig::from_env(),
+            };
+            let pool = match config {
+                Ok(config) => db::connect(&config)
+                    .await
+                    .map(|pool| (pool, config))
+                    .map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            match pool {
+                Ok((pool, config)) => Ok(rocket
+                    .manage(pool)
+                    .manage(PoolGate::new(&config))
+                    .manage(RetryPolicy::new(&config))
+                    .manage(CircuitBreaker::new(&config))),
+                Err(err) => {
+                    log::error!("couldn't set up the database: {}", err);
+                    Err(rocket)
+                }
+            }
+        }))
+        .attach(AdHoc::config::<ApiConfig>())
+        .attach(Prune)
+        .attach(AdHoc::on_ignite("Security headers", |rocket| async {
+            let config = rocket.state::<ApiConfig>().cloned().unwrap_or_default();
+            rocket.attach(security::shield(&config))
+        }))
+        // tests manage a token and a webhook of their own beforehand
+        .attach(AdHoc::on_ignite("Admin token", |rocket| async {
+            match rocket.state::<AdminToken>() {
+                Some(_) => rocket,
+                None => rocket.manage(AdminToken::from_env()),
+            }
+        }))
+        .attach(AdHoc::try_on_ignite("Idempotency", |rocket| async {
+            match IdempotencyStore::<Slap>::from_env() {
+                Ok(store) => Ok(rocket.manage(store)),
+                Err(err) => {
+                    log::error!("couldn't set up idempotency keys: {}", err);
+                    Err(rocket)
+                }
+            }
+        }))
+        .manage(SlapFeed::new())
+        .attach(AdHoc::on_ignite("Webhook", |rocket| async {
+            match rocket.state::<Webhook>() {
+                Some(_) => rocket,
+                None => rocket.manage(Webhook::from_env()),
+            }
+        }))
+        .attach(AdHoc::on_request("Trailing slash", |request, _| {
+            Box::pin(async move {
+                if let Some(uri) = without_trailing_slash(request.uri()) {
+                    request.set_uri(uri);
+                }
+            })
+        }))
+        .register(
             "/",
-            routes![
-                gsr_len,
-                gsr_slaps,
-                gsr_offenders,
-                new_slap,
-                msr_len,
-                msr_slaps,
-                guild_admin_chan,
-                guild_advertise,
-                guild_exists,
-                guild_goodbye_message,
-                guild_has_privileges,
-                guild_privileges_for,
-                guild_roles_with,
-                guild_welcome_message,
-                guild_have_privilege,
-                guild_new,
-                guild_set_admin_chan,
-                guild_set_advertise,
-                guild_set_welcome_message,
-                guild_set_goodbye_message
+            catchers![
+                malformed,
+                unauthorized,
+                forbidden,
+                unprocessable,
+                unsupported_media_type,
+                saturated
             ],
         )
-        .launch()
-        .await
-        .unwrap();
-}
-
-/// `GET` up to `number` [`SlapReport`] from the guild.
-///
-///Currently there's no way to stream all [`SlapReport`] from a guild so this is often used alongside
-///[`gsr_len()`]. Otherwise you may provide a very big `number` *should* give them all.
-///
-/// # Errors
-///
-/// Aside from failures from the underlying database, the request will fail if `number` is greater than
-/// either 2^32 or 2^64 depending on the platform.
-#[get("/slaps/<guild>/reports?<number>")]
-async fn gsr_slaps(guild: u64, number: usize, pool: &Pool) -> ApiResult<Json<Vec<SlapReport>>> {
-    Ok(Json(
-        GuildSlapRecord::from(GuildId(guild))
-            .slaps(pool.inner())
-            .take(number)
-            .collect::<Result<Vec<SlapReport>, AdapterError>>()
-            .await?,
-    ))
-}
-
-/// `GET` up to `number` [`UserId`] (`u64`) who were slapped in the guild.
-///
-///Currently there's no way to stream all [`UserId`] from a guild so this is often used alongside
-///[`gsr_offender_len`]. Otherwise you may provide a very big `number` *should* give them all.
-///
-/// # Errors
-///
-/// Aside from failures from the underlying database, the request will fail if `number` is greater than
-/// either 2^32 or 2^64 depending on the platform.
-#[get("/slaps/<guild>/offenders?<number>")]
-async fn gsr_offenders(guild: u64, number: usize, pool: &Pool) -> ApiResult<Json<Vec<u64>>> {
-    Ok(Json(
-        GuildSlapRecord::from(GuildId(guild))
-            .offenders(pool.inner())
-            .take(number)
-            .map(|res| res.map(|msr| msr.1 .0))
-            .collect::<Result<Vec<u64>, AdapterError>>()
-            .await?,
-    ))
-}
-
-/// `GET` the number of slaps in the guild
-#[get("/slaps/<guild>/len")]
-async fn gsr_len(pool: &Pool, guild: u64) -> ApiResult<Json<usize>> {
-    Ok(Json(
-        GuildSlapRecord::from(GuildId(guild))
-            .len(pool.inner())
-            .await?,
-    ))
-}
-
-#[derive(Debug, FromForm)]
-struct SlapForm {
-    guild: u64,
-    sentence: u64,
-    offender: u64,
-    enforcer: Option<u64>,
-    reason: Option<String>,
-}
-
-#[post("/slaps/new", data = "<slap>")]
-async fn new_slap(pool: &Pool, slap: Form<SlapForm>) -> ApiResult<Json<SlapReport>> {
-    let gsr = GuildSlapRecord(slap.guild.into());
-    Ok(Json(
-        gsr.new_slap(
-            pool.inner(),
-            slap.sentence.into(),
-            slap.offender.into(),
-            slap.enforcer.into(),
-            slap.reason.as_ref(),
-        )
-        .await?,
-    ))
-}
-
-/// `GET` the number of slaps in the guild for `member` ([`UserId`])
-#[get("/slaps/<guild>/<member>/len")]
-async fn msr_len(pool: &Pool, guild: u64, member: u64) -> ApiResult<Json<usize>> {
-    Ok(Json(
-        MemberSlapRecord::from((GuildId(guild), UserId(member)))
-            .len(pool.inner())
-            .await?,
-    ))
-}
-
-#[get("/slaps/<guild>/<member>/reports?<number>")]
-async fn msr_slaps(
-    guild: u64,
-    member: u64,
-    number: usize,
-    pool: &Pool,
-) -> ApiResult<Json<Vec<SlapReport>>> {
-    Ok(Json(
-        MemberSlapRecord::from((GuildId(guild), UserId(member)))
-            .slaps(pool.inner())
-            .take(number)
-            .collect::<Result<Vec<SlapReport>, AdapterError>>()
-            .await?,
-    ))
-}
-
-#[get("/guild/<guild>/exists")]
-async fn guild_exists(pool: &Pool, guild: u64) -> ApiResult<Json<bool>> {
-    Ok(Json(GuildConfig(guild.into()).exists(pool.inner()).await?))
-}
-
-#[get("/guild/<guild>/admin_channel")]
-async fn guild_admin_chan(pool: &Pool, guild: u64) -> ApiResult<Json<Option<u64>>> {
-    Ok(Json(
-        GuildConfig(guild.into())
-            .get_admin_chan(pool.inner())
-            .await?
-            .map(|chan_id| chan_id.into()),
-    ))
-}
-
-#[get("/guild/<guild>/advertise")]
-async fn guild_advertise(pool: &Pool, guild: u64) -> ApiResult<Json<bool>> {
-    Ok(Json(
-        GuildConfig(guild.into())
-            .get_advertise(pool.inner())
-            .await?,
-    ))
-}
-
-#[get("/guild/<guild>/goodbye_message")]
-async fn guild_goodbye_message(pool: &Pool, guild: u64) -> ApiResult<Json<Option<String>>> {
-    Ok(Json(
-        GuildConfig(guild.into())
-            .get_goodbye_message(pool.inner())
-            .await?,
-    ))
-}
-
-#[get("/guild/<guild>/welcome_message")]
-async fn guild_welcome_message(pool: &Pool, guild: u64) -> ApiResult<Json<Option<String>>> {
-    Ok(Json(
-        GuildConfig(guild.into())
-            .get_welcome_message(pool.inner())
-            .await?,
-    ))
+        .mount(V1, routes())
+        // unversioned routes are deprecated aliases kept for one release
+        .mount("/", routes())
+        .attach(AdHoc::on_response("Deprecation", |request, response| {
+            Box::pin(async move {
+                let path = request.uri().path().as_str();
+                if path != V1 && !path.starts_with(&format!("{}/", V1)) {
+                    response.set_raw_header("Deprecation", "true");
+                    response.set_raw_header(
+                        "Link",
+                        format!("<{}{}>; rel=\"successor-version\"", V1, path),
+                    );
+                }
+            })
+        }))
+        .attach(CacheControl)
+        // indented before being compressed
+        .attach(Pretty)
+        .attach(Compress)
 }
 
-#[get("/guild/<guild>/privileges/for_role/<role>")]
-async fn guild_privileges_for(pool: &Pool, guild: u64, role: u64) -> ApiResult<Json<Vec<String>>> {
-    let privs = GuildConfig(guild.into())
-        .get_privileges_for(pool.inner(), role.into())
-        .await?
-        .iter()
-        //consider finding a more optimized way to do this
-        .map(|privilege| privilege.as_ref().into())
-        .collect();
-    Ok(Json(privs))
+/// `uri` without the trailing slashes of its path, if it has any, so that `/guild/<guild>/exists/`
+/// is routed like `/guild/<guild>/exists`. The root is left as is.
+fn without_trailing_slash(uri: &Origin<'_>) -> Option<Origin<'static>> {
+    let path = uri.path().as_str();
+    if path == "/" || !path.ends_with('/') {
+        return None;
+    }
+    let path = match path.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    };
+    let uri = match uri.query() {
+        Some(query) => format!("{}?{}", path, query.as_str()),
+        None => path.to_string(),
+    };
+    Origin::parse_owned(uri).ok()
 }
 
-//TODO: good candiadate for a TryInto impl -> see db-adapter
-fn str_to_priv(src: &str) -> ApiResult<Privilege> {
-    Ok(match src {
-        "admin" => Privilege::Admin,
-        "manager" => Privilege::Manager,
-        "event" => Privilege::Event,
-        _ => return Err(ApiError::UnrecognizedPrivilege(src.into())),
-    })
+/// Every route of the API.
+fn routes() -> Vec<Route> {
+    let mut routes = routes![version];
+    routes.extend(slaps::routes());
+    routes.extend(guild::routes());
+    routes.extend(admin::routes());
+    routes.extend(health::routes());
+    routes
 }
 
-#[get("/guild/<guild>/privileges/roles_with/<privilege_str>")]
-async fn guild_roles_with(
-    pool: &Pool,
-    guild: u64,
-    privilege_str: &str,
-) -> ApiResult<Json<Vec<u64>>> {
-    Ok(Json(
-        GuildConfig(guild.into())
-            .get_roles_with(pool.inner(), str_to_priv(privilege_str)?)
-            .await?
-            .iter()
-            .map(|role| u64::from(*role))
-            .collect::<Vec<u64>>(),
-    ))
+#[rocket::main]
+async fn main() {
+    let rocket = match rocket().ignite().await {
+        Ok(rocket) => rocket,
+        Err(err) => {
+            log::error!("the linker couldn't start: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let pool = rocket
+        .state::<PgPool>()
+        .cloned()
+        .expect("the database is managed once ignited");
+
+    // only returns once in-flight requests completed or the grace period expired
+    let result = rocket.launch().await;
+    pool.close().await;
+    if let Err(err) = result {
+        log::error!("the linker stopped unexpectedly: {}", err);
+        std::process::exit(1);
+    }
 }
 
-#[get("/guild/<guild>/privileges/has/<role>?<privileges_str>")]
-async fn guild_has_privileges(
-    pool: &Pool,
-    guild: u64,
-    role: u64,
-    privileges_str: Vec<String>,
-) -> ApiResult<Json<bool>> {
-    let mut privileges = Vec::with_capacity(privileges_str.len());
-    for string in privileges_str {
-        privileges.push(str_to_priv(string.as_str())?)
+/// Only the [`pool::Pool`] guard fails with `503`, when the database is saturated or while the
+/// [`CircuitBreaker`] is open.
+#[catch(503)]
+fn saturated(request: &Request<'_>) -> ApiError {
+    match request.local_cache(|| None::<BreakerOpen>) {
+        Some(BreakerOpen(retry_after)) => ApiError::Unavailable {
+            retry_after: *retry_after,
+        },
+        None => ApiError::Saturated,
     }
-    Ok(Json(
-        GuildConfig(guild.into())
-            .has_privileges(pool.inner(), role.into(), privileges.as_slice())
-            .await?,
-    ))
 }
 
-#[get("/guild/<guild>/privileges/have/<privilege_str>?<roles>")]
-async fn guild_have_privilege(
-    pool: &Pool,
-    guild: u64,
-    roles: Vec<u64>,
-    privilege_str: &str,
-) -> ApiResult<Json<bool>> {
-    Ok(Json(
-        GuildConfig(guild.into())
-            .have_privilege(
-                pool.inner(),
-                roles
-                    .iter()
-                    .map(|int| RoleId(*int))
-                    .collect::<Vec<RoleId>>()
-                    .as_slice(),
-                str_to_priv(privilege_str)?,
-            )
-            .await?,
-    ))
+/// Bodies the [`body::Body`] guard couldn't parse are answered with their [`ApiError::InvalidBody`],
+/// anything else Rocket itself rejected with [`ApiError::Malformed`]. The status is kept either way.
+#[catch(400)]
+fn malformed(status: Status, request: &Request<'_>) -> (Status, ApiError) {
+    let err = match request.local_cache(|| None::<BodyRejected>) {
+        Some(BodyRejected(reason)) => ApiError::InvalidBody(reason.clone()),
+        None => ApiError::Malformed,
+    };
+    (status, err)
 }
 
-#[derive(Debug, FromForm)]
-struct NewGuildForm {
-    id: u64,
-    welcome_message: Option<String>,
-    goodbye_message: Option<String>,
-    advertise: bool,
+/// See [`malformed`].
+#[catch(422)]
+fn unprocessable(status: Status, request: &Request<'_>) -> (Status, ApiError) {
+    malformed(status, request)
 }
 
-#[post("/guild/new", data = "<config>")]
-async fn guild_new<'a>(pool: &Pool, config: Form<NewGuildForm>) -> ApiResult<()> {
-    //consider moving some of this code into an `TryFrom` impl and call `into_inner` instead
-    let mut builder = GuildConfigBuilder::new(config.id.into());
-    builder.advertise(config.advertise);
-    if let Some(welcome) = &config.welcome_message {
-        builder.welcome_message(welcome.as_str())?;
-    }
-    if let Some(goodbye) = &config.goodbye_message {
-        builder.welcome_message(goodbye.as_str())?;
-    }
-
-    GuildConfig::new(pool.inner(), builder).await?;
-    Ok(())
+/// Only the [`auth::Admin`] guard fails with `401`, when the request doesn't bear the admin token.
+#[catch(401)]
+fn unauthorized() -> ApiError {
+    ApiError::Unauthorized
 }
 
-#[post("/guild/<guild>/admin_channel", data = "<chan>")]
-async fn guild_set_admin_chan(pool: &Pool, guild: u64, chan: Form<Option<u64>>) -> ApiResult<()> {
-    Ok(GuildConfig(guild.into())
-        .set_admin_chan(pool.inner(), chan.into_inner().map(|int| int.into()))
-        .await?)
+/// Only the [`auth::Admin`] guard fails with `403`, when no admin token is set.
+#[catch(403)]
+fn forbidden() -> ApiError {
+    ApiError::AdminDisabled
 }
 
-#[post("/guild/<guild>/advertise", data = "<policy>")]
-async fn guild_set_advertise(pool: &Pool, guild: u64, policy: Form<bool>) -> ApiResult<()> {
-    Ok(GuildConfig(guild.into())
-        .set_advertise(pool.inner(), policy.into_inner())
-        .await?)
+/// Only the [`body::Body`] guard fails with `415`, when the body is neither JSON nor a form.
+#[catch(415)]
+fn unsupported_media_type(request: &Request<'_>) -> ApiError {
+    ApiError::UnsupportedMediaType(request.content_type().map(ToString::to_string))
 }
 
-#[post("/guild/<guild>/welcome_message", data = "<message>")]
-async fn guild_set_welcome_message(
-    pool: &Pool,
-    guild: u64,
-    message: Form<Option<&str>>,
-) -> ApiResult<()> {
-    Ok(GuildConfig(guild.into())
-        .set_welcome_message(pool.inner(), message.into_inner())
-        .await?)
+/// Build information of the running linker.
+#[derive(Debug, Serialize)]
+struct Version {
+    /// Version of the crate.
+    version: &'static str,
+    /// Hash of the commit the linker was built from, `unknown` if it wasn't built from a git
+    /// checkout.
+    commit: &'static str,
+    /// Unix timestamp (in seconds) of the build.
+    built_at: u64,
 }
 
-#[post("/guild/<guild>/goodbye_message", data = "<message>")]
-async fn guild_set_goodbye_message(
-    pool: &Pool,
-    guild: u64,
-    message: Form<Option<&str>>,
-) -> ApiResult<()> {
-    Ok(GuildConfig(guild.into())
-        .set_goodbye_message(pool.inner(), message.into_inner())
-        .await?)
+/// `GET` the [`Version`] of the linker. This never touches the database.
+#[get("/version")]
+fn version() -> Json<Version> {
+    Json(Version {
+        version: env!("CARGO_PKG_VERSION"),
+        commit: env!("GIT_COMMIT"),
+        built_at: env!("BUILD_TIMESTAMP")
+            .parse()
+            .expect("build script emits a valid timestamp"),
+    })
 }
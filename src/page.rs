@@ -0,0 +1,108 @@
+//! Pagination of listings.
+
+use crate::{
+    db::SlapRow,
+    error::{ApiError, ApiResult},
+    params::SlapOrder,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Window of a listing: up to `limit` items after skipping the first `offset` ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Page {
+    /// Page of `number` items from the `offset`, by default the start of the listing.
+    pub fn new(number: usize, offset: Option<usize>) -> Self {
+        Page {
+            offset: offset.unwrap_or(0),
+            limit: number,
+        }
+    }
+}
+
+/// A [`Page`] of a listing, along with the size of the whole listing.
+#[derive(Debug, Serialize)]
+pub struct Paginated<T> {
+    pub data: Vec<T>,
+    /// Number of items in the whole listing.
+    pub total: u64,
+    pub offset: usize,
+    pub limit: usize,
+    /// Token resuming the listing after this page, for the listings paged with a [`Cursor`]. Left
+    /// out once the end is reached.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Paginated<T> {
+    pub fn new(data: Vec<T>, total: u64, page: Page) -> Self {
+        Paginated {
+            data,
+            total,
+            offset: page.offset,
+            limit: page.limit,
+            next_cursor: None,
+        }
+    }
+
+    /// The same page with `f` applied to every item.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Paginated<U> {
+        Paginated {
+            data: self.data.into_iter().map(f).collect(),
+            total: self.total,
+            offset: self.offset,
+            limit: self.limit,
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+/// Position right after a slap in a listing sorted by `order`, which doesn't move when slaps are
+/// inserted or deleted before it the way an offset does.
+///
+/// Clients only ever see it [encoded](Cursor::encode) as an opaque token. Tokens aren't signed:
+/// tampering with one is caught as long as it doesn't decode to a cursor anymore, and one which
+/// still does only resumes the listing somewhere else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    pub order: SlapOrder,
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub sentence: i64,
+}
+
+impl Cursor {
+    /// Cursor right after `slap` in a listing sorted by `order`.
+    pub fn after(order: SlapOrder, slap: &SlapRow) -> Self {
+        Cursor {
+            order,
+            id: slap.id,
+            created_at: slap.created_at,
+            sentence: slap.sentence,
+        }
+    }
+
+    /// The token handed to clients.
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursors can always be serialized");
+        base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+    }
+
+    /// The cursor of a token given by [`Cursor::encode`].
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ApiError::InvalidCursor`] if `token` isn't one.
+    pub fn decode(token: &str) -> ApiResult<Self> {
+        base64::decode_config(token, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|json| serde_json::from_slice::<Cursor>(&json).ok())
+            .filter(|cursor| cursor.id > 0 && cursor.sentence >= 0)
+            .ok_or(ApiError::InvalidCursor)
+    }
+}
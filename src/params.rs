@@ -0,0 +1,238 @@
+//! Types parsed out of query strings and forms.
+
+use crate::error::{ApiError, ApiResult};
+use chrono::{DateTime, Duration, Utc};
+use rocket::form::{self, FromFormField, ValueField};
+use serde::{
+    de::{self, Deserializer, Visitor},
+    Deserialize, Serialize,
+};
+use std::{convert::TryFrom, fmt};
+
+/// Ensures the `param` id could be a Discord snowflake before using it.
+///
+/// `0` never is one and ids beyond `i64::MAX` can't be stored by postgres.
+pub fn snowflake(param: &'static str, id: u64) -> ApiResult<u64> {
+    if id == 0 || id > i64::MAX as u64 {
+        return Err(ApiError::InvalidId { param, id });
+    }
+    Ok(id)
+}
+
+/// Ensures the `param` integer can be stored by postgres, whose `BIGINT` stops at `i64::MAX`,
+/// before comparing it with one.
+pub fn bigint(param: &'static str, value: u64) -> ApiResult<u64> {
+    if value > i64::MAX as u64 {
+        return Err(ApiError::IntegerTooLarge {
+            param,
+            max: i64::MAX as u64,
+        });
+    }
+    Ok(value)
+}
+
+/// RFC 3339 timestamp, such as `2021-09-14T12:00:00Z`.
+#[derive(Debug, Clone, Copy)]
+pub struct Timestamp(pub DateTime<Utc>);
+
+impl<'v> FromFormField<'v> for Timestamp {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        DateTime::parse_from_rfc3339(field.value)
+            .map(|time| Timestamp(time.with_timezone(&Utc)))
+            .map_err(|_| form::Error::validation("expected an RFC 3339 timestamp").into())
+    }
+}
+
+/// Value of an optional [`Timestamp`] query parameter, see [`timestamp`].
+pub type OptionalTimestamp<'r> = Option<form::Result<'r, Timestamp>>;
+
+/// The timestamp of the `param` query parameter if it was given, which must then be valid.
+///
+/// An `Option<Timestamp>` parameter would silently be `None` when invalid instead.
+pub fn timestamp(
+    param: &'static str,
+    value: OptionalTimestamp<'_>,
+) -> ApiResult<Option<DateTime<Utc>>> {
+    value
+        .map(|value| {
+            value
+                .map(|timestamp| timestamp.0)
+                .map_err(|_| ApiError::InvalidTimestamp(param))
+        })
+        .transpose()
+}
+
+/// Value of an optional count query parameter such as `number`, see [`count`].
+///
+/// It is parsed to a `u128` first so that counts beyond a `usize`, which is 32 or 64 bits wide
+/// depending on the platform, are told apart from values which aren't counts at all.
+pub type OptionalCount<'r> = Option<form::Result<'r, u128>>;
+
+/// The count of the `param` query parameter if it was given, which must then fit in a `usize`.
+///
+/// An `Option<usize>` parameter would silently be `None` when invalid or too large instead.
+pub fn count(param: &'static str, value: OptionalCount<'_>) -> ApiResult<Option<usize>> {
+    value
+        .map(|value| {
+            let value = value.map_err(|_| ApiError::InvalidNumber(param))?;
+            usize::try_from(value).map_err(|_| ApiError::NumberTooLarge {
+                param,
+                max: usize::MAX,
+            })
+        })
+        .transpose()
+}
+
+/// Boolean of a form, spelled `true`/`false`, `1`/`0` or `yes`/`no` in any case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flag(pub bool);
+
+impl<'v> FromFormField<'v> for Flag {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        match field.value.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => Ok(Flag(true)),
+            "false" | "0" | "no" => Ok(Flag(false)),
+            _ => Err(form::Error::validation("expected a boolean").into()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Flag {
+    /// A JSON boolean, or one of the strings of a form.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FlagVisitor;
+
+        impl<'de> Visitor<'de> for FlagVisitor {
+            type Value = Flag;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a boolean")
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<Flag, E> {
+                Ok(Flag(value))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Flag, E> {
+                match value.to_ascii_lowercase().as_str() {
+                    "true" | "1" | "yes" => Ok(Flag(true)),
+                    "false" | "0" | "no" => Ok(Flag(false)),
+                    _ => Err(E::invalid_value(de::Unexpected::Str(value), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(FlagVisitor)
+    }
+}
+
+/// Value of a [`Flag`] form field, see [`flag`].
+pub type FlagField<'r> = form::Result<'r, Flag>;
+
+/// Deserializes a [`FlagField`] out of JSON, for the forms which are accepted as JSON as well.
+pub fn flag_field<'de, 'r, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<FlagField<'r>, D::Error> {
+    Flag::deserialize(deserializer).map(Ok)
+}
+
+/// [`FlagField`] of a JSON body leaving the flag out, as it would be of a form.
+pub fn missing_flag<'r>() -> FlagField<'r> {
+    Err(form::Error::from(form::error::ErrorKind::Missing).into())
+}
+
+/// The flag of the `field`, which must be valid.
+///
+/// A plain `Flag` field would have the whole form fail with `422` instead.
+pub fn flag(field: &'static str, value: &FlagField<'_>) -> ApiResult<bool> {
+    value
+        .as_ref()
+        .map(|flag| flag.0)
+        .map_err(|_| ApiError::InvalidFlag(field))
+}
+
+/// Width of the time buckets statistics are grouped in.
+#[derive(Debug, Clone, Copy, FromFormField)]
+pub enum Bucket {
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+impl Bucket {
+    /// Unit understood by postgres' `date_trunc`.
+    pub fn unit(self) -> &'static str {
+        match self {
+            Bucket::Hour => "hour",
+            Bucket::Day => "day",
+            Bucket::Week => "week",
+            Bucket::Month => "month",
+        }
+    }
+
+    /// Postgres `interval` spanning one bucket.
+    pub fn interval(self) -> &'static str {
+        match self {
+            Bucket::Hour => "1 hour",
+            Bucket::Day => "1 day",
+            Bucket::Week => "1 week",
+            Bucket::Month => "1 month",
+        }
+    }
+
+    /// Shortest duration of one bucket, used to bound the size of a series.
+    pub fn min_duration(self) -> Duration {
+        match self {
+            Bucket::Hour => Duration::hours(1),
+            Bucket::Day => Duration::days(1),
+            Bucket::Week => Duration::weeks(1),
+            Bucket::Month => Duration::days(28),
+        }
+    }
+}
+
+/// Order slaps are listed in, `created_asc` by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromFormField, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlapOrder {
+    #[field(value = "created_asc")]
+    CreatedAsc,
+    #[field(value = "created_desc")]
+    CreatedDesc,
+    #[field(value = "sentence_asc")]
+    SentenceAsc,
+    #[field(value = "sentence_desc")]
+    SentenceDesc,
+}
+
+impl Default for SlapOrder {
+    fn default() -> Self {
+        SlapOrder::CreatedAsc
+    }
+}
+
+impl SlapOrder {
+    /// `ORDER BY` clause of the order, ties broken by id so that pages never overlap.
+    pub fn order_by(self) -> &'static str {
+        match self {
+            SlapOrder::CreatedAsc => "created_at, id",
+            SlapOrder::CreatedDesc => "created_at DESC, id DESC",
+            SlapOrder::SentenceAsc => "sentence, id",
+            SlapOrder::SentenceDesc => "sentence DESC, id",
+        }
+    }
+
+    /// Condition on the slaps coming after a cursor in the order, given its id as `$10`, its
+    /// creation time as `$11` and its sentence as `$12`.
+    pub fn after(self) -> &'static str {
+        match self {
+            SlapOrder::CreatedAsc => "(created_at, id) > ($11::TIMESTAMPTZ, $10::BIGINT)",
+            SlapOrder::CreatedDesc => "(created_at, id) < ($11::TIMESTAMPTZ, $10::BIGINT)",
+            SlapOrder::SentenceAsc => "(sentence, id) > ($12::BIGINT, $10::BIGINT)",
+            SlapOrder::SentenceDesc => {
+                "(sentence < $12::BIGINT OR sentence = $12::BIGINT AND id > $10::BIGINT)"
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Access to the database from the handlers.
+//!
+//! Rather than letting requests pile up waiting for a connection, the number of requests using the
+//! database at once is bounded. Once the pool and its queue are full, requests are immediately
+//! answered with `503 Service Unavailable` so that clients can back off.
+//!
+//! Reads can also be retried when they fail for reasons unrelated to the request (see
+//! [`ApiError::is_transient`]). Writes must not be, unless they are protected by an idempotency key.
+//!
+//! When connections repeatedly can't be acquired in time, the [`CircuitBreaker`] opens and requests
+//! are answered with `503` right away for a while rather than each waiting for the timeout.
+
+use crate::{config::PoolConfig, error::ApiError};
+use db_adapter::PgPool;
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome, Request},
+    tokio::{
+        sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError},
+        time::sleep,
+    },
+};
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Bounds how many requests may use the database at once.
+pub struct PoolGate(Option<Arc<Semaphore>>);
+
+impl PoolGate {
+    /// As many requests as there are connections and `max_queue` more waiting for one are let in.
+    /// Without `max_queue` any number of requests may wait.
+    pub fn new(config: &PoolConfig) -> Self {
+        PoolGate(config.max_queue.map(|max_queue| {
+            Arc::new(Semaphore::new(
+                (config.max_connections + max_queue) as usize,
+            ))
+        }))
+    }
+
+    /// Lets the caller in, unless the pool and its queue are full. Never waits.
+    pub fn try_enter(&self) -> Result<Option<OwnedSemaphorePermit>, ApiError> {
+        match &self.0 {
+            Some(semaphore) => match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => Ok(Some(permit)),
+                Err(TryAcquireError::NoPermits) | Err(TryAcquireError::Closed) => {
+                    Err(ApiError::Saturated)
+                }
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+/// Fails requests right away once too many connections in a row couldn't be acquired.
+///
+/// The breaker opens after [`PoolConfig::breaker_threshold`] acquisition timeouts in a row and
+/// stays open for [`PoolConfig::breaker_cooldown`]. Requests are then let through again, the first
+/// query to succeed closing it while another timeout opens it right back.
+///
+/// Only the queries run through [`Pool::retry`] are accounted for.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    failures: AtomicU32,
+    opened_at: Mutex<Option<Instant>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: &PoolConfig) -> Self {
+        CircuitBreaker {
+            threshold: config.breaker_threshold,
+            cooldown: config.breaker_cooldown,
+            failures: AtomicU32::new(0),
+            opened_at: Mutex::new(None),
+        }
+    }
+
+    /// Fails with [`ApiError::Unavailable`] while the breaker is open.
+    pub fn check(&self) -> Result<(), ApiError> {
+        let opened_at = *self
+            .opened_at
+            .lock()
+            .expect("the breaker is never poisoned");
+        match opened_at.map(|opened_at| opened_at.elapsed()) {
+            Some(elapsed) if elapsed < self.cooldown => Err(ApiError::Unavailable {
+                retry_after: ((self.cooldown - elapsed).as_secs_f64().ceil() as u64).max(1),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Accounts for the outcome of a query.
+    pub fn record<T>(&self, result: &Result<T, ApiError>) {
+        match result {
+            Ok(_) => {
+                self.failures.store(0, Ordering::Relaxed);
+                *self
+                    .opened_at
+                    .lock()
+                    .expect("the breaker is never poisoned") = None;
+            }
+            Err(ApiError::Unavailable { .. }) => {
+                let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+                if self.threshold > 0 && failures >= self.threshold {
+                    log::warn!(
+                        "{} connections in a row couldn't be acquired, failing requests for {:?}",
+                        failures,
+                        self.cooldown
+                    );
+                    *self
+                        .opened_at
+                        .lock()
+                        .expect("the breaker is never poisoned") = Some(Instant::now());
+                }
+            }
+            // the database answered, even if with an error
+            Err(_) => (),
+        }
+    }
+}
+
+/// Seconds the [`CircuitBreaker`] asked to wait when it failed the request, cached for the `503`
+/// catcher.
+#[derive(Debug, Clone, Copy)]
+pub struct BreakerOpen(pub u64);
+
+/// How queries failing transiently are retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt.
+    pub retries: u32,
+    /// Wait before the first retry, doubled for every following one.
+    pub delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(config: &PoolConfig) -> Self {
+        RetryPolicy {
+            retries: config.retries,
+            delay: config.retry_delay,
+        }
+    }
+
+    /// Runs `query` until it succeeds, fails for good or runs out of retries.
+    pub async fn run<T, E, F, Fut>(&self, mut query: F) -> Result<T, ApiError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Into<ApiError>,
+    {
+        let mut delay = self.delay;
+        let mut retries = 0;
+        loop {
+            match query().await.map_err(Into::into) {
+                Err(err) if retries < self.retries && err.is_transient() => {
+                    log::warn!("retrying a query in {:?}: {}", delay, err);
+                    sleep(delay).await;
+                    delay *= 2;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// The database, for as long as the request lasts.
+pub struct Pool<'r> {
+    pool: &'r PgPool,
+    retries: &'r RetryPolicy,
+    breaker: &'r CircuitBreaker,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl<'r> Pool<'r> {
+    pub fn inner(&self) -> &'r PgPool {
+        self.pool
+    }
+
+    /// Runs `query` on the database following the [`RetryPolicy`].
+    ///
+    /// `query` must be safe to repeat, which is always the case of reads.
+    pub async fn retry<T, E, F, Fut>(&self, mut query: F) -> Result<T, ApiError>
+    where
+        F: FnMut(&'r PgPool) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Into<ApiError>,
+    {
+        let pool = self.pool;
+        let result = self.retries.run(|| query(pool)).await;
+        self.breaker.record(&result);
+        result
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Pool<'r> {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let rocket = request.rocket();
+        let (((pool, gate), retries), breaker) = rocket
+            .state::<PgPool>()
+            .zip(rocket.state::<PoolGate>())
+            .zip(rocket.state::<RetryPolicy>())
+            .zip(rocket.state::<CircuitBreaker>())
+            .expect("the database is managed once ignited");
+        match breaker.check().and_then(|_| gate.try_enter()) {
+            Ok(permit) => Outcome::Success(Pool {
+                pool,
+                retries,
+                breaker,
+                _permit: permit,
+            }),
+            Err(err) => {
+                if let ApiError::Unavailable { retry_after } = err {
+                    request.local_cache(|| Some(BreakerOpen(retry_after)));
+                }
+                Outcome::Failure((err.status(), err))
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Pruning of the slaps older than [`ApiConfig::slap_retention_days`].
+
+use crate::{config::ApiConfig, db};
+use chrono::{Duration, Utc};
+use db_adapter::PgPool;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    tokio::{self, time},
+    Orbit, Rocket,
+};
+
+/// Deletes the slaps of every guild older than `days`, returning how many there were.
+pub async fn prune(pool: &PgPool, days: u32) -> Result<u64, sqlx::Error> {
+    db::prune_slaps(pool, Utc::now() - Duration::days(days.into())).await
+}
+
+/// Prunes the old slaps every [`ApiConfig::prune_interval`] once the linker is launched, until it
+/// is shut down.
+///
+/// Nothing is ever pruned unless `SLAP_RETENTION_DAYS` is set.
+pub struct Prune;
+
+#[rocket::async_trait]
+impl Fairing for Prune {
+    fn info(&self) -> Info {
+        Info {
+            name: "Slap pruning",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let config = rocket.state::<ApiConfig>().cloned().unwrap_or_default();
+        let days = match config.slap_retention_days {
+            Some(days) => days,
+            None => return,
+        };
+        let pool = rocket
+            .state::<PgPool>()
+            .cloned()
+            .expect("the database is managed once ignited");
+        let mut shutdown = rocket.shutdown();
+        let mut interval =
+            time::interval(std::time::Duration::from_secs(config.prune_interval.max(1)));
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => (),
+                    _ = &mut shutdown => break,
+                }
+                match prune(&pool, days).await {
+                    Ok(pruned) => log::info!("pruned {} slaps older than {} days", pruned, days),
+                    Err(err) => log::error!("couldn't prune the old slaps: {}", err),
+                }
+            }
+        });
+    }
+}
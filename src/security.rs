@@ -0,0 +1,19 @@
+//! Security headers of the responses, which scanners expect even though the API serves no pages.
+
+use crate::config::ApiConfig;
+use rocket::shield::{Frame, NoSniff, Referrer, Shield};
+
+/// The [`Shield`] setting `X-Content-Type-Options: nosniff`, `X-Frame-Options: DENY` and
+/// `Referrer-Policy: no-referrer` on every response, or none of them unless
+/// [`ApiConfig::security_headers`].
+///
+/// It replaces rocket's default one. Headers set by a route itself are never overridden.
+pub fn shield(config: &ApiConfig) -> Shield {
+    if !config.security_headers {
+        return Shield::new();
+    }
+    Shield::new()
+        .enable(NoSniff::Enable)
+        .enable(Frame::Deny)
+        .enable(Referrer::NoReferrer)
+}
@@ -0,0 +1,1264 @@
+//! Endpoints about the slaps of guilds and of their members.
+
+use crate::{
+    audit::Audit,
+    auth::Admin,
+    body::Body,
+    config::ApiConfig,
+    db::{self, NewSlap, SlapFilter, SlapRow},
+    error::{ApiError, ApiResult},
+    feed::SlapFeed,
+    format::Negotiated,
+    id::Snowflake,
+    idempotency::{fingerprint, IdempotencyKey, IdempotencyStore},
+    page::{Cursor, Page, Paginated},
+    params::{bigint, count, snowflake, timestamp, OptionalCount, OptionalTimestamp, SlapOrder},
+    pool::Pool,
+    webhook::Webhook,
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use csv::{Position, ReaderBuilder, StringRecord};
+use rocket::{
+    data::{ByteUnit, Data, Limits},
+    delete,
+    form::FromForm,
+    get,
+    http::ContentType,
+    post,
+    response::stream::{Event, EventStream, TextStream},
+    routes,
+    serde::json::Json,
+    tokio::{select, sync::broadcast::error::RecvError},
+    Route, Shutdown, State,
+};
+use serde::{Deserialize, Serialize};
+use tokio_stream::StreamExt;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        gsr_len,
+        gsr_count,
+        gsr_recent_count,
+        gsr_timeseries,
+        gsr_slaps,
+        gsr_offenders,
+        gsr_offenders_detailed,
+        gsr_top_enforcers,
+        gsr_recent,
+        gsr_search,
+        gsr_stream,
+        gsr_export,
+        gsr_import,
+        gsr_report,
+        gsr_report_exists,
+        gsr_pardon,
+        new_slap,
+        new_slaps,
+        gsr_clear,
+        gsr_transfer,
+        gsr_reassign_enforcer,
+        msr_len,
+        msr_slaps,
+        msr_exceeds,
+        msr_sentence,
+        msr_active,
+        msr_recent,
+    ]
+}
+
+/// A slap, as answered to clients.
+#[derive(Debug, Clone, Serialize)]
+pub struct Slap {
+    pub id: i64,
+    pub sentence: u64,
+    pub offender: Snowflake,
+    /// Who gave the slap, `null` when no one in particular did.
+    pub enforcer: Option<Snowflake>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Left out unless the slap was pardoned, see [`gsr_pardon()`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pardon: Option<Pardon>,
+}
+
+/// Reversal of a slap.
+#[derive(Debug, Clone, Serialize)]
+pub struct Pardon {
+    pub at: DateTime<Utc>,
+    /// Who pardoned the slap, `null` when no one in particular did.
+    pub by: Option<Snowflake>,
+    pub reason: String,
+}
+
+impl From<SlapRow> for Slap {
+    fn from(row: SlapRow) -> Self {
+        let by = row.pardoned_by.map(|id| Snowflake(id as u64));
+        let reason = row.pardon_reason.unwrap_or_default();
+        Slap {
+            id: row.id,
+            sentence: row.sentence as u64,
+            offender: Snowflake(row.offender as u64),
+            enforcer: row.enforcer.map(|id| Snowflake(id as u64)),
+            reason: row.reason,
+            created_at: row.created_at,
+            pardon: row.pardoned_at.map(|at| Pardon { at, by, reason }),
+        }
+    }
+}
+
+/// `GET` up to `number` [`Slap`]s from the guild, skipping the first `offset` ones.
+///
+/// The reports are wrapped in a [`Paginated`] envelope whose `total` tells how many reports there
+/// are in all, so that clients know when they reached the end.
+///
+/// Only the reports whose sentence is between `min_sentence` and `max_sentence` (inclusive) are
+/// given when those are provided.
+///
+/// Reports are sorted by `sort`, one of `created_asc` (the default), `created_desc`, `sentence_asc`
+/// and `sentence_desc`. Reports created or sentenced alike are sorted by id.
+///
+/// Reports only hold the comma-separated `fields` when those are provided, among those of a
+/// [`Slap`]. All of them are given otherwise.
+///
+/// Pardoned reports are left out, both from the reports and the `total`, unless `include_removed`
+/// (or `include_pardoned`, see [`gsr_len()`]).
+///
+/// `number` defaults to [`ApiConfig::default_page_size`] and can't exceed
+/// [`ApiConfig::max_page_size`].
+///
+/// Pages also hold a `next_cursor` unless they are the last one. Given back as `cursor`, the next
+/// page starts right after the last report of this one, whatever was inserted or deleted in the
+/// meantime, and `offset` counts from there. The `sort` of the previous page is kept when it isn't
+/// given again.
+///
+/// # Errors
+///
+/// Aside from failures from the underlying database, the request fails with `NumberTooLarge` if
+/// `number` or `offset` is greater than the largest `usize`, 2^32 - 1 or 2^64 - 1 depending on the
+/// platform, and with `InvalidNumber` if either isn't a number. It also fails if `min_sentence` is greater than
+/// `max_sentence` or either is greater than 2^63 - 1, if one of the `fields` isn't one of a
+/// [`Slap`], or if `cursor` isn't a `next_cursor` of a page with the same `sort`.
+#[get(
+    "/slaps/<guild>/reports?<number>&<offset>&<min_sentence>&<max_sentence>&<sort>&<fields>&<include_pardoned>&<include_removed>&<cursor>"
+)]
+async fn gsr_slaps(
+    settings: &State<ApiConfig>,
+    guild: u64,
+    number: OptionalCount<'_>,
+    offset: OptionalCount<'_>,
+    min_sentence: Option<u64>,
+    max_sentence: Option<u64>,
+    sort: Option<SlapOrder>,
+    fields: Option<&str>,
+    include_pardoned: Option<bool>,
+    include_removed: Option<bool>,
+    cursor: Option<&str>,
+    pool: Pool<'_>,
+) -> ApiResult<Negotiated<Paginated<serde_json::Value>>> {
+    let guild = snowflake("guild", guild)?;
+    let filter = SlapFilter {
+        include_pardoned: include_removed.or(include_pardoned).unwrap_or(false),
+        ..sentence_filter(min_sentence, max_sentence)?
+    };
+    let number = count("number", number)?;
+    let offset = count("offset", offset)?;
+    let fields = fields.map(slap_fields).transpose()?;
+    let after = cursor.map(Cursor::decode).transpose()?;
+    let order = match (after, sort) {
+        (Some(after), Some(sort)) if after.order != sort => return Err(ApiError::InvalidCursor),
+        (Some(after), _) => after.order,
+        (None, sort) => sort.unwrap_or_default(),
+    };
+    let page = settings.page(number, offset);
+
+    // one more report than asked tells whether there is a next page
+    let lookahead = Page {
+        limit: page.limit + 1,
+        ..page
+    };
+    let mut rows = pool
+        .retry(|pool| db::slaps(pool, guild, &filter, order, lookahead, after.as_ref()))
+        .await?;
+    let next_cursor = if rows.len() > page.limit {
+        rows.truncate(page.limit);
+        rows.last().map(|last| Cursor::after(order, last).encode())
+    } else {
+        None
+    };
+    let total = pool
+        .retry(|pool| db::count_slaps(pool, guild, &filter))
+        .await?;
+    let slaps: Vec<Slap> = rows.into_iter().map(Slap::from).collect();
+    Ok(Negotiated(
+        Paginated {
+            next_cursor,
+            ..Paginated::new(slaps, total, page)
+        }
+        .map(|slap| sparse(slap, fields.as_deref())),
+    ))
+}
+
+/// Fields of a [`Slap`], as they are serialized.
+const SLAP_FIELDS: &[&str] = &[
+    "id",
+    "sentence",
+    "offender",
+    "enforcer",
+    "reason",
+    "created_at",
+    "pardon",
+];
+
+/// The comma-separated `fields`, which must all be [`SLAP_FIELDS`].
+fn slap_fields(fields: &str) -> ApiResult<Vec<&str>> {
+    fields
+        .split(',')
+        .map(str::trim)
+        .map(|field| {
+            if SLAP_FIELDS.contains(&field) {
+                Ok(field)
+            } else {
+                Err(ApiError::UnknownField {
+                    field: field.into(),
+                    expected: SLAP_FIELDS,
+                })
+            }
+        })
+        .collect()
+}
+
+/// `slap` holding only `fields` if provided.
+fn sparse(slap: Slap, fields: Option<&[&str]>) -> serde_json::Value {
+    let json = serde_json::to_value(slap).expect("slaps are always serializable");
+    match (json, fields) {
+        (serde_json::Value::Object(mut slap), Some(fields)) => fields
+            .iter()
+            .filter_map(|&field| slap.remove(field).map(|value| (field.to_string(), value)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        (json, _) => json,
+    }
+}
+
+/// A [`Page`] of the slaps of `guild` passing `filter`, in `order`.
+async fn paginated_slaps(
+    pool: Pool<'_>,
+    guild: u64,
+    filter: &SlapFilter,
+    order: SlapOrder,
+    page: Page,
+) -> ApiResult<Negotiated<Paginated<Slap>>> {
+    Ok(Negotiated(Paginated::new(
+        pool.retry(|pool| db::slaps(pool, guild, filter, order, page, None))
+            .await?
+            .into_iter()
+            .map(Slap::from)
+            .collect(),
+        pool.retry(|pool| db::count_slaps(pool, guild, filter))
+            .await?,
+        page,
+    )))
+}
+
+/// [`SlapFilter`] for sentences between `min` and `max`, given `min` isn't greater than `max` and
+/// both can be compared with the sentences the database holds.
+fn sentence_filter(min: Option<u64>, max: Option<u64>) -> ApiResult<SlapFilter> {
+    let min = min.map(|min| bigint("min_sentence", min)).transpose()?;
+    let max = max.map(|max| bigint("max_sentence", max)).transpose()?;
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            return Err(ApiError::InvalidSentenceRange { min, max });
+        }
+    }
+    Ok(SlapFilter {
+        min_sentence: min,
+        max_sentence: max,
+        ..SlapFilter::default()
+    })
+}
+
+/// `GET` up to `number` ids ([`Snowflake`]) of the members who were slapped in the guild, skipping
+/// the first `offset` ones.
+///
+/// Offenders are ordered by id so that paging through them with `offset` neither skips nor repeats
+/// any, as long as no one is slapped for the first time in the meantime. `number` is bounded like
+/// in [`gsr_slaps()`].
+///
+/// # Errors
+///
+/// Aside from failures from the underlying database, the request fails like [`gsr_slaps()`] if
+/// `number` or `offset` is too large or isn't a number.
+#[get("/slaps/<guild>/offenders?<number>&<offset>")]
+async fn gsr_offenders(
+    settings: &State<ApiConfig>,
+    guild: u64,
+    number: OptionalCount<'_>,
+    offset: OptionalCount<'_>,
+    pool: Pool<'_>,
+) -> ApiResult<Negotiated<Vec<Snowflake>>> {
+    let guild = snowflake("guild", guild)?;
+    let number = count("number", number)?;
+    let offset = count("offset", offset)?;
+    let page = settings.page(number, offset);
+    Ok(Negotiated(
+        pool.retry(|pool| db::offenders(pool, guild, page))
+            .await?
+            .into_iter()
+            .map(Snowflake)
+            .collect(),
+    ))
+}
+
+/// An offender of a guild along with a summary of their slaps.
+#[derive(Debug, Serialize)]
+pub struct Offender {
+    pub offender: Snowflake,
+    pub slap_count: u64,
+    /// Sum of the sentences of the slaps, see [`msr_sentence()`].
+    pub total_sentence: u64,
+    pub last_slap_at: DateTime<Utc>,
+}
+
+/// `GET` up to `number` [`Offender`]s of the guild, skipping the first `offset` ones.
+///
+/// Same as [`gsr_offenders()`], with a summary of the slaps of every offender.
+#[get("/slaps/<guild>/offenders/detailed?<number>&<offset>")]
+async fn gsr_offenders_detailed(
+    settings: &State<ApiConfig>,
+    guild: u64,
+    number: OptionalCount<'_>,
+    offset: OptionalCount<'_>,
+    pool: Pool<'_>,
+) -> ApiResult<Negotiated<Vec<Offender>>> {
+    let guild = snowflake("guild", guild)?;
+    let number = count("number", number)?;
+    let offset = count("offset", offset)?;
+    let page = settings.page(number, offset);
+    Ok(Negotiated(
+        pool.retry(|pool| db::offender_rows(pool, guild, page))
+            .await?
+            .into_iter()
+            .map(|row| Offender {
+                offender: Snowflake(row.offender as u64),
+                slap_count: row.slap_count as u64,
+                total_sentence: row.total_sentence as u64,
+                last_slap_at: row.last_slap_at,
+            })
+            .collect(),
+    ))
+}
+
+/// A member along with how many slaps they gave.
+#[derive(Debug, Serialize)]
+pub struct Enforcer {
+    pub enforcer: Snowflake,
+    pub count: u64,
+}
+
+/// `GET` the `number` [`Enforcer`]s who gave the most slaps in the guild, most first.
+///
+/// Enforcers who gave as many slaps are ordered by id. Slaps without an enforcer aren't counted.
+/// `number` is bounded like in [`gsr_slaps()`].
+#[get("/slaps/<guild>/enforcers/top?<number>")]
+async fn gsr_top_enforcers(
+    settings: &State<ApiConfig>,
+    guild: u64,
+    number: OptionalCount<'_>,
+    pool: Pool<'_>,
+) -> ApiResult<Negotiated<Vec<Enforcer>>> {
+    let guild = snowflake("guild", guild)?;
+    let number = count("number", number)?;
+    let page = settings.page(number, None);
+    Ok(Negotiated(
+        pool.retry(|pool| db::top_enforcers(pool, guild, page.limit))
+            .await?
+            .into_iter()
+            .map(|(enforcer, count)| Enforcer {
+                enforcer: Snowflake(enforcer),
+                count,
+            })
+            .collect(),
+    ))
+}
+
+/// `GET` the `number` most recent [`Slap`]s of the guild, newest first.
+///
+/// Same as [`gsr_slaps()`] sorted by `created_desc`, without the envelope.
+#[get("/slaps/<guild>/recent?<number>")]
+async fn gsr_recent(
+    settings: &State<ApiConfig>,
+    guild: u64,
+    number: OptionalCount<'_>,
+    pool: Pool<'_>,
+) -> ApiResult<Negotiated<Vec<Slap>>> {
+    let guild = snowflake("guild", guild)?;
+    let number = count("number", number)?;
+    recent(&pool, settings, guild, SlapFilter::default(), number).await
+}
+
+/// The `number` most recent [`Slap`]s of the guild let through `filter`, newest first.
+async fn recent(
+    pool: &Pool<'_>,
+    settings: &ApiConfig,
+    guild: u64,
+    filter: SlapFilter,
+    number: Option<usize>,
+) -> ApiResult<Negotiated<Vec<Slap>>> {
+    let page = settings.page(number, None);
+    Ok(Negotiated(
+        pool.retry(|pool| db::slaps(pool, guild, &filter, SlapOrder::CreatedDesc, page, None))
+            .await?
+            .into_iter()
+            .map(Slap::from)
+            .collect(),
+    ))
+}
+
+/// `GET` up to `number` [`Slap`]s of the guild whose reason contains `q`, whatever the case,
+/// skipping the first `offset` ones.
+///
+/// `q` is matched as is: `%` and `_` aren't wildcards. Supports the same `number` bounds, `sort`
+/// and [`Paginated`] envelope as [`gsr_slaps()`].
+///
+/// # Errors
+///
+/// Fails with `400` if `q` is empty or only made of whitespace.
+#[get("/slaps/<guild>/search?<q>&<number>&<offset>&<sort>")]
+async fn gsr_search(
+    settings: &State<ApiConfig>,
+    guild: u64,
+    q: &str,
+    number: OptionalCount<'_>,
+    offset: OptionalCount<'_>,
+    sort: Option<SlapOrder>,
+    pool: Pool<'_>,
+) -> ApiResult<Negotiated<Paginated<Slap>>> {
+    let guild = snowflake("guild", guild)?;
+    let number = count("number", number)?;
+    let offset = count("offset", offset)?;
+    let q = q.trim();
+    if q.is_empty() {
+        return Err(ApiError::EmptyParam("q"));
+    }
+    let filter = SlapFilter {
+        reason: Some(q.to_string()),
+        ..SlapFilter::default()
+    };
+    let order = sort.unwrap_or_default();
+    paginated_slaps(pool, guild, &filter, order, settings.page(number, offset)).await
+}
+
+/// `GET` the number of slaps in the guild, but the pardoned ones unless `include_pardoned`.
+///
+/// `include_removed` is the same as `include_pardoned`, like for the other counts, as pardoning is
+/// how slaps are removed.
+#[get("/slaps/<guild>/len?<include_pardoned>&<include_removed>")]
+async fn gsr_len(
+    pool: Pool<'_>,
+    guild: u64,
+    include_pardoned: Option<bool>,
+    include_removed: Option<bool>,
+) -> ApiResult<Json<usize>> {
+    let guild = snowflake("guild", guild)?;
+    let filter = SlapFilter {
+        include_pardoned: include_removed.or(include_pardoned).unwrap_or(false),
+        ..SlapFilter::default()
+    };
+    Ok(Json(
+        pool.retry(|pool| db::count_slaps(pool, guild, &filter))
+            .await? as usize,
+    ))
+}
+
+/// `GET` the number of slaps created in the guild from `since` until `until`, both optional RFC 3339
+/// timestamps. The pardoned ones are left out unless `include_removed` (or `include_pardoned`, see
+/// [`gsr_len()`]).
+///
+/// # Errors
+///
+/// Fails with `400` if either bound isn't a valid timestamp or if `since` is after `until`.
+#[get("/slaps/<guild>/count?<since>&<until>&<include_pardoned>&<include_removed>")]
+async fn gsr_count(
+    pool: Pool<'_>,
+    guild: u64,
+    since: OptionalTimestamp<'_>,
+    until: OptionalTimestamp<'_>,
+    include_pardoned: Option<bool>,
+    include_removed: Option<bool>,
+) -> ApiResult<Json<usize>> {
+    let guild = snowflake("guild", guild)?;
+    let include_pardoned = include_removed.or(include_pardoned).unwrap_or(false);
+    let (since, until) = (timestamp("since", since)?, timestamp("until", until)?);
+    if let (Some(since), Some(until)) = (since, until) {
+        if since > until {
+            return Err(ApiError::InvalidTimeRange("`since` is after `until`"));
+        }
+    }
+    Ok(Json(
+        pool.retry(|pool| db::count_slaps_between(pool, guild, since, until, include_pardoned))
+            .await? as usize,
+    ))
+}
+
+/// Widest window of [`gsr_recent_count()`], a week.
+const MAX_RECENT_MINUTES: u32 = 7 * 24 * 60;
+
+/// `GET` the number of slaps created in the guild in the last `minutes`, but the pardoned ones
+/// unless `include_removed` (or `include_pardoned`, see [`gsr_len()`]).
+///
+/// # Errors
+///
+/// Fails with `400` unless `minutes` is between 1 and [`MAX_RECENT_MINUTES`].
+#[get("/slaps/<guild>/recent_count?<minutes>&<include_pardoned>&<include_removed>")]
+async fn gsr_recent_count(
+    pool: Pool<'_>,
+    guild: u64,
+    minutes: u32,
+    include_pardoned: Option<bool>,
+    include_removed: Option<bool>,
+) -> ApiResult<Json<usize>> {
+    let guild = snowflake("guild", guild)?;
+    let include_pardoned = include_removed.or(include_pardoned).unwrap_or(false);
+    if minutes == 0 || minutes > MAX_RECENT_MINUTES {
+        return Err(ApiError::InvalidTimeRange(
+            "`minutes` must be between 1 and 10080 (a week)",
+        ));
+    }
+    Ok(Json(
+        pool.retry(|pool| db::count_recent_slaps(pool, guild, minutes, include_pardoned))
+            .await? as usize,
+    ))
+}
+
+/// Widest window of [`gsr_timeseries()`], a year.
+const MAX_TIMESERIES_DAYS: usize = 366;
+
+/// `GET` the number of slaps created in the guild and the sum of their sentences for each of the
+/// last `days` (30 by default), today included, oldest first. Pardoned slaps are left out unless
+/// `include_removed` (or `include_pardoned`, see [`gsr_len()`]).
+///
+/// Days are those of the timezone of the guild, UTC if it has none. Days without any slap are
+/// present with a `count` of 0.
+///
+/// # Errors
+///
+/// Fails with `400` unless `days` is between 1 and [`MAX_TIMESERIES_DAYS`].
+#[get("/slaps/<guild>/timeseries?<days>&<include_pardoned>&<include_removed>")]
+async fn gsr_timeseries(
+    pool: Pool<'_>,
+    guild: u64,
+    days: OptionalCount<'_>,
+    include_pardoned: Option<bool>,
+    include_removed: Option<bool>,
+) -> ApiResult<Negotiated<Vec<db::DayCount>>> {
+    let guild = snowflake("guild", guild)?;
+    let include_pardoned = include_removed.or(include_pardoned).unwrap_or(false);
+    let days = count("days", days)?.unwrap_or(30);
+    if days == 0 || days > MAX_TIMESERIES_DAYS {
+        return Err(ApiError::InvalidTimeRange(
+            "`days` must be between 1 and 366 (a year)",
+        ));
+    }
+    let timezone = timezone(&pool, guild).await?;
+    Ok(Negotiated(
+        pool.retry(|pool| {
+            db::slaps_per_day(pool, guild, timezone.name(), days as u32, include_pardoned)
+        })
+        .await?,
+    ))
+}
+
+/// Timezone of the guild, UTC if it has none.
+async fn timezone(pool: &Pool<'_>, guild: u64) -> ApiResult<Tz> {
+    Ok(pool
+        .retry(|pool| db::timezone(pool, guild))
+        .await?
+        // only valid timezones are ever stored
+        .and_then(|timezone| timezone.parse().ok())
+        .unwrap_or(Tz::UTC))
+}
+
+/// `GET` the [`Slap`]s created in the guild from now on as a stream of server-sent events.
+///
+/// Each event holds one report as JSON. The stream ends when the server shuts down.
+#[get("/slaps/<guild>/stream")]
+fn gsr_stream(
+    guild: u64,
+    feed: &State<SlapFeed>,
+    mut shutdown: Shutdown,
+) -> ApiResult<EventStream![]> {
+    let guild = snowflake("guild", guild)?;
+    let mut slaps = feed.subscribe();
+    Ok(EventStream! {
+        loop {
+            let report = select! {
+                slap = slaps.recv() => match slap {
+                    Ok((slapped_guild, report)) if slapped_guild == guild => report,
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+                _ = &mut shutdown => break,
+            };
+            yield Event::json(&report);
+        }
+    })
+}
+
+/// `GET` the [`Slap`] `report` of the guild.
+///
+/// # Errors
+///
+/// Fails with `404` if the guild has no such report, even if another guild does.
+// ranked after the routes whose last segment is static, such as `gsr_len()`
+#[get("/slaps/<guild>/<report>", rank = 2)]
+async fn gsr_report(pool: Pool<'_>, guild: u64, report: i64) -> ApiResult<Json<Slap>> {
+    let guild = snowflake("guild", guild)?;
+    pool.retry(|pool| db::slap(pool, guild, report))
+        .await?
+        .map(|row| Json(Slap::from(row)))
+        .ok_or_else(|| ApiError::not_found("report", report))
+}
+
+/// `GET` whether the guild has the slap `report`, cheaper than [`gsr_report()`] to check a cached id.
+#[get("/slaps/<guild>/<report>/exists")]
+async fn gsr_report_exists(pool: Pool<'_>, guild: u64, report: i64) -> ApiResult<Json<bool>> {
+    let guild = snowflake("guild", guild)?;
+    Ok(Json(
+        pool.retry(|pool| db::slap_exists(pool, guild, report))
+            .await?,
+    ))
+}
+
+/// Why and by whom a slap is pardoned, see [`gsr_pardon()`].
+#[derive(Debug, Deserialize)]
+struct PardonPayload {
+    reason: String,
+    by: Option<Snowflake>,
+}
+
+/// `POST` a JSON object with the `reason` of the pardon of the slap `report`, and optionally who
+/// pardons it (`by`), returning the pardoned [`Slap`].
+///
+/// Pardoned slaps are kept, with their [`Pardon`], but are left out of the listings and counts of
+/// slaps unless they say otherwise. They don't weigh on the severity of the offender either.
+///
+/// # Errors
+///
+/// Fails with `400` if the `reason` is blank, with `404` if there is no such report and with `409`
+/// if it was already pardoned.
+#[post("/slaps/<guild>/<report>/pardon", data = "<pardon>", format = "json")]
+async fn gsr_pardon(
+    _admin: Admin,
+    pool: Pool<'_>,
+    audit: Audit,
+    guild: u64,
+    report: i64,
+    pardon: Json<PardonPayload>,
+) -> ApiResult<Json<Slap>> {
+    let guild = snowflake("guild", guild)?;
+    let pardon_reason =
+        reason(Some(pardon.reason.as_str())).ok_or(ApiError::EmptyParam("reason"))?;
+    let new = db::NewPardon {
+        by: pardon.by.map(|by| snowflake("by", by.0)).transpose()?,
+        reason: pardon_reason,
+    };
+    let entry = audit.entry(
+        guild,
+        format!("pardoned report {} for {}", report, pardon_reason),
+    );
+    if let Some(row) = db::pardon_slap(pool.inner(), guild, report, new, &entry).await? {
+        return Ok(Json(Slap::from(row)));
+    }
+    if pool
+        .retry(|pool| db::slap_exists(pool, guild, report))
+        .await?
+    {
+        Err(ApiError::AlreadyPardoned(report))
+    } else {
+        Err(ApiError::not_found("report", report))
+    }
+}
+
+/// Header of CSV exports, [`csv_line`] gives the matching rows.
+const CSV_HEADER: &str = "id,offender,enforcer,sentence,reason,created_at\n";
+
+/// Line of a CSV export for `slap`, dated in `timezone`.
+fn csv_line(slap: &SlapRow, timezone: Tz) -> String {
+    format!(
+        "{},{},{},{},{},{}\n",
+        slap.id,
+        slap.offender,
+        slap.enforcer.map(|id| id.to_string()).unwrap_or_default(),
+        slap.sentence,
+        slap.reason.as_deref().map(csv_field).unwrap_or_default(),
+        slap.created_at.with_timezone(&timezone).to_rfc3339(),
+    )
+}
+
+/// Quotes `field` when it would otherwise break the CSV.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// `GET` every slap of the guild as CSV, starting with a header row.
+///
+/// Timestamps are in the timezone of the guild, UTC if it has none. Imports read the offset back so
+/// exports can be imported anywhere.
+///
+/// Reports are streamed from the database as they are written so that exports of any size use a
+/// bounded amount of memory. Rows are only fetched as the client reads the previous ones, so a
+/// slow client slows the query down rather than having rows pile up. The response has no
+/// `Content-Length` and is neither compressed nor indented, which would need the whole body.
+///
+/// Should the database fail midway the export is cut short, since the response status was already
+/// sent.
+#[get("/slaps/<guild>/export.csv")]
+async fn gsr_export(
+    guild: u64,
+    pool: Pool<'_>,
+) -> ApiResult<(ContentType, TextStream![String + '_])> {
+    let guild = snowflake("guild", guild)?;
+    let timezone = timezone(&pool, guild).await?;
+    let stream = TextStream! {
+        yield CSV_HEADER.to_string();
+        let mut slaps = Box::pin(db::stream_slaps(pool.inner(), guild));
+        while let Some(slap) = slaps.next().await {
+            match slap {
+                Ok(slap) => yield csv_line(&slap, timezone),
+                Err(err) => {
+                    log::error!("export of the slaps of {} failed: {}", guild, err);
+                    break;
+                }
+            }
+        }
+    };
+    Ok((ContentType::CSV, stream))
+}
+
+/// Most bytes of a CSV import, unless rocket's `csv` limit says otherwise.
+const IMPORT_LIMIT: ByteUnit = ByteUnit::Mebibyte;
+
+/// Row of a CSV import which couldn't be imported, see [`gsr_import()`].
+#[derive(Debug, Serialize)]
+struct RowError {
+    /// Line of the row, starting from 1 for the header.
+    line: u64,
+    reason: String,
+}
+
+/// Outcome of a CSV import, see [`gsr_import()`].
+#[derive(Debug, Serialize)]
+struct Import {
+    imported: u64,
+    errors: Vec<RowError>,
+}
+
+/// `POST` slaps as CSV, with the same header and columns as [`gsr_export()`], to add them to the
+/// guild. Returns how many were imported along with the rows which weren't and why.
+///
+/// Malformed rows are skipped, without preventing the well-formed ones from being imported all at
+/// once. The `id` column is ignored since imported slaps are given new ids, while they keep their
+/// `created_at`. Being historical, they are neither published to the feed nor to the [`Webhook`].
+///
+/// # Errors
+///
+/// Fails with `400` if the header isn't that of exports, and with `413` if the body is larger than
+/// rocket's `csv` limit (1 MiB by default). Rows whose sentence is above
+/// [`ApiConfig::max_sentence`] are skipped like the malformed ones.
+#[post("/slaps/<guild>/import", data = "<body>")]
+async fn gsr_import(
+    _admin: Admin,
+    pool: Pool<'_>,
+    audit: Audit,
+    settings: &State<ApiConfig>,
+    limits: &Limits,
+    guild: u64,
+    body: Data<'_>,
+) -> ApiResult<Json<Import>> {
+    let guild = snowflake("guild", guild)?;
+    let limit = limits.get("csv").unwrap_or(IMPORT_LIMIT);
+    let body = body
+        .open(limit)
+        .into_string()
+        .await
+        .map_err(|err| ApiError::InvalidCsv(err.to_string()))?;
+    if !body.is_complete() {
+        return Err(ApiError::TooLarge(limit));
+    }
+
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(body.as_bytes());
+    let header = reader
+        .headers()
+        .map_err(|err| ApiError::InvalidCsv(err.to_string()))?;
+    if header.iter().collect::<Vec<_>>().join(",") != CSV_HEADER.trim_end() {
+        return Err(ApiError::InvalidCsv(format!(
+            "expected the header `{}`",
+            CSV_HEADER.trim_end()
+        )));
+    }
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for record in reader.records() {
+        let row = record
+            .map_err(|err| (err.position().map_or(0, Position::line), err.to_string()))
+            .and_then(|record| {
+                csv_row(&record, settings.max_sentence())
+                    .map_err(|reason| (record.position().map_or(0, Position::line), reason))
+            });
+        match row {
+            Ok(row) => rows.push(row),
+            Err((line, reason)) => errors.push(RowError { line, reason }),
+        }
+    }
+
+    if !rows.is_empty() {
+        let slaps = rows
+            .iter()
+            .map(|row| {
+                let slap = NewSlap {
+                    guild,
+                    sentence: row.sentence,
+                    offender: row.offender,
+                    enforcer: row.enforcer,
+                    reason: row.reason.as_deref(),
+                };
+                (slap, row.created_at)
+            })
+            .collect::<Vec<_>>();
+        let entry = audit.entry(guild, format!("imported {} slaps", slaps.len()));
+        db::import_slaps(pool.inner(), &slaps, &entry).await?;
+    }
+    Ok(Json(Import {
+        imported: rows.len() as u64,
+        errors,
+    }))
+}
+
+/// Slap of a CSV import, see [`csv_row`].
+#[derive(Debug)]
+struct CsvRow {
+    sentence: u64,
+    offender: u64,
+    enforcer: Option<u64>,
+    reason: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+/// Parses a `record` of a CSV import, whichever its id, whose sentence can't be above
+/// `max_sentence`. Tells what is wrong with it otherwise.
+fn csv_row(record: &StringRecord, max_sentence: u64) -> Result<CsvRow, String> {
+    let columns = CSV_HEADER.split(',').count();
+    if record.len() != columns {
+        return Err(format!(
+            "expected {} columns, found {}",
+            columns,
+            record.len()
+        ));
+    }
+    let id = |column: &'static str, value: &str| {
+        value
+            .parse()
+            .ok()
+            .and_then(|id| snowflake(column, id).ok())
+            .ok_or_else(|| format!("`{}` must be a Discord id, found `{}`", column, value))
+    };
+    Ok(CsvRow {
+        offender: id("offender", &record[1])?,
+        enforcer: match &record[2] {
+            "" => None,
+            enforcer => Some(id("enforcer", enforcer)?),
+        },
+        sentence: record[3]
+            .parse()
+            .map_err(|_| {
+                format!(
+                    "`sentence` must be a positive integer, found `{}`",
+                    &record[3]
+                )
+            })
+            .and_then(|sentence| {
+                check_sentence(sentence, max_sentence).map_err(|err| err.to_string())
+            })?,
+        reason: reason(Some(&record[4])).map(str::to_string),
+        created_at: DateTime::parse_from_rfc3339(&record[5])
+            .map(|time| time.with_timezone(&Utc))
+            .map_err(|_| {
+                format!(
+                    "`created_at` must be an RFC 3339 timestamp, found `{}`",
+                    &record[5]
+                )
+            })?,
+    })
+}
+
+#[derive(Debug, FromForm, Deserialize)]
+struct SlapForm {
+    guild: Snowflake,
+    sentence: u64,
+    offender: Snowflake,
+    enforcer: Option<Snowflake>,
+    reason: Option<String>,
+}
+
+/// `POST` a new slap, as a form or a JSON object, returning the created [`Slap`].
+///
+/// The `reason` is trimmed, one made of whitespace only is the same as none. The `enforcer` may be
+/// left out for slaps given by no one in particular, it is then `null` in the report.
+///
+/// When an `Idempotency-Key` header is sent, repeating the request with the same key in the same
+/// guild returns the report created by the first one instead of slapping again. Keys are kept for
+/// `IDEMPOTENCY_TTL` seconds (a day by default).
+///
+/// The report is also sent to the [`Webhook`], if any, without waiting for it to be delivered.
+///
+/// # Errors
+///
+/// Fails with `422` if the `sentence` is above [`ApiConfig::max_sentence`]. A sentence of 0 is a
+/// mere warning, which is allowed.
+///
+/// Fails with `422` as well if the `Idempotency-Key` was already used for a different slap.
+#[post("/slaps/new", data = "<slap>")]
+async fn new_slap(
+    pool: Pool<'_>,
+    audit: Audit,
+    settings: &State<ApiConfig>,
+    feed: &State<SlapFeed>,
+    webhook: &State<Webhook>,
+    replays: &State<IdempotencyStore<Slap>>,
+    key: IdempotencyKey,
+    slap: Body<SlapForm>,
+) -> ApiResult<Json<Slap>> {
+    let guild = snowflake("guild", slap.guild.0)?;
+    let enforcer = slap
+        .enforcer
+        .map(|enforcer| snowflake("enforcer", enforcer.0))
+        .transpose()?;
+    let new = NewSlap {
+        guild,
+        sentence: check_sentence(slap.sentence, settings.max_sentence())?,
+        offender: snowflake("offender", slap.offender.0)?,
+        enforcer,
+        reason: reason(slap.reason.as_deref()),
+    };
+    let entry = audit.entry(guild, summary(&new));
+    let create = || async {
+        let mut tx = pool.inner().begin().await?;
+        let report = Slap::from(db::insert_slap(&mut tx, new).await?);
+        db::record_audit(&mut tx, &entry).await?;
+        tx.commit().await?;
+        webhook.notify(guild, &report);
+        feed.publish(guild, report.clone());
+        Ok::<_, ApiError>(report)
+    };
+    Ok(Json(match key.0 {
+        Some(key) => {
+            replays
+                .get_or_try_insert(guild, key, fingerprint(&new), create)
+                .await?
+        }
+        None => create().await?,
+    }))
+}
+
+/// `sentence`, given it isn't above `max`.
+fn check_sentence(sentence: u64, max: u64) -> ApiResult<u64> {
+    if sentence > max {
+        return Err(ApiError::SentenceTooLarge { sentence, max });
+    }
+    Ok(sentence)
+}
+
+/// `reason` without its surrounding whitespace, if there is anything left of it.
+///
+/// An empty reason is no reason, so it is stored as none rather than rejected.
+fn reason(reason: Option<&str>) -> Option<&str> {
+    reason.map(str::trim).filter(|reason| !reason.is_empty())
+}
+
+/// Summary of the creation of `slap` in the audit log.
+fn summary(slap: &NewSlap<'_>) -> String {
+    format!("slapped {} for {}", slap.offender, slap.sentence)
+}
+
+/// Slap of a batch, see [`new_slaps()`].
+#[derive(Debug, Deserialize)]
+struct SlapPayload {
+    guild: Snowflake,
+    sentence: u64,
+    offender: Snowflake,
+    enforcer: Option<Snowflake>,
+    reason: Option<String>,
+}
+
+/// `POST` a JSON array of slaps to create them all at once, returning the created [`Slap`]s in the
+/// same order.
+///
+/// Either every slap is created or none is. The created slaps are then published like those of
+/// [`new_slap()`], and their sentences are bounded the same way.
+#[post("/slaps/batch", data = "<slaps>", format = "json")]
+async fn new_slaps(
+    _admin: Admin,
+    pool: Pool<'_>,
+    audit: Audit,
+    settings: &State<ApiConfig>,
+    feed: &State<SlapFeed>,
+    webhook: &State<Webhook>,
+    slaps: Json<Vec<SlapPayload>>,
+) -> ApiResult<Json<Vec<Slap>>> {
+    let mut new = Vec::with_capacity(slaps.len());
+    for slap in slaps.iter() {
+        new.push(NewSlap {
+            guild: snowflake("guild", slap.guild.0)?,
+            sentence: check_sentence(slap.sentence, settings.max_sentence())?,
+            offender: snowflake("offender", slap.offender.0)?,
+            enforcer: slap
+                .enforcer
+                .map(|enforcer| snowflake("enforcer", enforcer.0))
+                .transpose()?,
+            reason: reason(slap.reason.as_deref()),
+        });
+    }
+
+    let entries = new
+        .iter()
+        .map(|slap| audit.entry(slap.guild, summary(slap)))
+        .collect::<Vec<_>>();
+    let reports = db::insert_slaps(pool.inner(), &new, &entries)
+        .await?
+        .into_iter()
+        .map(Slap::from)
+        .collect::<Vec<_>>();
+    for (slap, report) in new.iter().zip(&reports) {
+        webhook.notify(slap.guild, report);
+        feed.publish(slap.guild, report.clone());
+    }
+    Ok(Json(reports))
+}
+
+/// `DELETE` every slap of the guild at once, returning how many there were.
+///
+/// Either all of them are deleted or none is. With `dry_run` nothing is deleted (nor audited), the
+/// number of slaps which would be is returned instead.
+#[delete("/slaps/<guild>?<dry_run>")]
+async fn gsr_clear(
+    _admin: Admin,
+    pool: Pool<'_>,
+    audit: Audit,
+    guild: u64,
+    dry_run: Option<bool>,
+) -> ApiResult<Json<u64>> {
+    let guild = snowflake("guild", guild)?;
+    if dry_run.unwrap_or(false) {
+        // pardoned slaps are deleted too
+        let filter = SlapFilter {
+            include_pardoned: true,
+            ..SlapFilter::default()
+        };
+        return Ok(Json(
+            pool.retry(|pool| db::count_slaps(pool, guild, &filter))
+                .await?,
+        ));
+    }
+    let entry = audit.entry(guild, "cleared every slap");
+    Ok(Json(db::clear_slaps(pool.inner(), guild, &entry).await?))
+}
+
+/// Members between whom slaps are transferred, see [`gsr_transfer()`] and
+/// [`gsr_reassign_enforcer()`].
+#[derive(Debug, Deserialize)]
+struct Transfer {
+    from: Snowflake,
+    to: Snowflake,
+}
+
+/// `POST` a JSON `{"from": ..., "to": ...}` to reassign every slap of `from` in the guild to `to`,
+/// returning how many were moved.
+///
+/// The slaps keep their id, sentence and date. Either all of them are moved or none is.
+///
+/// # Errors
+///
+/// Fails with `404` if the guild wasn't created, even if it has slaps.
+#[post("/slaps/<guild>/transfer", data = "<transfer>", format = "json")]
+async fn gsr_transfer(
+    _admin: Admin,
+    pool: Pool<'_>,
+    audit: Audit,
+    guild: u64,
+    transfer: Json<Transfer>,
+) -> ApiResult<Json<u64>> {
+    let guild = snowflake("guild", guild)?;
+    let from = snowflake("from", transfer.from.0)?;
+    let to = snowflake("to", transfer.to.0)?;
+    let entry = audit.entry(
+        guild,
+        format!("transferred the slaps of {} to {}", from, to),
+    );
+    db::transfer_slaps(pool.inner(), guild, from, to, &entry)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("guild", guild))
+}
+
+/// `POST` a JSON `{"from": ..., "to": ...}` to make `to` the enforcer of every slap `from` gave in
+/// the guild, such as when a moderator changed accounts. Returns how many were changed.
+///
+/// Only the enforcer changes, and either all of them do or none does.
+///
+/// # Errors
+///
+/// Fails with `404` if the guild wasn't created, like [`gsr_transfer()`].
+#[post(
+    "/slaps/<guild>/enforcer/reassign",
+    data = "<transfer>",
+    format = "json"
+)]
+async fn gsr_reassign_enforcer(
+    _admin: Admin,
+    pool: Pool<'_>,
+    audit: Audit,
+    guild: u64,
+    transfer: Json<Transfer>,
+) -> ApiResult<Json<u64>> {
+    let guild = snowflake("guild", guild)?;
+    let from = snowflake("from", transfer.from.0)?;
+    let to = snowflake("to", transfer.to.0)?;
+    let entry = audit.entry(
+        guild,
+        format!("reassigned the slaps given by {} to {}", from, to),
+    );
+    db::reassign_enforcer(pool.inner(), guild, from, to, &entry)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("guild", guild))
+}
+
+/// `GET` the number of slaps of `member` in the guild, but the pardoned ones unless
+/// `include_pardoned` (or `include_removed`, see [`gsr_len()`]).
+#[get("/slaps/<guild>/<member>/len?<include_pardoned>&<include_removed>")]
+async fn msr_len(
+    pool: Pool<'_>,
+    guild: u64,
+    member: u64,
+    include_pardoned: Option<bool>,
+    include_removed: Option<bool>,
+) -> ApiResult<Json<usize>> {
+    let guild = snowflake("guild", guild)?;
+    let member = snowflake("member", member)?;
+    let filter = SlapFilter {
+        member: Some(member),
+        include_pardoned: include_removed.or(include_pardoned).unwrap_or(false),
+        ..SlapFilter::default()
+    };
+    Ok(Json(
+        pool.retry(|pool| db::count_slaps(pool, guild, &filter))
+            .await? as usize,
+    ))
+}
+
+/// `GET` up to `number` [`Slap`]s of `member` in the guild.
+///
+/// Supports the same `number` bounds, `offset`, sentence range, `sort`, `include_removed` (or
+/// `include_pardoned`) and [`Paginated`] envelope as [`gsr_slaps()`].
+#[get(
+    "/slaps/<guild>/<member>/reports?<number>&<offset>&<min_sentence>&<max_sentence>&<sort>&<include_pardoned>&<include_removed>"
+)]
+async fn msr_slaps(
+    settings: &State<ApiConfig>,
+    guild: u64,
+    member: u64,
+    number: OptionalCount<'_>,
+    offset: OptionalCount<'_>,
+    min_sentence: Option<u64>,
+    max_sentence: Option<u64>,
+    sort: Option<SlapOrder>,
+    include_pardoned: Option<bool>,
+    include_removed: Option<bool>,
+    pool: Pool<'_>,
+) -> ApiResult<Negotiated<Paginated<Slap>>> {
+    let guild = snowflake("guild", guild)?;
+    let number = count("number", number)?;
+    let offset = count("offset", offset)?;
+    let member = snowflake("member", member)?;
+    let filter = SlapFilter {
+        member: Some(member),
+        include_pardoned: include_removed.or(include_pardoned).unwrap_or(false),
+        ..sentence_filter(min_sentence, max_sentence)?
+    };
+    let order = sort.unwrap_or_default();
+    paginated_slaps(pool, guild, &filter, order, settings.page(number, offset)).await
+}
+
+/// `GET` whether the severity of `member` in the guild is strictly above `threshold`.
+///
+/// The severity of a member is the sum of the sentences of all their slaps, but the pardoned ones.
+///
+/// # Errors
+///
+/// Aside from failures from the underlying database, the request fails with `IntegerTooLarge` if
+/// `threshold` is greater than 2^63 - 1, the largest sentence the database can hold.
+#[get("/slaps/<guild>/<member>/exceeds?<threshold>")]
+async fn msr_exceeds(
+    pool: Pool<'_>,
+    guild: u64,
+    member: u64,
+    threshold: u64,
+) -> ApiResult<Json<bool>> {
+    let guild = snowflake("guild", guild)?;
+    let member = snowflake("member", member)?;
+    let threshold = bigint("threshold", threshold)?;
+    Ok(Json(
+        pool.retry(|pool| db::member_exceeds(pool, guild, member, threshold))
+            .await?,
+    ))
+}
+
+/// `GET` the severity of `member` in the guild: the sum of the sentences of all their slaps, but
+/// the pardoned ones.
+#[get("/slaps/<guild>/<member>/sentence")]
+async fn msr_sentence(pool: Pool<'_>, guild: u64, member: u64) -> ApiResult<Json<u64>> {
+    let guild = snowflake("guild", guild)?;
+    let member = snowflake("member", member)?;
+    Ok(Json(
+        pool.retry(|pool| db::member_sentence(pool, guild, member))
+            .await?,
+    ))
+}
+
+/// `GET` the `number` most recent [`Slap`]s of `member` in the guild, newest first.
+///
+/// Same as [`gsr_recent()`] for the slaps of `member` only, the pardoned ones being left out too.
+#[get("/slaps/<guild>/<member>/recent?<number>")]
+async fn msr_recent(
+    settings: &State<ApiConfig>,
+    guild: u64,
+    member: u64,
+    number: OptionalCount<'_>,
+    pool: Pool<'_>,
+) -> ApiResult<Negotiated<Vec<Slap>>> {
+    let guild = snowflake("guild", guild)?;
+    let number = count("number", number)?;
+    let filter = SlapFilter {
+        member: Some(snowflake("member", member)?),
+        ..SlapFilter::default()
+    };
+    recent(&pool, settings, guild, filter, number).await
+}
+
+/// `GET` whether `member` has at least one slap in the guild, but the pardoned ones.
+#[get("/slaps/<guild>/<member>/active")]
+async fn msr_active(pool: Pool<'_>, guild: u64, member: u64) -> ApiResult<Json<bool>> {
+    let guild = snowflake("guild", guild)?;
+    let member = snowflake("member", member)?;
+    Ok(Json(
+        pool.retry(|pool| db::member_active(pool, guild, member))
+            .await?,
+    ))
+}
@@ -1,4 +1,4163 @@
-use rocket::{http::Status, local::blocking::Client};
+use super::{
+    auth::AdminToken,
+    cache,
+    config::{ApiConfig, ConfigError, PoolConfig},
+    db,
+    error::{ApiError, ApiResult},
+    guild::PrivilegeName,
+    id::Snowflake,
+    idempotency::IdempotencyStore,
+    params,
+    pool::{CircuitBreaker, Pool, PoolGate, RetryPolicy},
+    retention, rocket,
+    webhook::Webhook,
+};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use db_adapter::{guild::Privilege, PgPool};
+use rocket::{
+    figment::Figment,
+    http::{ContentType, Header, Status},
+    local::blocking::{Client, LocalResponse},
+    Build, Rocket,
+};
+use sqlx::{Connection, Executor, PgConnection};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+const ADMIN_TOKEN: &str = "test-token";
+
+/// [`Harness`] with the default configuration.
+fn client() -> Harness {
+    Harness::new()
+}
+
+/// [`client`] whose configuration went through `configure`, to override settings without touching
+/// the environment other tests share.
+fn client_with(configure: impl FnOnce(Figment) -> Figment) -> Harness {
+    Harness::with(settings(configure))
+}
+
+/// Tables of the bot, owned by db-adapter, which the [`Harness`] copies.
+const BOT_TABLES: &[&str] = &["guilds", "slaps"];
+
+/// Client against tables of its own rather than those other tests share, with the test routes under
+/// `/test`.
+///
+/// The tables of the bot in the database pointed at by `DATABASE_URL` are copied (empty) into a
+/// throwaway schema, which is migrated when the client is ignited and dropped along with the harness.
+/// Admin endpoints expect [`ADMIN_TOKEN`] and no webhook is notified, whatever the environment says.
+struct Harness {
+    schema: String,
+    client: Option<Client>,
+}
+
+impl Harness {
+    fn new() -> Self {
+        Self::with(|rocket| rocket)
+    }
+
+    /// [`Harness::new`] whose rocket went through `configure` before being ignited, to manage state
+    /// or override settings of its own.
+    fn with(configure: impl FnOnce(Rocket<Build>) -> Rocket<Build>) -> Self {
+        let schema = format!("linker_test_{}", unique_id());
+        let url = PoolConfig::from_env()
+            .expect("tests run against `DATABASE_URL`")
+            .url;
+        block_on(async {
+            let mut conn = PgConnection::connect(&url).await?;
+            conn.execute(format!("CREATE SCHEMA {}", schema).as_str())
+                .await?;
+            for table in BOT_TABLES {
+                conn.execute(
+                    format!(
+                        "CREATE TABLE {0}.{1} (LIKE {1} INCLUDING ALL)",
+                        schema, table
+                    )
+                    .as_str(),
+                )
+                .await?;
+            }
+            conn.close().await
+        })
+        .expect("the schema of the harness can be created");
+
+        let mut harness = Harness {
+            schema,
+            client: None,
+        };
+        harness.client = Some(harness.sibling(configure));
+        harness
+    }
+
+    /// Rocket against the tables of the harness, which went through `configure` before the test
+    /// routes are mounted.
+    fn rocket(&self, configure: impl FnOnce(Rocket<Build>) -> Rocket<Build>) -> Rocket<Build> {
+        let mut config = PoolConfig::from_env().expect("tests run against `DATABASE_URL`");
+        config.schema = Some(self.schema.clone());
+        config.run_migrations = true;
+        let mut rocket = configure(rocket().manage(config));
+        // unless `configure` managed its own
+        if rocket.state::<AdminToken>().is_none() {
+            rocket = rocket.manage(AdminToken::new(Some(ADMIN_TOKEN.to_string())));
+        }
+        if rocket.state::<Webhook>().is_none() {
+            rocket = rocket.manage(Webhook::new(None));
+        }
+        rocket.mount("/test", test_routes())
+    }
+
+    /// Another client against the tables of the harness, whose rocket went through `configure`.
+    ///
+    /// It must be dropped before the harness.
+    fn sibling(&self, configure: impl FnOnce(Rocket<Build>) -> Rocket<Build>) -> Client {
+        Client::tracked(self.rocket(configure)).expect("valid rocket instance")
+    }
+
+    fn client(&self) -> &Client {
+        self.client
+            .as_ref()
+            .expect("the client lives as long as the harness")
+    }
+
+    /// Pool of the client, whose connections all look the tables up in the harness' schema.
+    fn pool(&self) -> &PgPool {
+        self.client()
+            .rocket()
+            .state::<PgPool>()
+            .expect("the database is managed once ignited")
+    }
+
+    /// Creates a guild whose member is slapped `slaps` times for 1, returning the ids of both.
+    fn seed(&self, slaps: usize) -> (u64, u64) {
+        let (guild, member) = (new_guild(self.client()), unique_id());
+        for _ in 0..slaps {
+            slap_with_sentence(self.client(), guild, member, 1);
+        }
+        (guild, member)
+    }
+}
+
+impl std::ops::Deref for Harness {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client()
+    }
+}
+
+/// Overrides the settings of a rocket through its figment, rather than the environment.
+fn settings(
+    configure: impl FnOnce(Figment) -> Figment,
+) -> impl FnOnce(Rocket<Build>) -> Rocket<Build> {
+    move |rocket| {
+        let figment = configure(rocket.figment().clone());
+        rocket.configure(figment)
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        // connections must be closed for the schema to be dropped right away
+        self.client.take();
+        let url = PoolConfig::from_env()
+            .expect("tests run against `DATABASE_URL`")
+            .url;
+        let dropped = block_on(async {
+            let mut conn = PgConnection::connect(&url).await?;
+            conn.execute(format!("DROP SCHEMA {} CASCADE", self.schema).as_str())
+                .await?;
+            conn.close().await
+        });
+        if let Err(err) = dropped {
+            eprintln!("couldn't drop the schema {}: {}", self.schema, err);
+        }
+    }
+}
+
+/// Runs `future` to completion, outside of rocket's runtime.
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    rocket::tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("a runtime can be built")
+        .block_on(future)
+}
+
+/// Snowflake-looking id that no other test (or previous run) uses.
+fn unique_id() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64;
+    (nanos >> 4) + COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The ids in `json`, which are answered as strings.
+fn ids(json: serde_json::Value) -> Vec<u64> {
+    serde_json::from_value::<Vec<Snowflake>>(json)
+        .unwrap()
+        .into_iter()
+        .map(|id| id.0)
+        .collect()
+}
+
+fn admin() -> Header<'static> {
+    Header::new("Authorization", format!("Bearer {}", ADMIN_TOKEN))
+}
+
+/// Creates a guild with the default configuration and returns its id.
+fn new_guild(client: &Client) -> u64 {
+    let id = unique_id();
+    let response = client
+        .post("/guild/new")
+        .header(ContentType::Form)
+        .body(format!("id={}&advertise=true", id))
+        .dispatch();
+    assert_eq!(response.status(), Status::Created);
+    id
+}
+
+/// `POST`s a slap of `offender` in `guild`, with an `Idempotency-Key` if provided.
+fn slap<'c>(client: &'c Client, guild: u64, offender: u64, key: Option<&str>) -> LocalResponse<'c> {
+    post_slap(
+        client,
+        format!(
+            "guild={}&sentence={}&offender={}",
+            guild,
+            unique_id(),
+            offender
+        ),
+        key,
+    )
+}
+
+/// `POST`s a slap of `offender` in `guild` with the given `sentence`.
+fn slap_with_sentence(client: &Client, guild: u64, offender: u64, sentence: u64) {
+    let response = post_slap(
+        client,
+        format!(
+            "guild={}&sentence={}&offender={}",
+            guild, sentence, offender
+        ),
+        None,
+    );
+    assert_eq!(response.status(), Status::Ok);
+}
+
+fn post_slap<'c>(client: &'c Client, form: String, key: Option<&str>) -> LocalResponse<'c> {
+    let mut request = client
+        .post("/slaps/new")
+        .header(ContentType::Form)
+        .body(form);
+    if let Some(key) = key {
+        request = request.header(Header::new("Idempotency-Key", key.to_string()));
+    }
+    request.dispatch()
+}
+
+fn gsr_len(client: &Client, guild: u64) -> usize {
+    client
+        .get(format!("/slaps/{}/len", guild))
+        .dispatch()
+        .into_json()
+        .unwrap()
+}
+
+/// Routes of the tests, mounted under `/test`.
+fn test_routes() -> Vec<rocket::Route> {
+    rocket::routes![failing_query, pool_timeout, retry_after, strong_etag]
+}
+
+/// Body large enough to be compressed bearing a strong `ETag`, as a responder other than
+/// [`cache::Tagged`] could answer.
+#[derive(rocket::Responder)]
+#[response(content_type = "json")]
+struct StronglyTagged {
+    body: String,
+    etag: Header<'static>,
+}
+
+#[rocket::get("/strong_etag")]
+fn strong_etag() -> StronglyTagged {
+    StronglyTagged {
+        body: format!(
+            "\"{}\"",
+            "a".repeat(ApiConfig::default().compression_threshold)
+        ),
+        etag: Header::new("ETag", "\"strong\""),
+    }
+}
+
+/// Stands for any query the database failed to answer.
+#[rocket::get("/failing_query")]
+fn failing_query() -> ApiResult<()> {
+    Err(sqlx::Error::Protocol("unexpected message".into()).into())
+}
+
+/// Answers the error matching `status` among those bearing a `Retry-After` of 7 seconds.
+#[rocket::get("/retry_after/<status>")]
+fn retry_after(status: u16) -> ApiResult<()> {
+    Err(match status {
+        429 => ApiError::RateLimited { retry_after: 7 },
+        _ => ApiError::Unavailable { retry_after: 7 },
+    })
+}
+
+/// Stands for a query which couldn't get a connection in time.
+#[rocket::get("/pool_timeout")]
+async fn pool_timeout(pool: Pool<'_>) -> ApiResult<()> {
+    pool.retry(|_| async { Err::<(), _>(sqlx::Error::PoolTimedOut) })
+        .await
+}
+
+fn grant_privilege(client: &Client, guild: u64, role: u64, privilege: &str) {
+    let response = client
+        .post(format!("/guild/{}/privileges/grant/{}", guild, role))
+        .header(ContentType::Form)
+        .body(format!("privileges={}", privilege))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
 
 #[test]
 fn test_() {}
+
+#[test]
+fn admin_chan_guilds() {
+    let client = client();
+    let channel = unique_id();
+    let (first, second, other) = (new_guild(&client), new_guild(&client), new_guild(&client));
+    for (guild, chan) in [(first, channel), (second, channel), (other, channel + 1)] {
+        let response = client
+            .post(format!("/guild/{}/admin_channel", guild))
+            .header(ContentType::Form)
+            .body(format!("chan={}", chan))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    let response = client
+        .get(format!("/admin/admin_channel/{}/guilds", channel))
+        .header(admin())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(ids(response.into_json().unwrap()), [first, second]);
+
+    let response = client
+        .get(format!("/admin/admin_channel/{}/guilds", channel))
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    let error = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(error["type"], "validation");
+
+    // admin endpoints are closed to everyone without a token
+    let disabled = client.sibling(|rocket| rocket.manage(AdminToken::new(None)));
+    let response = disabled
+        .get(format!("/admin/admin_channel/{}/guilds", channel))
+        .header(admin())
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+    let error = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(error["type"], "validation");
+    assert!(error["reason"].as_str().unwrap().contains("no admin token"));
+
+    let response = client
+        .get("/admin/admin_channel/0/guilds")
+        .header(admin())
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn new_slap_idempotency_key() {
+    let client = client();
+    let (guild, offender) = (unique_id(), unique_id());
+    let form = format!("guild={}&sentence=1&offender={}", guild, offender);
+
+    let first = post_slap(&client, form.clone(), Some("retry")).into_string();
+    let retry = post_slap(&client, form.clone(), Some("retry")).into_string();
+    assert!(first.is_some());
+    assert_eq!(first, retry);
+    assert_eq!(gsr_len(&client, guild), 1);
+
+    // the key was used for another slap
+    let reused = post_slap(&client, format!("{}&reason=other", form), Some("retry"));
+    assert_eq!(reused.status(), Status::UnprocessableEntity);
+    let error = reused.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(error["type"], "validation");
+    assert_eq!(gsr_len(&client, guild), 1);
+
+    let other = post_slap(&client, form, Some("other")).into_string();
+    assert_ne!(first, other);
+    assert_eq!(gsr_len(&client, guild), 2);
+}
+
+#[test]
+fn slaps_per_bucket_timezone() {
+    let harness = Harness::new();
+    let (guild, _) = harness.seed(0);
+    let time = |t: &str| t.parse::<DateTime<Utc>>().unwrap();
+
+    // Paris moved from UTC+1 to UTC+2 on 2021-03-28, whatever the time zone of the session
+    let buckets = block_on(db::slaps_per_bucket(
+        harness.pool(),
+        Some(guild),
+        time("2021-03-27T12:00:00Z"),
+        time("2021-03-29T12:00:00Z"),
+        params::Bucket::Day,
+        "Europe/Paris",
+        false,
+    ))
+    .unwrap();
+    let starts = buckets
+        .iter()
+        .map(|bucket| bucket.start)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        starts,
+        [
+            time("2021-03-26T23:00:00Z"),
+            time("2021-03-27T23:00:00Z"),
+            time("2021-03-28T22:00:00Z"),
+        ]
+    );
+}
+
+#[test]
+fn gsr_stream() {
+    let client = client();
+    let (guild, offender) = (unique_id(), unique_id());
+    let mut stream = client.get(format!("/slaps/{}/stream", guild)).dispatch();
+    assert_eq!(stream.content_type(), Some(ContentType::EventStream));
+
+    assert_eq!(slap(&client, guild, offender, None).status(), Status::Ok);
+    let mut event = String::new();
+    let mut buffer = [0; 256];
+    while !event.ends_with("\n\n") {
+        let read = stream.read(&mut buffer).unwrap();
+        assert_ne!(read, 0, "stream ended before the event was received");
+        event.push_str(std::str::from_utf8(&buffer[..read]).unwrap());
+    }
+    assert!(event.starts_with("data:"));
+    assert!(event.contains(&offender.to_string()));
+}
+
+#[test]
+fn admin_slaps_growth() {
+    let client = client();
+    let since = (Utc::now() - Duration::hours(3)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let growth = |client: &Client| {
+        client
+            .get(format!("/admin/slaps/growth?since={}&bucket=hour", since))
+            .header(admin())
+            .dispatch()
+            .into_json::<Vec<serde_json::Value>>()
+            .unwrap()
+    };
+
+    let before = growth(&client);
+    for _ in 0..2 {
+        let response = slap(&client, unique_id(), unique_id(), None);
+        assert_eq!(response.status(), Status::Ok);
+    }
+    let after = growth(&client);
+
+    // zero-filled: one bucket per hour since three hours ago, whether slaps happened or not
+    assert_eq!(after.len(), 4);
+    let count =
+        |series: &[serde_json::Value], i: usize, field: &str| series[i][field].as_i64().unwrap();
+    assert!(count(&after, 3, "count") >= count(&before, 3, "count") + 2);
+    for i in 1..after.len() {
+        assert_eq!(
+            count(&after, i, "total"),
+            count(&after, i - 1, "total") + count(&after, i, "count")
+        );
+    }
+
+    let response = client
+        .get(format!(
+            "/admin/slaps/growth?since={}&bucket=hour&until=2000-01-01T00:00:00Z",
+            since
+        ))
+        .header(admin())
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    for query in [
+        "",
+        "?since=yesterday",
+        "?since=2021-09-14T12:00:00Z&until=now",
+    ] {
+        let response = client
+            .get(format!("/admin/slaps/growth{}", query))
+            .header(admin())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "{}", query);
+        let body = response.into_json::<serde_json::Value>().unwrap();
+        assert!(
+            body["reason"].as_str().unwrap().contains("RFC 3339"),
+            "{}",
+            body
+        );
+    }
+}
+
+#[test]
+fn version() {
+    let client = client();
+    let response = client.get("/version").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert!(body["commit"].is_string());
+    assert!(body["built_at"].is_u64());
+}
+
+#[test]
+fn health() {
+    let client = client();
+    let response = client.get("/health").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(body, serde_json::json!({"database": true}));
+
+    let response = client.get("/health/detailed").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(body["database"], true);
+    let pool = &body["pool"];
+    let (size, idle, in_use) = (
+        pool["size"].as_u64().unwrap(),
+        pool["idle"].as_u64().unwrap(),
+        pool["in_use"].as_u64().unwrap(),
+    );
+    assert!(size >= 1);
+    assert_eq!(idle + in_use, size);
+}
+
+#[test]
+fn msr_exceeds() {
+    let client = client();
+    let (guild, offender) = (unique_id(), unique_id());
+    slap_with_sentence(&client, guild, offender, 3);
+    slap_with_sentence(&client, guild, offender, 4);
+
+    let exceeds = |threshold: u64| {
+        client
+            .get(format!(
+                "/slaps/{}/{}/exceeds?threshold={}",
+                guild, offender, threshold
+            ))
+            .dispatch()
+            .into_json::<bool>()
+            .unwrap()
+    };
+    assert!(exceeds(6));
+    assert!(!exceeds(7));
+    assert!(!exceeds(100));
+
+    // would wrap to a negative threshold every member exceeds
+    let response = client
+        .get(format!(
+            "/slaps/{}/{}/exceeds?threshold={}",
+            guild,
+            unique_id(),
+            u64::MAX
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn error_types() {
+    let client = client();
+    let response = client
+        .get(format!(
+            "/guild/{}/privileges/roles_with/owner",
+            unique_id()
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(body["type"], "validation");
+    assert!(body["reason"].as_str().unwrap().contains("owner"));
+
+    let response = client.get("/test/failing_query").dispatch();
+    assert_eq!(response.status(), Status::InternalServerError);
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(body["type"], "internal");
+}
+
+#[test]
+fn pool_config_from_vars() {
+    let vars = |max: &'static str| {
+        move |var: &str| match var {
+            "DATABASE_URL" => Some("postgres://localhost/botanist".to_string()),
+            "DATABASE_MAX_CONNECTIONS" => Some(max.to_string()),
+            _ => None,
+        }
+    };
+
+    let config = PoolConfig::from_vars(vars("4")).unwrap();
+    assert_eq!(config.max_connections, 4);
+    assert_eq!(config.min_connections, 0);
+
+    let err = PoolConfig::from_vars(vars("four")).unwrap_err();
+    assert!(matches!(
+        err,
+        ConfigError::Invalid {
+            var: "DATABASE_MAX_CONNECTIONS",
+            ..
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "`DATABASE_MAX_CONNECTIONS` must be a number, found `four`"
+    );
+}
+
+#[rocket::async_test]
+async fn idempotency_keys() {
+    let err = IdempotencyStore::<u64>::from_vars(|_| Some("a day".to_string()))
+        .err()
+        .unwrap();
+    assert!(matches!(
+        err,
+        ConfigError::Invalid {
+            var: "IDEMPOTENCY_TTL",
+            ..
+        }
+    ));
+
+    let store = IdempotencyStore::<u64>::from_vars(|_| None).unwrap();
+    let (unblock, blocked) = rocket::tokio::sync::oneshot::channel::<()>();
+    let slow = store.get_or_try_insert(1, "slow".into(), 0, || async {
+        blocked.await.unwrap();
+        Ok::<_, ApiError>(1)
+    });
+    // the other key goes through while the slow one is still being created
+    let other = async {
+        let other = store
+            .get_or_try_insert(1, "other".into(), 0, || async { Ok::<_, ApiError>(2) })
+            .await;
+        unblock.send(()).unwrap();
+        other
+    };
+    let (slow, other) = rocket::tokio::join!(slow, other);
+    assert!(matches!((slow, other), (Ok(1), Ok(2))));
+
+    let replayed = store
+        .get_or_try_insert(1, "slow".into(), 0, || async { Ok::<_, ApiError>(3) })
+        .await;
+    assert!(matches!(replayed, Ok(1)));
+    // the same key along with another request
+    let reused = store
+        .get_or_try_insert(1, "slow".into(), 1, || async { Ok::<_, ApiError>(3) })
+        .await;
+    assert!(matches!(reused, Err(ApiError::IdempotencyKeyReused(key)) if key == "slow"));
+    let failed = store
+        .get_or_try_insert(2, "slow".into(), 0, || async {
+            Err::<u64, _>(ApiError::Saturated)
+        })
+        .await;
+    assert!(matches!(failed, Err(ApiError::Saturated)));
+    let retried = store
+        .get_or_try_insert(2, "slow".into(), 1, || async { Ok::<_, ApiError>(4) })
+        .await;
+    assert!(matches!(retried, Ok(4)));
+}
+
+#[test]
+fn slaps_sentence_range() {
+    let client = client();
+    let (guild, offender) = (unique_id(), unique_id());
+    for sentence in [1, 5, 10] {
+        slap_with_sentence(&client, guild, offender, sentence);
+    }
+    slap_with_sentence(&client, guild, unique_id(), 5);
+
+    let reports = |path: String| {
+        client
+            .get(path)
+            .dispatch()
+            .into_json::<serde_json::Value>()
+            .unwrap()["data"]
+            .as_array()
+            .unwrap()
+            .len()
+    };
+    let range = "min_sentence=2&max_sentence=10";
+    assert_eq!(
+        reports(format!("/slaps/{}/reports?number=10&{}", guild, range)),
+        3
+    );
+    assert_eq!(
+        reports(format!(
+            "/slaps/{}/{}/reports?number=10&{}",
+            guild, offender, range
+        )),
+        2
+    );
+
+    let response = client
+        .get(format!(
+            "/slaps/{}/reports?number=10&min_sentence=10&max_sentence=2",
+            guild
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    // would wrap to negative bounds
+    for range in ["min_sentence", "max_sentence"] {
+        let response = client
+            .get(format!("/slaps/{}/reports?{}={}", guild, range, u64::MAX))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "{}", range);
+    }
+}
+
+#[test]
+fn v1_prefix() {
+    let client = client();
+    let guild = new_guild(&client);
+
+    let versioned = client.get(format!("/v1/guild/{}/exists", guild)).dispatch();
+    assert_eq!(versioned.status(), Status::Ok);
+    assert_eq!(versioned.headers().get_one("Deprecation"), None);
+    let legacy = client.get(format!("/guild/{}/exists", guild)).dispatch();
+    assert_eq!(legacy.headers().get_one("Deprecation"), Some("true"));
+    assert_eq!(versioned.into_json::<bool>(), Some(true));
+    assert_eq!(legacy.into_json::<bool>(), Some(true));
+
+    // created guilds are located under the current version, whichever they were created through
+    for prefix in ["/v1", ""] {
+        let id = unique_id();
+        let response = client
+            .post(format!("{}/guild/new", prefix))
+            .header(ContentType::Form)
+            .body(format!("id={}&advertise=true", id))
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+        let location = format!("/v1/guild/{}", id);
+        assert_eq!(
+            response.headers().get_one("Location"),
+            Some(location.as_str())
+        );
+    }
+}
+
+#[test]
+fn pool_gate_saturation() {
+    let mut config = PoolConfig::from_vars(|var| match var {
+        "DATABASE_URL" => Some("postgres://localhost/botanist".to_string()),
+        "DATABASE_MAX_CONNECTIONS" => Some("1".to_string()),
+        "DATABASE_MAX_QUEUE" => Some("1".to_string()),
+        _ => None,
+    })
+    .unwrap();
+    let gate = PoolGate::new(&config);
+    let first = gate.try_enter().unwrap();
+    let queued = gate.try_enter().unwrap();
+
+    let start = Instant::now();
+    let err = gate.try_enter().unwrap_err();
+    assert!(start.elapsed() < std::time::Duration::from_millis(10));
+    assert_eq!(err.status(), Status::ServiceUnavailable);
+
+    drop(first);
+    assert!(gate.try_enter().is_ok());
+    drop(queued);
+
+    config.max_queue = None;
+    let unbounded = PoolGate::new(&config);
+    let permits = (0..100).map(|_| unbounded.try_enter()).collect::<Vec<_>>();
+    assert!(permits.iter().all(Result::is_ok));
+}
+
+#[test]
+fn admin_recently_modified() {
+    let client = client();
+    let (first, second) = (new_guild(&client), new_guild(&client));
+    let response = client
+        .post(format!("/guild/{}/advertise", first))
+        .header(ContentType::Form)
+        .body("policy=false")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let guilds = ids(client
+        .get("/admin/guilds/recently_modified?number=50")
+        .header(admin())
+        .dispatch()
+        .into_json()
+        .unwrap());
+    let position = |id| guilds.iter().position(|guild| *guild == id).unwrap();
+    assert!(position(first) < position(second));
+}
+
+#[test]
+fn slaps_paginated() {
+    let client = client();
+    let (guild, offender) = (unique_id(), unique_id());
+    for _ in 0..3 {
+        slap(&client, guild, offender, None);
+    }
+
+    for path in [
+        format!("/slaps/{}/reports?number=2", guild),
+        format!("/slaps/{}/{}/reports?number=2", guild, offender),
+    ] {
+        let page = client
+            .get(path)
+            .dispatch()
+            .into_json::<serde_json::Value>()
+            .unwrap();
+        assert_eq!(page["data"].as_array().unwrap().len(), 2);
+        assert_eq!(page["total"], 3);
+        assert_eq!(page["offset"], 0);
+        assert_eq!(page["limit"], 2);
+    }
+}
+
+#[test]
+fn gsr_export() {
+    let client = client();
+    let (guild, offender) = (unique_id(), unique_id());
+    let response = post_slap(
+        &client,
+        format!(
+            "guild={}&sentence=3&offender={}&reason=spam%2C%20again",
+            guild, offender
+        ),
+        None,
+    );
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .get(format!("/slaps/{}/export.csv", guild))
+        .dispatch();
+    assert_eq!(response.content_type(), Some(ContentType::CSV));
+    let csv = response.into_string().unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("id,offender,enforcer,sentence,reason,created_at")
+    );
+    let row = lines.next().unwrap();
+    assert!(row.contains(&format!(",{},,3,\"spam, again\",", offender)));
+    assert_eq!(lines.next(), None);
+}
+
+#[test]
+fn msr_slaps_offset() {
+    let client = client();
+    let (guild, offender) = (unique_id(), unique_id());
+    for _ in 0..5 {
+        slap(&client, guild, offender, None);
+    }
+    slap(&client, guild, unique_id(), None);
+
+    let ids = |query: &str| {
+        client
+            .get(format!("/slaps/{}/{}/reports?{}", guild, offender, query))
+            .dispatch()
+            .into_json::<serde_json::Value>()
+            .unwrap()["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|report| report["id"].as_i64().unwrap())
+            .collect::<Vec<_>>()
+    };
+    let all = ids("number=5");
+    assert_eq!(all.len(), 5);
+    assert_eq!(ids("number=2"), all[..2]);
+    assert_eq!(ids("number=2&offset=2"), all[2..4]);
+    assert_eq!(ids("number=2&offset=4"), all[4..]);
+    assert!(ids("number=2&offset=5").is_empty());
+}
+
+#[test]
+fn guild_privileges() {
+    let client = client();
+    let guild = new_guild(&client);
+    let (first, second) = (unique_id(), unique_id());
+    grant_privilege(&client, guild, first, "admin");
+    grant_privilege(&client, guild, first, "event");
+    grant_privilege(&client, guild, second, "manager");
+
+    let privileges = client
+        .get(format!("/guild/{}/privileges", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(
+        privileges,
+        serde_json::json!([
+            {"role": first.to_string(), "privileges": ["admin", "event"]},
+            {"role": second.to_string(), "privileges": ["manager"]},
+        ])
+    );
+}
+
+#[test]
+fn guild_grant_privileges() {
+    let client = client();
+    let guild = new_guild(&client);
+    let role = unique_id();
+    let grant = |privileges: &str| {
+        client
+            .post(format!("/guild/{}/privileges/grant/{}", guild, role))
+            .header(ContentType::Form)
+            .body(privileges.to_string())
+            .dispatch()
+    };
+    let privileges_for = || {
+        client
+            .get(format!("/guild/{}/privileges/for_role/{}", guild, role))
+            .dispatch()
+            .into_json::<Vec<String>>()
+            .unwrap()
+    };
+
+    let response = grant("privileges=admin&privileges=event");
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_json::<Vec<String>>().unwrap(),
+        ["admin", "event"]
+    );
+
+    let response = grant("privileges=manager&privileges=owner");
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(privileges_for(), ["admin", "event"]);
+
+    let response = client
+        .post(format!("/guild/{}/privileges/grant/{}", guild, role))
+        .header(ContentType::JSON)
+        .body(r#"{"privileges": ["manager"]}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_json::<Vec<String>>().unwrap(),
+        ["admin", "event", "manager"]
+    );
+}
+
+#[test]
+fn null_privileges() {
+    let harness = Harness::new();
+    let client = harness.client();
+    let guild = new_guild(&client);
+    let role = unique_id();
+    block_on(
+        sqlx::query("UPDATE guilds SET priv_admin = NULL WHERE id = $1")
+            .bind(guild as i64)
+            .execute(harness.pool()),
+    )
+    .unwrap();
+
+    let response = client
+        .post(format!("/guild/{}/privileges/grant/{}", guild, role))
+        .header(ContentType::Form)
+        .body("privileges=admin")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let privileges = client
+        .get(format!("/guild/{}/privileges/for_role/{}", guild, role))
+        .dispatch()
+        .into_json::<Vec<String>>()
+        .unwrap();
+    assert_eq!(privileges, ["admin"]);
+
+    block_on(
+        sqlx::query("UPDATE guilds SET priv_event = NULL WHERE id = $1")
+            .bind(guild as i64)
+            .execute(harness.pool()),
+    )
+    .unwrap();
+    let response = client
+        .post(format!("/guild/{}/privileges/resolve", guild))
+        .header(ContentType::JSON)
+        .body(serde_json::to_string(&[role]).unwrap())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<Vec<String>>().unwrap(), ["admin"]);
+}
+
+#[rocket::async_test]
+async fn retry_policy() {
+    let policy = RetryPolicy {
+        retries: 2,
+        delay: std::time::Duration::from_millis(1),
+    };
+    let reset = || sqlx::Error::Io(std::io::ErrorKind::ConnectionReset.into());
+
+    let attempts = AtomicU64::new(0);
+    let flaky = || async {
+        match attempts.fetch_add(1, Ordering::Relaxed) {
+            0 | 1 => Err(reset()),
+            _ => Ok(42),
+        }
+    };
+    assert_eq!(policy.run(flaky).await.unwrap(), 42);
+    assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+    let attempts = AtomicU64::new(0);
+    let broken = || async {
+        attempts.fetch_add(1, Ordering::Relaxed);
+        Err::<(), _>(reset())
+    };
+    assert!(policy.run(broken).await.unwrap_err().is_transient());
+    assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+    let attempts = AtomicU64::new(0);
+    let invalid = || async {
+        attempts.fetch_add(1, Ordering::Relaxed);
+        Err::<(), _>(sqlx::Error::RowNotFound)
+    };
+    assert!(policy.run(invalid).await.is_err());
+    assert_eq!(attempts.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn gsr_report() {
+    let client = client();
+    let guild = unique_id();
+    let report = slap(&client, guild, unique_id(), None)
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    let id = report["id"].as_i64().unwrap();
+
+    let response = client.get(format!("/slaps/{}/{}", guild, id)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<serde_json::Value>().unwrap(), report);
+
+    let response = client
+        .get(format!("/slaps/{}/{}", unique_id(), id))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(
+        response.into_json::<serde_json::Value>().unwrap()["type"],
+        "validation"
+    );
+}
+
+#[test]
+fn message_length_limit() {
+    let client = client();
+    let guild = new_guild(&client);
+    let set_welcome = |message: &str| {
+        client
+            .post(format!("/guild/{}/welcome_message", guild))
+            .header(ContentType::Form)
+            .body(format!("message={}", message))
+            .dispatch()
+            .status()
+    };
+    let welcome = || {
+        client
+            .get(format!("/guild/{}/welcome_message", guild))
+            .dispatch()
+            .into_json::<Option<String>>()
+            .unwrap()
+    };
+
+    let at_limit = "a".repeat(2000);
+    assert_eq!(set_welcome(&at_limit), Status::Ok);
+    assert_eq!(welcome(), Some(at_limit.clone()));
+    assert_eq!(set_welcome(&"b".repeat(2001)), Status::UnprocessableEntity);
+    assert_eq!(welcome(), Some(at_limit));
+
+    let id = unique_id();
+    let response = client
+        .post("/guild/new")
+        .header(ContentType::Form)
+        .body(format!(
+            "id={}&advertise=true&goodbye_message={}",
+            id,
+            "c".repeat(2001)
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let exists = client.get(format!("/guild/{}/exists", id)).dispatch();
+    assert_eq!(exists.into_json::<bool>(), Some(false));
+}
+
+#[test]
+fn guild_settings_etag() {
+    let client = client();
+    let guild = new_guild(&client);
+    let settings = |etag: Option<&str>| {
+        let mut request = client.get(format!("/guild/{}", guild));
+        if let Some(etag) = etag {
+            request = request.header(Header::new("If-None-Match", etag.to_string()));
+        }
+        request.dispatch()
+    };
+
+    let response = settings(None);
+    assert_eq!(response.status(), Status::Ok);
+    let etag = response.headers().get_one("ETag").unwrap().to_string();
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(body["id"], guild.to_string());
+    assert_eq!(body["advertise"], true);
+
+    let response = settings(Some(&etag));
+    assert_eq!(response.status(), Status::NotModified);
+    assert_eq!(response.headers().get_one("ETag"), Some(etag.as_str()));
+    assert_eq!(response.into_string(), None);
+    // the same settings indented are only equivalent, hence a weak tag
+    assert!(etag.starts_with("W/\""), "{}", etag);
+    let pretty = client
+        .get(format!("/guild/{}?pretty", guild))
+        .header(Header::new("If-None-Match", etag.clone()))
+        .dispatch();
+    assert_eq!(pretty.status(), Status::NotModified);
+    let strong = etag.trim_start_matches("W/").to_string();
+    assert_eq!(settings(Some(&strong)).status(), Status::NotModified);
+
+    let response = client
+        .post(format!("/guild/{}/advertise", guild))
+        .header(ContentType::Form)
+        .body("policy=false")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = settings(Some(&etag));
+    assert_eq!(response.status(), Status::Ok);
+    let changed = response.headers().get_one("ETag").unwrap().to_string();
+    assert_ne!(changed, etag);
+
+    // the timezone is part of the settings, hence of their tag
+    let response = client
+        .post(format!("/guild/{}/timezone", guild))
+        .header(ContentType::Form)
+        .body("timezone=Europe/Paris")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = settings(Some(&changed));
+    assert_eq!(response.status(), Status::Ok);
+    assert_ne!(response.headers().get_one("ETag"), Some(changed.as_str()));
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(body["timezone"], "Europe/Paris");
+}
+
+#[test]
+fn stable_etags() {
+    // FNV-1a test vectors, which no release of the linker or of Rust can change
+    assert_eq!(cache::etag(""), "W/\"cbf29ce484222325\"");
+    assert_eq!(cache::etag("a"), "W/\"af63dc4c8601ec8c\"");
+    assert_eq!(cache::etag("foobar"), "W/\"85944171f73967e8\"");
+}
+
+#[test]
+fn slap_writes_need_admin() {
+    let client = client();
+    let (guild, offender) = (new_guild(&client), unique_id());
+    let report = slap(&client, guild, offender, None)
+        .into_json::<serde_json::Value>()
+        .unwrap()["id"]
+        .as_i64()
+        .unwrap();
+    let transfer = format!(r#"{{"from": "{}", "to": "{}"}}"#, offender, unique_id());
+
+    for (path, body) in [
+        (format!("/slaps/{}/transfer", guild), transfer.clone()),
+        (format!("/slaps/{}/enforcer/reassign", guild), transfer),
+        (
+            "/slaps/batch".to_string(),
+            serde_json::json!([{"guild": guild, "sentence": 1, "offender": offender}]).to_string(),
+        ),
+        (
+            format!("/slaps/{}/{}/pardon", guild, report),
+            serde_json::json!({"reason": "mistaken identity"}).to_string(),
+        ),
+    ] {
+        let response = client
+            .post(&path)
+            .header(ContentType::JSON)
+            .body(body)
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized, "{}", path);
+    }
+    assert_eq!(gsr_len(&client, guild), 1);
+}
+
+#[test]
+fn gsr_clear() {
+    let client = client();
+    let (guild, other) = (unique_id(), unique_id());
+    for _ in 0..3 {
+        slap(&client, guild, unique_id(), None);
+    }
+    slap(&client, other, unique_id(), None);
+
+    let response = client.delete(format!("/slaps/{}", guild)).dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert_eq!(
+        response.into_json::<serde_json::Value>().unwrap()["type"],
+        "validation"
+    );
+    assert_eq!(gsr_len(&client, guild), 3);
+
+    let response = client
+        .delete(format!("/slaps/{}", guild))
+        .header(admin())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<u64>(), Some(3));
+    assert_eq!(gsr_len(&client, guild), 0);
+    assert_eq!(gsr_len(&client, other), 1);
+}
+
+#[test]
+fn gsr_clear_dry_run() {
+    let client = client();
+    let guild = unique_id();
+    for _ in 0..2 {
+        slap(&client, guild, unique_id(), None);
+    }
+    let response = client
+        .delete(format!("/slaps/{}?dry_run=true", guild))
+        .header(admin())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<u64>(), Some(2));
+    assert_eq!(gsr_len(&client, guild), 2);
+    let audit = client
+        .get(format!("/guild/{}/audit", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert!(
+        !audit.to_string().contains("cleared every slap"),
+        "{}",
+        audit
+    );
+}
+
+#[test]
+fn gsr_offenders_offset() {
+    let client = client();
+    let guild = unique_id();
+    let mut offenders = (0..5).map(|_| unique_id()).collect::<Vec<_>>();
+    for offender in offenders.iter().rev() {
+        slap(&client, guild, *offender, None);
+    }
+    slap(&client, guild, offenders[0], None);
+    offenders.sort_unstable();
+
+    let page = |query: &str| {
+        ids(client
+            .get(format!("/slaps/{}/offenders?{}", guild, query))
+            .dispatch()
+            .into_json()
+            .unwrap())
+    };
+    assert_eq!(page("number=10"), offenders);
+    assert_eq!(page("number=2"), offenders[..2]);
+    assert_eq!(page("number=2&offset=2"), offenders[2..4]);
+    assert_eq!(page("number=2&offset=4"), offenders[4..]);
+    assert!(page("number=2&offset=5").is_empty());
+}
+
+#[test]
+fn msr_sentence() {
+    let client = client();
+    let (guild, offender) = (unique_id(), unique_id());
+    let sentence = |member: u64| {
+        client
+            .get(format!("/slaps/{}/{}/sentence", guild, member))
+            .dispatch()
+            .into_json::<u64>()
+            .unwrap()
+    };
+    assert_eq!(sentence(offender), 0);
+
+    for sentence in [2, 3, 7] {
+        slap_with_sentence(&client, guild, offender, sentence);
+    }
+    slap_with_sentence(&client, guild, unique_id(), 100);
+    slap_with_sentence(&client, unique_id(), offender, 100);
+    assert_eq!(sentence(offender), 12);
+}
+
+#[test]
+fn zero_ids() {
+    let client = client();
+    let guild = new_guild(&client);
+    for path in [
+        "/guild/0/exists".to_string(),
+        format!("/guild/{}/privileges/for_role/0", guild),
+        format!("/slaps/{}/0/len", guild),
+        format!("/slaps/{}/len", u64::MAX),
+    ] {
+        let response = client.get(path.as_str()).dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "{}", path);
+        let body = response.into_json::<serde_json::Value>().unwrap();
+        assert_eq!(body["type"], "validation");
+        assert!(body["reason"].as_str().unwrap().contains("Discord id"));
+    }
+
+    let response = post_slap(
+        &client,
+        format!("guild={}&sentence=1&offender=0", guild),
+        None,
+    );
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(gsr_len(&client, guild), 0);
+}
+
+#[test]
+fn new_slap_webhook() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/slaps", listener.local_addr().unwrap());
+    let client = Harness::with(|rocket| rocket.manage(Webhook::new(Some(url))));
+    let (guild, offender) = (unique_id(), unique_id());
+    assert_eq!(slap(&client, guild, offender, None).status(), Status::Ok);
+
+    let (mut stream, _) = listener.accept().unwrap();
+    stream
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .unwrap();
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    let body = loop {
+        let read = stream.read(&mut buffer).unwrap();
+        assert_ne!(read, 0, "webhook request ended early");
+        request.extend_from_slice(&buffer[..read]);
+        let request = String::from_utf8_lossy(&request);
+        if let Some((head, body)) = request.split_once("\r\n\r\n") {
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    line.to_ascii_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|length| length.trim().parse::<usize>().unwrap())
+                })
+                .unwrap();
+            if body.len() >= length {
+                break body.to_string();
+            }
+        }
+    };
+    stream
+        .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+        .unwrap();
+    let payload = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+    assert_eq!(payload["guild"], guild.to_string());
+    assert_eq!(payload["report"]["offender"], offender.to_string());
+}
+
+#[test]
+fn guild_clear_privileges() {
+    let client = client();
+    let guild = new_guild(&client);
+    let (role, other) = (unique_id(), unique_id());
+    grant_privilege(&client, guild, role, "admin");
+    grant_privilege(&client, guild, role, "event");
+    grant_privilege(&client, guild, other, "admin");
+
+    let response = client
+        .delete(format!("/guild/{}/privileges/for_role/{}", guild, role))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<Vec<String>>(), Some(vec![]));
+
+    let privileges_for = |role: u64| {
+        client
+            .get(format!("/guild/{}/privileges/for_role/{}", guild, role))
+            .dispatch()
+            .into_json::<Vec<String>>()
+            .unwrap()
+    };
+    assert!(privileges_for(role).is_empty());
+    assert_eq!(privileges_for(other), ["admin"]);
+}
+
+#[test]
+fn gsr_count() {
+    let client = client();
+    let guild = unique_id();
+    let before = (Utc::now() - Duration::minutes(5)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let after = (Utc::now() + Duration::minutes(5)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    for _ in 0..3 {
+        slap(&client, guild, unique_id(), None);
+    }
+
+    let count = |query: String| {
+        let response = client
+            .get(format!("/slaps/{}/count?{}", guild, query))
+            .dispatch();
+        (response.status(), response.into_json::<usize>())
+    };
+    assert_eq!(count(String::new()), (Status::Ok, Some(3)));
+    assert_eq!(count(format!("since={}", before)), (Status::Ok, Some(3)));
+    assert_eq!(count(format!("since={}", after)), (Status::Ok, Some(0)));
+    assert_eq!(count(format!("until={}", before)), (Status::Ok, Some(0)));
+    assert_eq!(
+        count(format!("since={}&until={}", before, after)),
+        (Status::Ok, Some(3))
+    );
+
+    assert_eq!(count("since=yesterday".into()).0, Status::BadRequest);
+    assert_eq!(
+        count(format!("since={}&until={}", after, before)).0,
+        Status::BadRequest
+    );
+}
+
+#[test]
+fn guilds_advertise() {
+    let client = client();
+    let mut advertised = Vec::new();
+    let mut hidden = Vec::new();
+    for i in 0..6 {
+        let guild = new_guild(&client);
+        if i % 2 == 0 {
+            advertised.push(guild);
+        } else {
+            let response = client
+                .post(format!("/guild/{}/advertise", guild))
+                .header(ContentType::Form)
+                .body("policy=false")
+                .dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            hidden.push(guild);
+        }
+    }
+
+    let listed = |advertise: bool| {
+        let mut listed = Vec::new();
+        loop {
+            let page = client
+                .get(format!(
+                    "/guilds?advertise={}&number=100000&offset={}",
+                    advertise,
+                    listed.len()
+                ))
+                .dispatch()
+                .into_json::<serde_json::Value>()
+                .unwrap();
+            // clamped to the default `MAX_PAGE_SIZE`
+            assert_eq!(page["limit"], 1000);
+            let data = ids(page["data"].clone());
+            if data.is_empty() {
+                break listed;
+            }
+            listed.extend(data);
+        }
+    };
+    assert_eq!(listed(true), advertised);
+    assert_eq!(listed(false), hidden);
+
+    let all = client
+        .get("/guilds?number=3")
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap()["data"]
+        .clone();
+    let all = ids(all);
+    let page = client
+        .get("/guilds?number=2&offset=1")
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(page["offset"], 1);
+    assert_eq!(page["limit"], 2);
+    assert_eq!(ids(page["data"].clone()), all[1..3]);
+}
+
+#[test]
+fn snowflakes_as_strings() {
+    // well above 2^53, which JavaScript numbers can't represent exactly
+    let (snowflake, json) = (Snowflake(u64::MAX - 1), "\"18446744073709551614\"");
+    assert_eq!(serde_json::to_string(&snowflake).unwrap(), json);
+    assert_eq!(serde_json::from_str::<Snowflake>(json).unwrap(), snowflake);
+    assert_eq!(
+        serde_json::from_str::<Snowflake>("18446744073709551614").unwrap(),
+        snowflake
+    );
+    assert!(serde_json::from_str::<Snowflake>("\"nope\"").is_err());
+
+    let client = client();
+    let (guild, offender) = (1 << 62 | unique_id(), 1 << 61 | unique_id());
+    let report = slap(&client, guild, offender, None)
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(report["offender"], offender.to_string());
+    let offenders = client
+        .get(format!("/slaps/{}/offenders?number=1", guild))
+        .dispatch()
+        .into_json()
+        .unwrap();
+    assert_eq!(ids(offenders), [offender]);
+}
+
+#[test]
+fn guild_admin_chan() {
+    let client = client();
+    let guild = new_guild(&client);
+    let set = |body: &str| {
+        client
+            .post(format!("/guild/{}/admin_channel", guild))
+            .header(ContentType::Form)
+            .body(body.to_string())
+            .dispatch()
+            .status()
+    };
+    let chan = || {
+        client
+            .get(format!("/guild/{}/admin_channel", guild))
+            .dispatch()
+            .into_json::<Option<Snowflake>>()
+            .unwrap()
+    };
+    let configured = || {
+        client
+            .get(format!("/guild/{}/admin_channel/configured", guild))
+            .dispatch()
+            .into_json::<bool>()
+            .unwrap()
+    };
+    assert_eq!((chan(), configured()), (None, false));
+
+    let channel = unique_id();
+    for clear in ["", "chan=", "chan=null"] {
+        assert_eq!(set(&format!("chan={}", channel)), Status::Ok);
+        assert_eq!((chan(), configured()), (Some(Snowflake(channel)), true));
+        assert_eq!(set(clear), Status::Ok);
+        assert_eq!((chan(), configured()), (None, false), "{:?}", clear);
+    }
+
+    assert_eq!(set(&format!("chan={}", channel)), Status::Ok);
+    assert_eq!(set("chan=general"), Status::BadRequest);
+    assert_eq!(chan(), Some(Snowflake(channel)));
+
+    let response = client
+        .post(format!("/guild/{}/admin_channel", guild))
+        .header(ContentType::Form)
+        .body("chan=0")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert!(
+        body["reason"].as_str().unwrap().contains("`admin_channel`"),
+        "{}",
+        body
+    );
+}
+
+#[test]
+fn new_slaps_batch() {
+    let client = client();
+    let (guild, offender) = (unique_id(), unique_id());
+    let batch = |slaps: serde_json::Value| {
+        client
+            .post("/slaps/batch")
+            .header(ContentType::JSON)
+            .header(admin())
+            .body(slaps.to_string())
+            .dispatch()
+    };
+
+    let response = batch(serde_json::json!([
+        {"guild": guild.to_string(), "sentence": 1, "offender": offender.to_string()},
+        {"guild": guild, "sentence": 2, "offender": offender, "reason": "again"},
+    ]));
+    assert_eq!(response.status(), Status::Ok);
+    let reports = response.into_json::<serde_json::Value>().unwrap();
+    let reports = reports.as_array().unwrap();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0]["sentence"], 1);
+    assert_eq!(reports[1]["reason"], "again");
+    assert_eq!(gsr_len(&client, guild), 2);
+
+    let other = unique_id();
+    let response = batch(serde_json::json!([
+        {"guild": other, "sentence": 1, "offender": offender},
+        {"guild": other, "sentence": 1, "offender": 0},
+    ]));
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(gsr_len(&client, other), 0);
+}
+
+#[test]
+fn unknown_guild_getters() {
+    let client = client();
+    let (guild, unknown) = (new_guild(&client), unique_id());
+
+    let response = client
+        .get(format!("/guild/{}/welcome_message", guild))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<Option<String>>(), Some(None));
+
+    for getter in [
+        "",
+        "/welcome_message",
+        "/goodbye_message",
+        "/advertise",
+        "/admin_channel",
+        "/admin_channel/configured",
+    ] {
+        let response = client
+            .get(format!("/guild/{}{}", unknown, getter))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound, "{}", getter);
+        let body = response.into_json::<serde_json::Value>().unwrap();
+        assert_eq!(body["type"], "validation");
+    }
+
+    let response = client.get(format!("/guild/{}/exists", unknown)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<bool>(), Some(false));
+}
+
+#[test]
+fn max_page_size() {
+    let client = client_with(|figment| figment.merge(("max_page_size", 2)));
+    let guild = unique_id();
+    for _ in 0..3 {
+        slap(&client, guild, unique_id(), None);
+    }
+
+    let page = |query: &str| {
+        client
+            .get(format!("/slaps/{}/reports?{}", guild, query))
+            .dispatch()
+            .into_json::<serde_json::Value>()
+            .unwrap()
+    };
+    for query in ["number=5", ""] {
+        let page = page(query);
+        assert_eq!(page["data"].as_array().unwrap().len(), 2, "{}", query);
+        assert_eq!(page["limit"], 2);
+        assert_eq!(page["total"], 3);
+    }
+    assert_eq!(page("number=1")["limit"], 1);
+
+    // untouched elsewhere
+    let page = client
+        .sibling(|rocket| rocket)
+        .get(format!("/slaps/{}/reports?number=5", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(page["data"].as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn msgpack_listings() {
+    let client = client();
+    let guild = unique_id();
+    for _ in 0..2 {
+        slap(&client, guild, unique_id(), None);
+    }
+
+    for path in [
+        format!("/slaps/{}/reports?number=10", guild),
+        format!("/slaps/{}/offenders?number=10", guild),
+    ] {
+        let json = client.get(path.as_str()).dispatch();
+        assert_eq!(json.content_type(), Some(ContentType::JSON));
+        let json = json.into_json::<serde_json::Value>().unwrap();
+
+        let msgpack = client
+            .get(path.as_str())
+            .header(Header::new("Accept", "application/msgpack"))
+            .dispatch();
+        assert_eq!(
+            msgpack.content_type(),
+            Some(ContentType::new("application", "msgpack"))
+        );
+        let msgpack = msgpack.into_bytes().unwrap();
+        assert_eq!(
+            rmp_serde::from_slice::<serde_json::Value>(&msgpack).unwrap(),
+            json,
+            "{}",
+            path
+        );
+    }
+}
+
+#[test]
+fn gzip_responses() {
+    let client = client();
+    let guild = unique_id();
+    for _ in 0..30 {
+        slap(&client, guild, unique_id(), None);
+    }
+    let path = format!("/slaps/{}/reports?number=30", guild);
+    let plain = client.get(path.as_str()).dispatch();
+    assert_eq!(plain.headers().get_one("Content-Encoding"), None);
+    // the same response may be compressed for other clients
+    assert_eq!(plain.headers().get_one("Vary"), Some("Accept-Encoding"));
+    let plain = plain.into_bytes().unwrap();
+
+    let gzipped = client
+        .get(path.as_str())
+        .header(Header::new("Accept-Encoding", "deflate, gzip;q=0.8"))
+        .dispatch();
+    assert_eq!(gzipped.headers().get_one("Content-Encoding"), Some("gzip"));
+    assert_eq!(gzipped.headers().get_one("Vary"), Some("Accept-Encoding"));
+    let gzipped = gzipped.into_bytes().unwrap();
+    assert!(gzipped.len() < plain.len());
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(gzipped.as_slice())
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, plain);
+
+    // the compressed body isn't the same bytes, so a strong tag can't be shared with the plain one
+    let plain = client.get("/test/strong_etag").dispatch();
+    assert_eq!(plain.headers().get_one("ETag"), Some("\"strong\""));
+    let gzipped = client
+        .get("/test/strong_etag")
+        .header(Header::new("Accept-Encoding", "gzip"))
+        .dispatch();
+    assert_eq!(gzipped.headers().get_one("Content-Encoding"), Some("gzip"));
+    assert_eq!(gzipped.headers().get_one("ETag"), Some("W/\"strong\""));
+    assert_eq!(gzipped.headers().get_one("Vary"), Some("Accept-Encoding"));
+
+    // below the threshold
+    let small = client
+        .get("/version")
+        .header(Header::new("Accept-Encoding", "gzip"))
+        .dispatch();
+    assert_eq!(small.headers().get_one("Content-Encoding"), None);
+    // streamed
+    let export = client
+        .get(format!("/slaps/{}/export.csv", guild))
+        .header(Header::new("Accept-Encoding", "gzip"))
+        .dispatch();
+    assert_eq!(export.status(), Status::Ok);
+    assert_eq!(export.headers().get_one("Content-Encoding"), None);
+}
+
+#[test]
+fn guild_put() {
+    let client = client();
+    let guild = unique_id();
+    let chan = unique_id();
+    let put = |body: String| {
+        client
+            .put(format!("/guild/{}", guild))
+            .header(ContentType::Form)
+            .body(body)
+            .dispatch()
+            .status()
+    };
+    let settings = || {
+        client
+            .get(format!("/guild/{}", guild))
+            .dispatch()
+            .into_json::<serde_json::Value>()
+            .unwrap()
+    };
+
+    assert_eq!(
+        put(format!(
+            "advertise=true&welcome_message=hi&admin_channel={}",
+            chan
+        )),
+        Status::Created
+    );
+    assert_eq!(
+        settings(),
+        serde_json::json!({
+            "id": guild.to_string(),
+            "admin_channel": chan.to_string(),
+            "advertise": true,
+            "welcome_message": "hi",
+            "goodbye_message": null,
+            "timezone": null,
+        })
+    );
+    // created along with its audit entry
+    let audit = client
+        .get(format!("/guild/{}/audit", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(audit["total"], 1);
+    assert_eq!(audit["data"][0]["summary"], "created the guild");
+
+    assert_eq!(
+        put("advertise=false&goodbye_message=bye".into()),
+        Status::Ok
+    );
+    assert_eq!(
+        settings(),
+        serde_json::json!({
+            "id": guild.to_string(),
+            "admin_channel": null,
+            "advertise": false,
+            "welcome_message": null,
+            "goodbye_message": "bye",
+            "timezone": null,
+        })
+    );
+
+    let response = client
+        .put(format!("/guild/{}", guild))
+        .header(ContentType::JSON)
+        .body(serde_json::json!({"advertise": true, "admin_channel": chan.to_string()}).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        settings(),
+        serde_json::json!({
+            "id": guild.to_string(),
+            "admin_channel": chan.to_string(),
+            "advertise": true,
+            "welcome_message": null,
+            "goodbye_message": null,
+            "timezone": null,
+        })
+    );
+}
+
+#[test]
+fn setters_of_unknown_guild() {
+    let client = client();
+    let guild = unique_id();
+    for (setter, body) in [
+        ("admin_channel", format!("chan={}", unique_id())),
+        ("advertise", "policy=true".to_owned()),
+        ("welcome_message", "message=hi".to_owned()),
+        ("goodbye_message", "message=bye".to_owned()),
+    ] {
+        let response = client
+            .post(format!("/guild/{}/{}", guild, setter))
+            .header(ContentType::Form)
+            .body(body)
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound, "{}", setter);
+    }
+    let audit = client
+        .get(format!("/guild/{}/audit", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(audit["total"], 0);
+    let exists = client.get(format!("/guild/{}/exists", guild)).dispatch();
+    assert_eq!(exists.into_json::<bool>(), Some(false));
+}
+
+#[test]
+fn writes_to_unknown_guild() {
+    let client = client();
+    let (guild, role) = (unique_id(), unique_id());
+    // slaps don't create their guild
+    slap(&client, guild, unique_id(), None);
+    let transfer = format!(r#"{{"from": "{}", "to": "{}"}}"#, unique_id(), unique_id());
+    for (request, name) in [
+        (
+            client
+                .post(format!("/guild/{}/privileges/grant/{}", guild, role))
+                .header(ContentType::Form)
+                .body("privileges=admin"),
+            "grant",
+        ),
+        (
+            client
+                .post(format!("/guild/{}/privileges/grant", guild))
+                .header(ContentType::JSON)
+                .body(serde_json::json!([{"role": role, "privileges": ["admin"]}]).to_string()),
+            "grant to roles",
+        ),
+        (
+            client
+                .put(format!("/guild/{}/privileges/for_role/{}", guild, role))
+                .header(ContentType::JSON)
+                .body(r#"["event"]"#),
+            "set",
+        ),
+        (
+            client.delete(format!("/guild/{}/privileges/for_role/{}", guild, role)),
+            "clear",
+        ),
+        (
+            client
+                .post(format!("/slaps/{}/transfer", guild))
+                .header(ContentType::JSON)
+                .header(admin())
+                .body(transfer.clone()),
+            "transfer",
+        ),
+        (
+            client
+                .post(format!("/slaps/{}/enforcer/reassign", guild))
+                .header(ContentType::JSON)
+                .header(admin())
+                .body(transfer.clone()),
+            "reassign",
+        ),
+    ] {
+        let response = request.dispatch();
+        assert_eq!(response.status(), Status::NotFound, "{}", name);
+        let error = response.into_json::<serde_json::Value>().unwrap();
+        assert_eq!(error["resource"], "guild", "{}", name);
+    }
+
+    // only the slap was audited
+    let audit = client
+        .get(format!("/guild/{}/audit", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(audit["total"], 1);
+    let exists = client.get(format!("/guild/{}/exists", guild)).dispatch();
+    assert_eq!(exists.into_json::<bool>(), Some(false));
+}
+
+#[test]
+fn guild_audit() {
+    let client = client();
+    let guild = new_guild(&client);
+    let response = client
+        .post(format!("/guild/{}/advertise", guild))
+        .header(ContentType::Form)
+        .header(admin())
+        .body("policy=false")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let audit = client
+        .get(format!("/guild/{}/audit", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(audit["total"], 2);
+    let entries = audit["data"].as_array().unwrap();
+    assert_eq!(entries[0]["endpoint"], "POST /guild/<guild>/advertise");
+    assert_eq!(entries[0]["actor"], "admin");
+    assert_eq!(entries[0]["summary"], "set advertise to false");
+    assert_eq!(entries[1]["summary"], "created the guild");
+    assert_eq!(entries[1]["actor"], serde_json::Value::Null);
+}
+
+#[test]
+fn gsr_slaps_sort() {
+    let client = client();
+    let guild = unique_id();
+    for sentence in [2, 5, 1, 5] {
+        slap_with_sentence(&client, guild, unique_id(), sentence);
+    }
+    let sentences = |sort: &str| {
+        let body = client
+            .get(format!("/slaps/{}/reports?sort={}", guild, sort))
+            .dispatch()
+            .into_json::<serde_json::Value>()
+            .unwrap();
+        body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|slap| {
+                (
+                    slap["sentence"].as_u64().unwrap(),
+                    slap["id"].as_i64().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let created = sentences("created_asc");
+    assert_eq!(
+        created
+            .iter()
+            .map(|(sentence, _)| *sentence)
+            .collect::<Vec<_>>(),
+        [2, 5, 1, 5]
+    );
+    let ids = created.iter().map(|(_, id)| *id).collect::<Vec<_>>();
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let mut reversed = created.clone();
+    reversed.reverse();
+    assert_eq!(sentences("created_desc"), reversed);
+    assert_eq!(
+        sentences("sentence_asc"),
+        [created[2], created[0], created[1], created[3]]
+    );
+    // ties are broken by id in every order
+    assert_eq!(
+        sentences("sentence_desc"),
+        [created[1], created[3], created[0], created[2]]
+    );
+}
+
+#[test]
+fn gsr_transfer() {
+    let client = client();
+    let guild = new_guild(&client);
+    let (from, to) = (unique_id(), unique_id());
+    for _ in 0..3 {
+        slap(&client, guild, from, None);
+    }
+    slap(&client, guild, to, None);
+    let msr_len = |member: u64| -> usize {
+        client
+            .get(format!("/slaps/{}/{}/len", guild, member))
+            .dispatch()
+            .into_json()
+            .unwrap()
+    };
+
+    let response = client
+        .post(format!("/slaps/{}/transfer", guild))
+        .header(ContentType::JSON)
+        .header(admin())
+        .body(format!(r#"{{"from": "{}", "to": {}}}"#, from, to))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<u64>(), Some(3));
+    assert_eq!(msr_len(from), 0);
+    assert_eq!(msr_len(to), 4);
+    assert_eq!(gsr_len(&client, guild), 4);
+}
+
+#[test]
+fn pool_timeout_unavailable() {
+    let client = client();
+    for _ in 0..5 {
+        let response = client.get("/test/pool_timeout").dispatch();
+        assert_eq!(response.status(), Status::ServiceUnavailable);
+        assert_eq!(response.headers().get_one("Retry-After"), Some("1"));
+        let body = response.into_json::<serde_json::Value>().unwrap();
+        assert_eq!(body["type"], "internal");
+    }
+
+    // the breaker is now open, even requests which would succeed fail right away
+    let response = client.get(format!("/slaps/{}/len", unique_id())).dispatch();
+    assert_eq!(response.status(), Status::ServiceUnavailable);
+    let retry_after = response.headers().get_one("Retry-After").unwrap();
+    assert!((1..=5).contains(&retry_after.parse::<u64>().unwrap()));
+}
+
+#[test]
+fn circuit_breaker() {
+    let mut config = PoolConfig::from_vars(|var| match var {
+        "DATABASE_URL" => Some("postgres://localhost/botanist".to_string()),
+        "DATABASE_BREAKER_THRESHOLD" => Some("2".to_string()),
+        "DATABASE_BREAKER_COOLDOWN" => Some("0".to_string()),
+        _ => None,
+    })
+    .unwrap();
+    let timeout = || -> ApiResult<()> { Err(sqlx::Error::PoolTimedOut.into()) };
+
+    let breaker = CircuitBreaker::new(&config);
+    breaker.record(&timeout());
+    breaker.record(&timeout());
+    // no cooldown, the breaker lets requests through right away
+    assert!(breaker.check().is_ok());
+
+    config.breaker_cooldown = std::time::Duration::from_secs(60);
+    let breaker = CircuitBreaker::new(&config);
+    breaker.record(&timeout());
+    assert!(breaker.check().is_ok());
+    breaker.record(&Err(
+        sqlx::Error::Protocol("unexpected message".into()).into()
+    ));
+    breaker.record(&timeout());
+    let err = breaker.check().unwrap_err();
+    assert_eq!(err.status(), Status::ServiceUnavailable);
+    assert_eq!(err.retry_after(), Some(60));
+
+    breaker.record(&Ok(()));
+    assert!(breaker.check().is_ok());
+    breaker.record(&timeout());
+    assert!(breaker.check().is_ok());
+}
+
+#[test]
+fn guild_resolve_privileges() {
+    let client = client();
+    let guild = new_guild(&client);
+    let (role, other, none) = (unique_id(), unique_id(), unique_id());
+    grant_privilege(&client, guild, role, "admin");
+    grant_privilege(&client, guild, other, "event");
+    grant_privilege(&client, guild, other, "admin");
+
+    let resolve = |roles: &[u64]| {
+        let response = client
+            .post(format!("/guild/{}/privileges/resolve", guild))
+            .header(ContentType::JSON)
+            .body(serde_json::to_string(roles).unwrap())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let mut privileges = response.into_json::<Vec<String>>().unwrap();
+        privileges.sort();
+        privileges
+    };
+    assert_eq!(resolve(&[role, other, none]), ["admin", "event"]);
+    assert_eq!(resolve(&[role]), ["admin"]);
+    assert!(resolve(&[none]).is_empty());
+    assert!(resolve(&[]).is_empty());
+}
+
+#[rocket::async_test]
+async fn migrations() {
+    let mut config = PoolConfig::from_env().unwrap();
+    config.run_migrations = true;
+    let pool = db::connect(&config).await.unwrap();
+    // migrating an up to date schema changes nothing
+    db::prepare(&pool).await.unwrap();
+    db::check(&pool).await.unwrap();
+
+    config.run_migrations = false;
+    db::connect(&config).await.unwrap();
+}
+
+#[test]
+fn pretty_json() {
+    let client = client();
+    let compact = client.get("/version").dispatch().into_string().unwrap();
+    assert!(!compact.contains('\n'));
+    let pretty = client
+        .get("/version?pretty")
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert!(pretty.contains("\n  \"version\": "));
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&pretty).unwrap(),
+        serde_json::from_str::<serde_json::Value>(&compact).unwrap()
+    );
+
+    let client = client_with(|figment| figment.merge(("pretty_json", true)));
+    let response = client.get("/version").dispatch().into_string().unwrap();
+    assert!(response.contains('\n'));
+    let response = client
+        .get("/version?pretty=false")
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert!(!response.contains('\n'));
+}
+
+#[test]
+fn guilds_count() {
+    let client = client();
+    let count = || {
+        client
+            .get("/guilds/count")
+            .dispatch()
+            .into_json::<usize>()
+            .unwrap()
+    };
+    assert_eq!(count(), 0);
+    new_guild(&client);
+    new_guild(&client);
+    assert_eq!(count(), 2);
+
+    let listed = client
+        .get("/guilds?number=1")
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(listed["total"], 2);
+}
+
+#[test]
+fn advertise_spellings() {
+    let client = client();
+    let guild = new_guild(&client);
+    let set_advertise = |policy: &str| {
+        client
+            .post(format!("/guild/{}/advertise", guild))
+            .header(ContentType::Form)
+            .body(format!("policy={}", policy))
+            .dispatch()
+            .status()
+    };
+    let advertise = || {
+        client
+            .get(format!("/guild/{}/advertise", guild))
+            .dispatch()
+            .into_json::<bool>()
+            .unwrap()
+    };
+
+    for (policy, expected) in [
+        ("0", false),
+        ("YES", true),
+        ("no", false),
+        ("1", true),
+        ("False", false),
+        ("true", true),
+    ] {
+        assert_eq!(set_advertise(policy), Status::Ok, "{}", policy);
+        assert_eq!(advertise(), expected, "{}", policy);
+    }
+
+    let response = client
+        .post(format!("/guild/{}/advertise", guild))
+        .header(ContentType::Form)
+        .body("policy=maybe")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(body["type"], "validation");
+    assert!(advertise());
+
+    let id = unique_id();
+    let response = client
+        .post("/guild/new")
+        .header(ContentType::Form)
+        .body(format!("id={}&advertise=no", id))
+        .dispatch();
+    assert_eq!(response.status(), Status::Created);
+    let response = client.get(format!("/guild/{}/advertise", id)).dispatch();
+    assert_eq!(response.into_json::<bool>(), Some(false));
+}
+
+#[test]
+fn retry_after_errors() {
+    let client = client();
+    for (status, kind) in [
+        (Status::TooManyRequests, "validation"),
+        (Status::ServiceUnavailable, "internal"),
+    ] {
+        let response = client
+            .get(format!("/test/retry_after/{}", status.code))
+            .dispatch();
+        assert_eq!(response.status(), status);
+        assert_eq!(response.headers().get_one("Retry-After"), Some("7"));
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        let body = response.into_json::<serde_json::Value>().unwrap();
+        assert_eq!(body["type"], kind);
+        assert!(body["reason"].as_str().unwrap().contains("7 seconds"));
+    }
+
+    // errors which can't be retried as is tell no wait
+    let response = client.get("/test/failing_query").dispatch();
+    assert_eq!(response.headers().get_one("Retry-After"), None);
+}
+
+#[test]
+fn gsr_recent() {
+    let client = client();
+    let guild = unique_id();
+    let offenders = (0..3).map(|_| unique_id()).collect::<Vec<_>>();
+    for offender in &offenders {
+        slap(&client, guild, *offender, None);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let recent = client
+        .get(format!("/slaps/{}/recent?number=2", guild))
+        .dispatch()
+        .into_json::<Vec<serde_json::Value>>()
+        .unwrap();
+    let recent = recent
+        .iter()
+        .map(|slap| slap["offender"].as_str().unwrap().parse::<u64>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(recent, [offenders[2], offenders[1]]);
+}
+
+#[test]
+fn slap_reason_trimmed() {
+    let client = client();
+    let guild = unique_id();
+    for (reason, expected) in [
+        ("", serde_json::Value::Null),
+        ("%20%20%09", serde_json::Value::Null),
+        ("%20spam%20", "spam".into()),
+    ] {
+        let response = post_slap(
+            &client,
+            format!(
+                "guild={}&sentence=1&offender={}&reason={}",
+                guild,
+                unique_id(),
+                reason
+            ),
+            None,
+        );
+        assert_eq!(response.status(), Status::Ok);
+        let slap = response.into_json::<serde_json::Value>().unwrap();
+        assert_eq!(slap["reason"], expected, "{:?}", reason);
+    }
+}
+
+#[test]
+fn harness_smoke() {
+    let harness = Harness::new();
+    let (guild, member) = harness.seed(3);
+    assert_eq!(gsr_len(harness.client(), guild), 3);
+
+    // the harness' tables are its own
+    let guilds = harness
+        .client()
+        .get("/guilds/count")
+        .dispatch()
+        .into_json::<usize>();
+    assert_eq!(guilds, Some(1));
+    let sentence = block_on(db::member_sentence(harness.pool(), guild, member)).unwrap();
+    assert_eq!(sentence, 3);
+}
+
+#[test]
+fn gsr_report_exists() {
+    let client = client();
+    let (guild, other) = (unique_id(), unique_id());
+    let report = slap(&client, guild, unique_id(), None)
+        .into_json::<serde_json::Value>()
+        .unwrap()["id"]
+        .as_i64()
+        .unwrap();
+    let exists = |guild: u64, report: i64| {
+        client
+            .get(format!("/slaps/{}/{}/exists", guild, report))
+            .dispatch()
+            .into_json::<bool>()
+            .unwrap()
+    };
+    assert!(exists(guild, report));
+    assert!(!exists(guild, report + 1_000_000));
+    assert!(!exists(other, report));
+}
+
+#[test]
+fn gsr_slaps_fields() {
+    let client = client();
+    let guild = unique_id();
+    let offender = unique_id();
+    slap_with_sentence(&client, guild, offender, 3);
+    let reports = |query: &str| {
+        client
+            .get(format!("/slaps/{}/reports?{}", guild, query))
+            .dispatch()
+    };
+
+    let full = reports("").into_json::<serde_json::Value>().unwrap();
+    let full = full["data"][0].as_object().unwrap();
+    assert_eq!(
+        full.keys().map(String::as_str).collect::<Vec<_>>(),
+        [
+            "created_at",
+            "enforcer",
+            "id",
+            "offender",
+            "reason",
+            "sentence"
+        ]
+    );
+
+    let sparse = reports("fields=offender,sentence")
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(sparse["total"], 1);
+    assert_eq!(
+        sparse["data"][0],
+        serde_json::json!({"offender": offender.to_string(), "sentence": 3})
+    );
+
+    let response = reports("fields=offender,password");
+    assert_eq!(response.status(), Status::BadRequest);
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert!(body["reason"].as_str().unwrap().contains("`password`"));
+}
+
+#[test]
+fn prune_slaps() {
+    let harness = Harness::new();
+    let (guild, _) = harness.seed(3);
+    block_on(
+        sqlx::query(
+            "UPDATE slaps SET created_at = now() - INTERVAL '31 days'
+            WHERE id IN (SELECT id FROM slaps ORDER BY id LIMIT 2)",
+        )
+        .execute(harness.pool()),
+    )
+    .unwrap();
+
+    assert_eq!(block_on(retention::prune(harness.pool(), 30)).unwrap(), 2);
+    assert_eq!(gsr_len(harness.client(), guild), 1);
+    assert_eq!(block_on(retention::prune(harness.pool(), 30)).unwrap(), 0);
+}
+
+#[test]
+fn gsr_top_enforcers() {
+    let client = client();
+    let guild = unique_id();
+    let (first, second) = (unique_id(), unique_id());
+    for (enforcer, slaps) in [(Some(second), 1), (Some(first), 3), (None, 5)] {
+        for _ in 0..slaps {
+            let mut form = format!("guild={}&sentence=1&offender={}", guild, unique_id());
+            if let Some(enforcer) = enforcer {
+                form.push_str(&format!("&enforcer={}", enforcer));
+            }
+            assert_eq!(post_slap(&client, form, None).status(), Status::Ok);
+        }
+    }
+
+    let top = |query: &str| {
+        client
+            .get(format!("/slaps/{}/enforcers/top?{}", guild, query))
+            .dispatch()
+            .into_json::<serde_json::Value>()
+            .unwrap()
+    };
+    // the slaps without an enforcer aren't counted
+    assert_eq!(
+        top(""),
+        serde_json::json!([
+            {"enforcer": first.to_string(), "count": 3},
+            {"enforcer": second.to_string(), "count": 1},
+        ])
+    );
+    assert_eq!(
+        top("number=1"),
+        serde_json::json!([{"enforcer": first.to_string(), "count": 3}])
+    );
+}
+
+#[test]
+fn cache_control() {
+    let client = client_with(|figment| figment.merge(("cache_ttl", 60)));
+    let guild = new_guild(&client);
+    let cache_control = |response: LocalResponse<'_>| {
+        response
+            .headers()
+            .get_one("Cache-Control")
+            .map(str::to_string)
+    };
+
+    let getter = client.get(format!("/v1/guild/{}/exists", guild)).dispatch();
+    assert_eq!(cache_control(getter).as_deref(), Some("max-age=60"));
+    let listing = client.get(format!("/slaps/{}/reports", guild)).dispatch();
+    assert_eq!(cache_control(listing).as_deref(), Some("no-cache"));
+    let mutation = client
+        .post(format!("/guild/{}/advertise", guild))
+        .header(ContentType::Form)
+        .body("policy=false")
+        .dispatch();
+    assert_eq!(mutation.status(), Status::Ok);
+    assert_eq!(cache_control(mutation).as_deref(), Some("no-store"));
+
+    // a guild looked up before it exists mustn't stay missing
+    let missing = client
+        .get(format!("/guild/{}/advertise", unique_id()))
+        .dispatch();
+    assert_eq!(cache_control(missing).as_deref(), Some("no-cache"));
+}
+
+#[test]
+fn gsr_search() {
+    let client = client();
+    let guild = unique_id();
+    // form-encoded
+    for reason in [
+        "spam+in+general",
+        "posted+SPAM+links",
+        "rude",
+        "100%25+toxic",
+        "",
+    ] {
+        let form = format!(
+            "guild={}&sentence=1&offender={}&reason={}",
+            guild,
+            unique_id(),
+            reason
+        );
+        assert_eq!(post_slap(&client, form, None).status(), Status::Ok);
+    }
+    let search = |query: &str| {
+        client
+            .get(format!("/slaps/{}/search?{}", guild, query))
+            .dispatch()
+    };
+    let reasons = |query: &str| {
+        let page = search(query).into_json::<serde_json::Value>().unwrap();
+        page["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|slap| slap["reason"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(reasons("q=spam"), ["spam in general", "posted SPAM links"]);
+    assert_eq!(reasons("q=spam&number=1&offset=1"), ["posted SPAM links"]);
+    assert_eq!(reasons("q=%25"), ["100% toxic"]);
+    assert_eq!(reasons("q=_"), Vec::<String>::new());
+    assert_eq!(
+        reasons("q=%27%3B+DROP+TABLE+slaps%3B--"),
+        Vec::<String>::new()
+    );
+    assert_eq!(search("q=+").status(), Status::BadRequest);
+}
+
+#[test]
+fn slap_without_enforcer() {
+    let client = client();
+    let guild = unique_id();
+    let report = slap(&client, guild, unique_id(), None)
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(report["enforcer"], serde_json::Value::Null);
+    let stored = client
+        .get(format!("/slaps/{}/{}", guild, report["id"]))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(stored["enforcer"], serde_json::Value::Null);
+
+    let form = format!(
+        "guild={}&sentence=1&offender={}&enforcer=0",
+        guild,
+        unique_id()
+    );
+    assert_eq!(post_slap(&client, form, None).status(), Status::BadRequest);
+}
+
+#[test]
+fn guild_privileges_diff() {
+    let client = client();
+    let guild = new_guild(&client);
+    let (role, unchanged, bare) = (unique_id(), unique_id(), unique_id());
+    grant_privilege(&client, guild, role, "admin");
+    grant_privilege(&client, guild, unchanged, "manager");
+
+    let diff = |desired: serde_json::Value| {
+        client
+            .post(format!("/guild/{}/privileges/diff", guild))
+            .header(ContentType::JSON)
+            .body(desired.to_string())
+            .dispatch()
+    };
+    let response = diff(serde_json::json!({
+        role.to_string(): ["event"],
+        unchanged.to_string(): ["manager"],
+        bare.to_string(): [],
+    }));
+    assert_eq!(
+        response.into_json::<serde_json::Value>().unwrap(),
+        serde_json::json!([{"role": role.to_string(), "add": ["event"], "remove": ["admin"]}])
+    );
+
+    // nothing was applied
+    let privileges = client
+        .get(format!("/guild/{}/privileges/for_role/{}", guild, role))
+        .dispatch()
+        .into_json::<Vec<String>>();
+    assert_eq!(privileges, Some(vec!["admin".to_string()]));
+
+    let response = diff(serde_json::json!({role.to_string(): ["owner"]}));
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn new_slap_concurrently() {
+    let harness = Harness::new();
+    block_on(slap_concurrently(&harness));
+}
+
+/// Creates slaps through an asynchronous client against the tables of `harness`, all at once.
+async fn slap_concurrently(harness: &Harness) {
+    const SLAPS: usize = 50;
+    let client = rocket::local::asynchronous::Client::tracked(harness.rocket(|rocket| rocket))
+        .await
+        .expect("valid rocket instance");
+    let guild = unique_id();
+    let requests = (0..SLAPS).map(|_| {
+        client
+            .post("/slaps/new")
+            .header(ContentType::Form)
+            .body(format!(
+                "guild={}&sentence=1&offender={}",
+                guild,
+                unique_id()
+            ))
+            .dispatch()
+    });
+
+    let mut ids = std::collections::HashSet::new();
+    for response in rocket::futures::future::join_all(requests).await {
+        assert_eq!(response.status(), Status::Ok);
+        let report = response.into_json::<serde_json::Value>().await.unwrap();
+        ids.insert(report["id"].as_i64().unwrap());
+    }
+    assert_eq!(ids.len(), SLAPS);
+    let len = client
+        .get(format!("/slaps/{}/len", guild))
+        .dispatch()
+        .await
+        .into_json::<usize>()
+        .await;
+    assert_eq!(len, Some(SLAPS));
+}
+
+/// `POST`s `csv` to be imported into `guild`, as an admin.
+fn import<'c>(client: &'c Client, guild: u64, csv: String) -> LocalResponse<'c> {
+    client
+        .post(format!("/slaps/{}/import", guild))
+        .header(admin())
+        .header(ContentType::CSV)
+        .body(csv)
+        .dispatch()
+}
+
+#[test]
+fn gsr_import() {
+    let client = client();
+    let (from, to) = (unique_id(), unique_id());
+    let offender = unique_id();
+    let form = format!(
+        "guild={}&sentence=2&offender={}&enforcer={}&reason=spam%2C+again",
+        from,
+        offender,
+        unique_id()
+    );
+    assert_eq!(post_slap(&client, form, None).status(), Status::Ok);
+    slap(&client, from, offender, None);
+    let export = client
+        .get(format!("/slaps/{}/export.csv", from))
+        .dispatch()
+        .into_string()
+        .unwrap();
+
+    let response = import(&client, to, export);
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_json::<serde_json::Value>().unwrap(),
+        serde_json::json!({"imported": 2, "errors": []})
+    );
+    let reports = |guild: u64| {
+        let mut page = client
+            .get(format!("/slaps/{}/reports", guild))
+            .dispatch()
+            .into_json::<serde_json::Value>()
+            .unwrap();
+        for slap in page["data"].as_array_mut().unwrap() {
+            slap.as_object_mut().unwrap().remove("id");
+        }
+        page["data"].take()
+    };
+    // everything but the ids is kept
+    assert_eq!(reports(to), reports(from));
+}
+
+#[test]
+fn gsr_import_bad_row() {
+    let client = client();
+    let guild = unique_id();
+    let csv = format!(
+        "id,offender,enforcer,sentence,reason,created_at\n\
+        1,{0},,1,,2021-09-14T12:00:00Z\n\
+        2,someone,,1,,2021-09-14T12:00:00Z\n\
+        3,{0},,1\n",
+        unique_id()
+    );
+    let response = import(&client, guild, csv);
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(body["imported"], 1);
+    let errors = body["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0]["line"], 3);
+    assert!(errors[0]["reason"].as_str().unwrap().contains("`offender`"));
+    assert_eq!(errors[1]["line"], 4);
+    assert_eq!(gsr_len(&client, guild), 1);
+
+    let response = import(&client, guild, "offender,sentence\n1,1\n".to_string());
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(gsr_len(&client, guild), 1);
+}
+
+#[test]
+fn advertise_kill_switch() {
+    let client = client();
+    // advertising
+    let guild = new_guild(&client);
+    let advertise = |client: &Client| {
+        let alone = client
+            .get(format!("/guild/{}/advertise", guild))
+            .dispatch()
+            .into_json::<bool>()
+            .unwrap();
+        let settings = client
+            .get(format!("/guild/{}", guild))
+            .dispatch()
+            .into_json::<serde_json::Value>()
+            .unwrap();
+        assert_eq!(settings["advertise"], alone);
+        alone
+    };
+    assert!(advertise(&client));
+
+    let disabled = client.sibling(settings(|figment| {
+        figment.merge(("advertise_enabled", false))
+    }));
+    assert!(!advertise(&disabled));
+    // the stored policy is untouched
+    assert!(advertise(&client));
+}
+
+#[test]
+fn gsr_offenders_detailed() {
+    let client = client();
+    let guild = unique_id();
+    let (offender, other) = (unique_id(), unique_id());
+    slap_with_sentence(&client, guild, offender, 2);
+    let last = post_slap(
+        &client,
+        format!("guild={}&sentence=3&offender={}", guild, offender),
+        None,
+    )
+    .into_json::<serde_json::Value>()
+    .unwrap();
+    slap_with_sentence(&client, guild, other, 1);
+
+    let offenders = client
+        .get(format!("/slaps/{}/offenders/detailed", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(offenders.as_array().unwrap().len(), 2);
+    assert_eq!(
+        offenders[0],
+        serde_json::json!({
+            "offender": offender.to_string(),
+            "slap_count": 2,
+            "total_sentence": 5,
+            "last_slap_at": last["created_at"],
+        })
+    );
+    assert_eq!(offenders[1]["offender"], other.to_string());
+}
+
+#[test]
+fn gsr_pardon() {
+    let client = client();
+    let guild = unique_id();
+    let (offender, moderator) = (unique_id(), unique_id());
+    let report = slap(&client, guild, offender, None)
+        .into_json::<serde_json::Value>()
+        .unwrap()["id"]
+        .as_i64()
+        .unwrap();
+    slap_with_sentence(&client, guild, offender, 1);
+    let pardon = |report: i64, body: serde_json::Value| {
+        client
+            .post(format!("/slaps/{}/{}/pardon", guild, report))
+            .header(ContentType::JSON)
+            .header(admin())
+            .body(body.to_string())
+            .dispatch()
+    };
+
+    let response = pardon(
+        report,
+        serde_json::json!({"reason": " mistaken identity ", "by": moderator}),
+    );
+    assert_eq!(response.status(), Status::Ok);
+    let pardoned = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(pardoned["pardon"]["reason"], "mistaken identity");
+    assert_eq!(pardoned["pardon"]["by"], moderator.to_string());
+
+    let reports = |query: &str| {
+        client
+            .get(format!("/slaps/{}/reports?{}", guild, query))
+            .dispatch()
+            .into_json::<serde_json::Value>()
+            .unwrap()
+    };
+    let hidden = reports("");
+    assert_eq!(hidden["total"], 1);
+    assert_ne!(hidden["data"][0]["id"], report);
+    assert!(hidden["data"][0].get("pardon").is_none());
+    let shown = reports("include_pardoned=true");
+    assert_eq!(shown["total"], 2);
+    assert_eq!(shown["data"][0]["id"], report);
+    assert_eq!(shown["data"][0]["pardon"], pardoned["pardon"]);
+
+    assert_eq!(gsr_len(&client, guild), 1);
+    let all = client
+        .get(format!("/slaps/{}/len?include_pardoned=true", guild))
+        .dispatch()
+        .into_json::<usize>();
+    assert_eq!(all, Some(2));
+    let severity = client
+        .get(format!("/slaps/{}/{}/sentence", guild, offender))
+        .dispatch()
+        .into_json::<u64>();
+    assert_eq!(severity, Some(1));
+
+    let again = pardon(report, serde_json::json!({"reason": "twice"}));
+    assert_eq!(again.status(), Status::Conflict);
+    let unknown = pardon(report + 1_000_000, serde_json::json!({"reason": "nope"}));
+    assert_eq!(unknown.status(), Status::NotFound);
+    let blank = pardon(report, serde_json::json!({"reason": "  "}));
+    assert_eq!(blank.status(), Status::BadRequest);
+}
+
+#[test]
+fn effective_messages() {
+    let client = client_with(|figment| figment.merge(("default_goodbye_message", "See you!")));
+    let guild = new_guild(&client);
+    let effective = |message: &str| {
+        client
+            .get(format!("/guild/{}/{}/effective", guild, message))
+            .dispatch()
+            .into_json::<String>()
+            .unwrap()
+    };
+    assert_eq!(effective("welcome_message"), "Welcome!");
+    assert_eq!(effective("goodbye_message"), "See you!");
+
+    let response = client
+        .post(format!("/guild/{}/welcome_message", guild))
+        .header(ContentType::Form)
+        .body("message=Hello+there")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(effective("welcome_message"), "Hello there");
+
+    let unknown = client
+        .get(format!("/guild/{}/welcome_message/effective", unique_id()))
+        .dispatch();
+    assert_eq!(unknown.status(), Status::NotFound);
+}
+
+#[test]
+fn lenient_privileges() {
+    let client = client();
+    let guild = new_guild(&client);
+    let role = unique_id();
+    grant_privilege(&client, guild, role, "admin");
+    let has = |query: &str| {
+        client
+            .get(format!(
+                "/guild/{}/privileges/has/{}?{}",
+                guild, role, query
+            ))
+            .dispatch()
+    };
+    let have = |privilege: &str, query: &str| {
+        client
+            .get(format!(
+                "/guild/{}/privileges/have/{}?roles={}{}",
+                guild, privilege, role, query
+            ))
+            .dispatch()
+    };
+
+    let strict = has("privileges_str=admin&privileges_str=owner");
+    assert_eq!(strict.status(), Status::BadRequest);
+    let lenient = has("privileges_str=admin&privileges_str=owner&strict=false");
+    assert_eq!(lenient.into_json::<bool>(), Some(true));
+    let lenient = has("privileges_str=manager&privileges_str=owner&strict=false");
+    assert_eq!(lenient.into_json::<bool>(), Some(false));
+
+    assert_eq!(have("owner", "").status(), Status::BadRequest);
+    assert_eq!(
+        have("owner", "&strict=false").into_json::<bool>(),
+        Some(false)
+    );
+    assert_eq!(
+        have("admin", "&strict=false").into_json::<bool>(),
+        Some(true)
+    );
+    assert_eq!(have("admin", "&roles=0").status(), Status::BadRequest);
+}
+
+#[test]
+fn unreachable_database() {
+    let mut config = PoolConfig::from_env().unwrap();
+    // nothing listens there
+    config.url = "postgres://linker@127.0.0.1:1/linker".to_string();
+    config.acquire_timeout = std::time::Duration::from_secs(1);
+    let started = Instant::now();
+    let ignited = block_on(rocket().manage(config).ignite());
+    assert!(ignited.is_err());
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+}
+
+#[test]
+fn guild_set_config() {
+    let client = client();
+    let guild = new_guild(&client);
+    let chan = unique_id();
+    let replace = |guild: u64, body: String| {
+        client
+            .post(format!("/guild/{}/config", guild))
+            .header(ContentType::Form)
+            .body(body)
+            .dispatch()
+    };
+
+    let response = replace(
+        guild,
+        format!(
+            "admin_channel={}&advertise=true&welcome_message=hi&goodbye_message=bye",
+            chan
+        ),
+    );
+    assert_eq!(response.status(), Status::Ok);
+    let config = serde_json::json!({
+        "id": guild.to_string(),
+        "admin_channel": chan.to_string(),
+        "advertise": true,
+        "welcome_message": "hi",
+        "goodbye_message": "bye",
+        "timezone": null,
+    });
+    assert_eq!(response.into_json::<serde_json::Value>().unwrap(), config);
+    let settings = client
+        .get(format!("/guild/{}", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(settings, config);
+
+    // the timezone is unset along with the other fields left out
+    let response = replace(guild, "advertise=false&timezone=Asia/Kolkata".into());
+    assert_eq!(
+        response.into_json::<serde_json::Value>().unwrap()["timezone"],
+        "Asia/Kolkata"
+    );
+    let response = replace(guild, "advertise=false".into());
+    assert_eq!(
+        response.into_json::<serde_json::Value>().unwrap(),
+        serde_json::json!({
+            "id": guild.to_string(),
+            "admin_channel": null,
+            "advertise": false,
+            "welcome_message": null,
+            "goodbye_message": null,
+            "timezone": null,
+        })
+    );
+
+    let too_long = "a".repeat(ApiConfig::default().max_message_len + 1);
+    let response = replace(
+        guild,
+        format!(
+            "advertise=true&welcome_message=hi&goodbye_message={}",
+            too_long
+        ),
+    );
+    assert_eq!(response.status(), Status::BadRequest);
+    let settings = client
+        .get(format!("/guild/{}", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(settings["welcome_message"], serde_json::Value::Null);
+
+    assert_eq!(
+        replace(unique_id(), "advertise=true".into()).status(),
+        Status::NotFound
+    );
+}
+
+#[test]
+fn gsr_slaps_cursor() {
+    let client = client();
+    let (guild, offender) = (unique_id(), unique_id());
+    for _ in 0..5 {
+        assert_eq!(slap(&client, guild, offender, None).status(), Status::Ok);
+    }
+    let page = |query: String| {
+        client
+            .get(format!("/slaps/{}/reports?number=2&{}", guild, query))
+            .dispatch()
+    };
+    let reports = |page: &serde_json::Value| -> Vec<i64> {
+        page["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|report| report["id"].as_i64().unwrap())
+            .collect()
+    };
+
+    // newest first, so that slaps given while paging come before the cursor
+    let first = page("sort=created_desc".into())
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    let mut seen = reports(&first);
+    let mut cursor = first["next_cursor"].as_str().unwrap().to_string();
+    for _ in 0..2 {
+        assert_eq!(slap(&client, guild, offender, None).status(), Status::Ok);
+    }
+    loop {
+        let next = page(format!("cursor={}", cursor))
+            .into_json::<serde_json::Value>()
+            .unwrap();
+        seen.extend(reports(&next));
+        match next["next_cursor"].as_str() {
+            Some(next) => cursor = next.to_string(),
+            None => break,
+        }
+    }
+    assert_eq!(seen.len(), 5);
+    assert!(seen.windows(2).all(|pair| pair[0] > pair[1]));
+
+    assert_eq!(
+        page(format!("cursor={}&sort=created_asc", cursor)).status(),
+        Status::BadRequest
+    );
+    let mut tampered = cursor.clone();
+    tampered.insert(4, 'x');
+    assert_eq!(
+        page(format!("cursor={}", tampered)).status(),
+        Status::BadRequest
+    );
+    assert_eq!(
+        page("cursor=bm90IGEgY3Vyc29y".into()).status(),
+        Status::BadRequest
+    );
+}
+
+#[test]
+fn guild_roles_with_all_any() {
+    let client = client();
+    let guild = new_guild(&client);
+    let roles = [unique_id(), unique_id(), unique_id(), unique_id()];
+    let held: [&[&str]; 4] = [
+        &["admin", "manager"],
+        &["manager"],
+        &["event"],
+        &["admin", "manager", "event"],
+    ];
+    for (role, privileges) in roles.iter().zip(held.iter()) {
+        for privilege in privileges.iter() {
+            grant_privilege(&client, guild, *role, privilege);
+        }
+    }
+    let roles_with = |semantics: &str, privileges: &[&str]| {
+        let query = privileges
+            .iter()
+            .map(|privilege| format!("privileges={}", privilege))
+            .collect::<Vec<_>>()
+            .join("&");
+        client
+            .get(format!(
+                "/guild/{}/privileges/roles_with_{}?{}",
+                guild, semantics, query
+            ))
+            .dispatch()
+    };
+    let ok = |semantics: &str, privileges: &[&str]| {
+        let response = roles_with(semantics, privileges);
+        assert_eq!(response.status(), Status::Ok);
+        ids(response.into_json().unwrap())
+    };
+
+    assert_eq!(ok("all", &["admin", "manager"]), [roles[0], roles[3]]);
+    assert_eq!(ok("all", &["manager", "event"]), [roles[3]]);
+    assert_eq!(ok("all", &["event"]), [roles[2], roles[3]]);
+    assert_eq!(
+        ok("any", &["admin", "event"]),
+        [roles[0], roles[2], roles[3]]
+    );
+    assert_eq!(ok("any", &["manager"]), [roles[0], roles[1], roles[3]]);
+
+    for semantics in ["all", "any"] {
+        assert_eq!(
+            roles_with(semantics, &["admin", "owner"]).status(),
+            Status::BadRequest
+        );
+        assert_eq!(roles_with(semantics, &[]).status(), Status::BadRequest);
+    }
+}
+
+#[test]
+fn oversized_number() {
+    let beyond = usize::MAX as u128 + 1;
+    assert_eq!(
+        params::count("number", Some(Ok(usize::MAX as u128))).unwrap(),
+        Some(usize::MAX)
+    );
+    assert!(matches!(
+        params::count("number", Some(Ok(beyond))),
+        Err(ApiError::NumberTooLarge {
+            param: "number",
+            max: usize::MAX
+        })
+    ));
+    assert_eq!(params::count("number", None).unwrap(), None);
+
+    let client = client();
+    let guild = unique_id();
+    let member = unique_id();
+    // beyond 2^64 - 1, hence too large whatever the platform
+    for path in [
+        format!("/slaps/{}/reports?", guild),
+        format!("/slaps/{}/offenders?", guild),
+        format!("/slaps/{}/offenders/detailed?", guild),
+        format!("/slaps/{}/enforcers/top?", guild),
+        format!("/slaps/{}/recent?", guild),
+        format!("/slaps/{}/search?q=spam&", guild),
+        format!("/slaps/{}/{}/reports?", guild, member),
+        format!("/slaps/{}/{}/recent?", guild, member),
+        "/guilds?".to_owned(),
+        format!("/guild/{}/audit?", guild),
+        "/admin/guilds/recently_modified?".to_owned(),
+    ] {
+        let response = client
+            .get(format!("{}number=18446744073709551616", path))
+            .header(admin())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "{}", path);
+        let body = response.into_string().unwrap();
+        assert!(body.contains("on this platform"), "{}", body);
+
+        let response = client
+            .get(format!("{}number=lots", path))
+            .header(admin())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "{}", path);
+    }
+
+    // offsets are bounded the same way, rather than silently ignored
+    for path in [
+        format!("/slaps/{}/reports?", guild),
+        format!("/slaps/{}/offenders?", guild),
+        format!("/slaps/{}/offenders/detailed?", guild),
+        format!("/slaps/{}/search?q=spam&", guild),
+        format!("/slaps/{}/{}/reports?", guild, member),
+        "/guilds?".to_owned(),
+        format!("/guild/{}/audit?", guild),
+    ] {
+        let response = client
+            .get(format!("{}offset=18446744073709551616", path))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "{}", path);
+        let body = response.into_json::<serde_json::Value>().unwrap();
+        assert!(
+            body["reason"].as_str().unwrap().contains("`offset`"),
+            "{}",
+            body
+        );
+
+        let response = client.get(format!("{}offset=abc", path)).dispatch();
+        assert_eq!(response.status(), Status::BadRequest, "{}", path);
+        let body = response.into_json::<serde_json::Value>().unwrap();
+        assert!(
+            body["reason"].as_str().unwrap().contains("`offset`"),
+            "{}",
+            body
+        );
+    }
+}
+
+#[test]
+fn msr_active() {
+    let client = client();
+    let guild = unique_id();
+    let (offender, bystander) = (unique_id(), unique_id());
+    let report = slap(&client, guild, offender, None)
+        .into_json::<serde_json::Value>()
+        .unwrap()["id"]
+        .as_i64()
+        .unwrap();
+    let active = |member: u64| {
+        client
+            .get(format!("/slaps/{}/{}/active", guild, member))
+            .dispatch()
+            .into_json::<bool>()
+            .unwrap()
+    };
+    assert!(active(offender));
+    assert!(!active(bystander));
+
+    let response = client
+        .post(format!("/slaps/{}/{}/pardon", guild, report))
+        .header(ContentType::JSON)
+        .header(admin())
+        .body(serde_json::json!({"reason": "mistaken identity"}).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(!active(offender));
+}
+
+#[test]
+fn trailing_slash() {
+    let client = client();
+    let guild = new_guild(&client);
+    slap(&client, guild, unique_id(), None);
+
+    for path in [
+        format!("/v1/guild/{}/exists", guild),
+        format!("/guild/{}/exists", guild),
+        format!("/v1/slaps/{}/reports?number=1", guild),
+    ] {
+        let slashed = match path.split_once('?') {
+            Some((path, query)) => format!("{}/?{}", path, query),
+            None => format!("{}//", path),
+        };
+        let expected = client.get(path.clone()).dispatch();
+        let response = client.get(slashed.clone()).dispatch();
+        assert_eq!(response.status(), expected.status(), "{}", slashed);
+        assert_eq!(response.status(), Status::Ok, "{}", slashed);
+        assert_eq!(
+            response.headers().get_one("Link"),
+            expected.headers().get_one("Link")
+        );
+        assert_eq!(response.into_string(), expected.into_string());
+    }
+}
+
+#[test]
+fn gsr_recent_count() {
+    let harness = Harness::new();
+    let (guild, _) = harness.seed(4);
+    block_on(
+        sqlx::query(
+            "UPDATE slaps SET created_at = now() - INTERVAL '30 minutes'
+            WHERE id IN (SELECT id FROM slaps ORDER BY id LIMIT 2)",
+        )
+        .execute(harness.pool()),
+    )
+    .unwrap();
+    block_on(
+        sqlx::query(
+            "UPDATE slaps SET created_at = now() - INTERVAL '2 hours'
+            WHERE id = (SELECT min(id) FROM slaps)",
+        )
+        .execute(harness.pool()),
+    )
+    .unwrap();
+    let recent_count = |minutes: &str| {
+        harness
+            .client()
+            .get(format!("/slaps/{}/recent_count?minutes={}", guild, minutes))
+            .dispatch()
+    };
+    let count = |minutes: u32| {
+        recent_count(&minutes.to_string())
+            .into_json::<usize>()
+            .unwrap()
+    };
+
+    assert_eq!(count(10), 2);
+    assert_eq!(count(60), 3);
+    assert_eq!(count(180), 4);
+    assert_eq!(count(10080), 4);
+    for minutes in ["0", "10081"] {
+        assert_eq!(recent_count(minutes).status(), Status::BadRequest);
+    }
+}
+
+#[test]
+fn guild_new_conflict() {
+    let client = client();
+    let guild = new_guild(&client);
+    let response = client
+        .post("/guild/new")
+        .header(ContentType::Form)
+        .body(format!("id={}&advertise=false", guild))
+        .dispatch();
+    assert_eq!(response.status(), Status::Conflict);
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(body["type"], "validation");
+    assert_eq!(body["id"], guild.to_string());
+
+    let advertise = client.get(format!("/guild/{}/advertise", guild)).dispatch();
+    assert_eq!(advertise.into_json::<bool>(), Some(true));
+}
+
+#[test]
+fn privilege_names() {
+    for name in PrivilegeName::ALL {
+        let json = serde_json::to_string(&name).unwrap();
+        assert_eq!(serde_json::from_str::<PrivilegeName>(&json).unwrap(), name);
+        assert_eq!(PrivilegeName::from(Privilege::from(name)), name);
+        // the names answered so far stay the same
+        assert_eq!(
+            json,
+            format!("\"{}\"", AsRef::<str>::as_ref(&Privilege::from(name)))
+        );
+    }
+    assert!(serde_json::from_str::<PrivilegeName>("\"owner\"").is_err());
+}
+
+#[test]
+fn max_sentence() {
+    let client = client_with(|figment| figment.merge(("max_sentence", 100)));
+    let (guild, offender) = (unique_id(), unique_id());
+    let new_slap = |sentence: u64| {
+        let form = format!(
+            "guild={}&sentence={}&offender={}",
+            guild, sentence, offender
+        );
+        post_slap(&client, form, None).status()
+    };
+    assert_eq!(new_slap(100), Status::Ok);
+    assert_eq!(new_slap(0), Status::Ok);
+    let response = post_slap(
+        &client,
+        format!("guild={}&sentence=101&offender={}", guild, offender),
+        None,
+    );
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert!(body["reason"].as_str().unwrap().contains("100"));
+
+    let batch = client
+        .post("/slaps/batch")
+        .header(ContentType::JSON)
+        .header(admin())
+        .body(
+            serde_json::json!([
+                {"guild": guild, "sentence": 1, "offender": offender},
+                {"guild": guild, "sentence": 101, "offender": offender},
+            ])
+            .to_string(),
+        )
+        .dispatch();
+    assert_eq!(batch.status(), Status::UnprocessableEntity);
+    assert_eq!(gsr_len(&client, guild), 2);
+
+    let csv = format!(
+        "id,offender,enforcer,sentence,reason,created_at\n\
+        1,{0},,100,,2021-09-14T12:00:00Z\n\
+        2,{0},,101,,2021-09-14T12:00:00Z\n",
+        offender
+    );
+    let imported = import(&client, guild, csv)
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(imported["imported"], 1);
+    assert_eq!(imported["errors"][0]["line"], 3);
+    assert_eq!(gsr_len(&client, guild), 3);
+}
+
+#[test]
+fn security_headers() {
+    let client = client();
+    let response = client.get("/v1/version").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let headers = response.headers();
+    assert_eq!(headers.get_one("X-Content-Type-Options"), Some("nosniff"));
+    assert_eq!(headers.get_one("X-Frame-Options"), Some("DENY"));
+    assert_eq!(headers.get_one("Referrer-Policy"), Some("no-referrer"));
+
+    let client = client_with(|figment| figment.merge(("security_headers", false)));
+    let response = client.get("/v1/version").dispatch();
+    for header in [
+        "X-Content-Type-Options",
+        "X-Frame-Options",
+        "Referrer-Policy",
+    ] {
+        assert_eq!(response.headers().get_one(header), None, "{}", header);
+    }
+}
+
+#[test]
+fn guild_grant_roles() {
+    let client = client();
+    let guild = new_guild(&client);
+    let (first, second) = (unique_id(), unique_id());
+    let grant = |query: &str| {
+        client
+            .post(format!("/guild/{}/privileges/grant{}", guild, query))
+            .header(ContentType::JSON)
+            .body(
+                serde_json::json!([
+                    {"role": first, "privileges": ["admin", "event"]},
+                    {"role": second, "privileges": ["owner"]},
+                    {"role": 0, "privileges": ["manager"]},
+                ])
+                .to_string(),
+            )
+            .dispatch()
+    };
+    let privileges = |role: u64| {
+        client
+            .get(format!("/guild/{}/privileges/for_role/{}", guild, role))
+            .dispatch()
+            .into_json::<Vec<String>>()
+            .unwrap()
+    };
+
+    // all or nothing by default
+    assert_eq!(grant("").status(), Status::BadRequest);
+    assert!(privileges(first).is_empty());
+
+    let response = grant("?partial=true");
+    assert_eq!(response.status(), Status::Ok);
+    let outcomes = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(
+        outcomes[0],
+        serde_json::json!({"role": first.to_string(), "status": 200})
+    );
+    assert_eq!(outcomes[1]["role"], second.to_string());
+    assert_eq!(outcomes[1]["status"], 400);
+    assert!(outcomes[1]["error"].as_str().unwrap().contains("owner"));
+    assert_eq!(outcomes[2]["status"], 400);
+    assert_eq!(privileges(first), ["admin", "event"]);
+    assert!(privileges(second).is_empty());
+
+    let response = client
+        .post(format!("/guild/{}/privileges/grant", guild))
+        .header(ContentType::JSON)
+        .body(serde_json::json!([{"role": second, "privileges": ["manager"]}]).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(privileges(second), ["manager"]);
+}
+
+#[test]
+fn gsr_reassign_enforcer() {
+    let client = client();
+    let guild = new_guild(&client);
+    let (from, to, other) = (unique_id(), unique_id(), unique_id());
+    for enforcer in [from, from, other] {
+        let form = format!(
+            "guild={}&sentence=1&offender={}&enforcer={}",
+            guild,
+            unique_id(),
+            enforcer
+        );
+        assert_eq!(post_slap(&client, form, None).status(), Status::Ok);
+    }
+
+    let response = client
+        .post(format!("/slaps/{}/enforcer/reassign", guild))
+        .header(ContentType::JSON)
+        .header(admin())
+        .body(format!(r#"{{"from": "{}", "to": {}}}"#, from, to))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<u64>(), Some(2));
+
+    let reports = client
+        .get(format!("/slaps/{}/reports", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    let enforcers = reports["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|report| report["enforcer"].as_str().unwrap().parse().unwrap())
+        .collect::<Vec<u64>>();
+    assert_eq!(enforcers, [to, to, other]);
+}
+
+#[test]
+fn stored_message_too_long() {
+    let client = client();
+    let guild = new_guild(&client);
+    let message = "a message from before the limit";
+    let set_welcome = |client: &Client, message: &str| {
+        client
+            .post(format!("/guild/{}/welcome_message", guild))
+            .header(ContentType::Form)
+            .body(format!("message={}", message.replace(' ', "+")))
+            .dispatch()
+    };
+    assert_eq!(set_welcome(&client, message).status(), Status::Ok);
+
+    // the limit is lowered afterwards
+    let strict = client.sibling(settings(|figment| figment.merge(("max_message_len", 10))));
+    let read = strict
+        .get(format!("/guild/{}/welcome_message", guild))
+        .dispatch();
+    assert_eq!(read.status(), Status::Ok);
+    assert_eq!(
+        read.into_json::<Option<String>>(),
+        Some(Some(message.into()))
+    );
+
+    let resent = set_welcome(&strict, message);
+    assert_eq!(resent.status(), Status::UnprocessableEntity);
+    let reason = resent.into_json::<serde_json::Value>().unwrap()["reason"].clone();
+    assert!(reason.as_str().unwrap().contains("predates"), "{}", reason);
+
+    let oversized = set_welcome(&strict, "another long message");
+    assert_eq!(oversized.status(), Status::UnprocessableEntity);
+    let reason = oversized.into_json::<serde_json::Value>().unwrap()["reason"].clone();
+    assert!(!reason.as_str().unwrap().contains("predates"), "{}", reason);
+}
+
+#[test]
+fn guild_has_all_any_privileges() {
+    let client = client();
+    let guild = new_guild(&client);
+    let role = unique_id();
+    grant_privilege(&client, guild, role, "manager");
+    let has = |mode: &str, query: &str| {
+        client
+            .get(format!(
+                "/guild/{}/privileges/{}/{}?{}",
+                guild, mode, role, query
+            ))
+            .dispatch()
+            .into_json::<bool>()
+            .unwrap()
+    };
+
+    // the role holds a subset of what is asked
+    let subset = "privileges_str=manager&privileges_str=admin";
+    assert!(!has("has_all", subset));
+    assert!(!has("has", subset));
+    assert!(has("has_any", subset));
+
+    assert!(has("has_all", "privileges_str=manager"));
+    assert!(!has("has_any", "privileges_str=admin&privileges_str=event"));
+    assert!(!has("has_any", "privileges_str=owner&strict=false"));
+    let unknown = client
+        .get(format!(
+            "/guild/{}/privileges/has_any/{}?privileges_str=owner",
+            guild, role
+        ))
+        .dispatch();
+    assert_eq!(unknown.status(), Status::BadRequest);
+}
+
+#[test]
+fn form_and_json_bodies() {
+    let client = client();
+    let guild = new_guild(&client);
+    let chan = unique_id();
+    let (first, second) = (unique_id(), unique_id());
+    let endpoints = [
+        (
+            "/slaps/new".to_owned(),
+            format!("guild={}&sentence=1&offender={}", guild, unique_id()),
+            serde_json::json!({"guild": guild.to_string(), "sentence": 1, "offender": unique_id()}),
+        ),
+        (
+            "/guild/new".to_owned(),
+            format!("id={}&advertise=yes", first),
+            serde_json::json!({"id": second.to_string(), "advertise": true}),
+        ),
+        (
+            format!("/guild/{}/config", guild),
+            "advertise=true&welcome_message=hi".to_owned(),
+            serde_json::json!({"advertise": false, "admin_channel": chan.to_string()}),
+        ),
+        (
+            format!("/guild/{}/admin_channel", guild),
+            format!("chan={}", chan),
+            serde_json::json!(chan.to_string()),
+        ),
+        (
+            format!("/guild/{}/advertise", guild),
+            "policy=no".to_owned(),
+            serde_json::json!(true),
+        ),
+        (
+            format!("/guild/{}/welcome_message", guild),
+            "message=hi".to_owned(),
+            serde_json::json!("hé \"there\""),
+        ),
+        (
+            format!("/guild/{}/goodbye_message", guild),
+            "message=bye".to_owned(),
+            serde_json::json!(null),
+        ),
+    ];
+    for (uri, form, json) in &endpoints {
+        let response = client
+            .post(uri.as_str())
+            .header(ContentType::Form)
+            .body(form)
+            .dispatch();
+        // guilds are created rather than merely set
+        let status = response.status();
+        assert!(status.class().is_success(), "form to {}: {}", uri, status);
+        let response = client
+            .post(uri.as_str())
+            .header(ContentType::JSON)
+            .body(json.to_string())
+            .dispatch();
+        let status = response.status();
+        assert!(status.class().is_success(), "JSON to {}: {}", uri, status);
+
+        let response = client
+            .post(uri.as_str())
+            .header(ContentType::Plain)
+            .body(form)
+            .dispatch();
+        assert_eq!(
+            response.status(),
+            Status::UnsupportedMediaType,
+            "text to {}",
+            uri
+        );
+        let body = response.into_json::<serde_json::Value>().unwrap();
+        assert_eq!(body["type"], "validation");
+        assert!(
+            body["reason"].as_str().unwrap().contains("text/plain"),
+            "{}",
+            body
+        );
+    }
+    let response = client.post("/guild/new").body("id=1").dispatch();
+    assert_eq!(response.status(), Status::UnsupportedMediaType);
+
+    let settings = client
+        .get(format!("/guild/{}", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(
+        settings,
+        serde_json::json!({
+            "id": guild.to_string(),
+            "admin_channel": chan.to_string(),
+            "advertise": true,
+            "welcome_message": "hé \"there\"",
+            "goodbye_message": null,
+            "timezone": null,
+        })
+    );
+    for id in [first, second] {
+        let response = client.get(format!("/guild/{}", id)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+}
+
+#[test]
+fn malformed_bodies() {
+    let client = client();
+    let guild = new_guild(&client);
+    let bodies = [
+        (ContentType::JSON, "{\"guild\": ".to_owned()),
+        (
+            ContentType::JSON,
+            serde_json::json!({"guild": guild.to_string()}).to_string(),
+        ),
+        (ContentType::Form, format!("guild={}&sentence=many", guild)),
+    ];
+    for (content_type, body) in bodies {
+        let response = client
+            .post("/slaps/new")
+            .header(content_type)
+            .body(&body)
+            .dispatch();
+        assert!(response.status().code < 500, "{}", body);
+        assert_eq!(response.content_type(), Some(ContentType::JSON), "{}", body);
+        let error = response.into_json::<serde_json::Value>().unwrap();
+        assert_eq!(error["type"], "validation", "{}", body);
+        assert!(
+            error["reason"]
+                .as_str()
+                .unwrap()
+                .starts_with("invalid body"),
+            "{}",
+            error
+        );
+    }
+    assert_eq!(gsr_len(&client, guild), 0);
+}
+
+#[test]
+fn msr_recent() {
+    let harness = Harness::new();
+    let client = harness.client();
+    let (guild, member) = (unique_id(), unique_id());
+    let reports = (0..3)
+        .map(|_| {
+            slap(client, guild, member, None)
+                .into_json::<serde_json::Value>()
+                .unwrap()["id"]
+                .as_i64()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    slap(client, guild, unique_id(), None);
+    // the first report is the newest, the last one the oldest
+    for (report, hours) in reports.iter().zip(1..) {
+        block_on(
+            sqlx::query(
+                "UPDATE slaps SET created_at = now() - make_interval(hours => $1) WHERE id = $2",
+            )
+            .bind(hours)
+            .bind(report)
+            .execute(harness.pool()),
+        )
+        .unwrap();
+    }
+    let recent = |number: usize| {
+        client
+            .get(format!(
+                "/slaps/{}/{}/recent?number={}",
+                guild, member, number
+            ))
+            .dispatch()
+            .into_json::<Vec<serde_json::Value>>()
+            .unwrap()
+            .iter()
+            .map(|slap| slap["id"].as_i64().unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(recent(10), reports);
+    assert_eq!(recent(2), reports[..2]);
+}
+
+#[test]
+fn guild_set_privileges() {
+    let client = client();
+    let guild = new_guild(&client);
+    let role = unique_id();
+    grant_privilege(&client, guild, role, "admin");
+    grant_privilege(&client, guild, role, "event");
+    let set = |privileges: serde_json::Value| {
+        client
+            .put(format!("/guild/{}/privileges/for_role/{}", guild, role))
+            .header(ContentType::JSON)
+            .body(privileges.to_string())
+            .dispatch()
+    };
+    let held = || {
+        client
+            .get(format!("/guild/{}/privileges/for_role/{}", guild, role))
+            .dispatch()
+            .into_json::<Vec<String>>()
+            .unwrap()
+    };
+
+    let response = set(serde_json::json!(["manager"]));
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<Vec<String>>().unwrap(), ["manager"]);
+    assert_eq!(held(), ["manager"]);
+
+    let response = set(serde_json::json!(["event", "owner"]));
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(held(), ["manager"]);
+
+    let response = set(serde_json::json!([]));
+    assert_eq!(
+        response.into_json::<Vec<String>>().unwrap(),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn not_found() {
+    let client = client();
+    let guild = unique_id();
+    let response = client
+        .get(format!("/slaps/{}/{}", guild, i64::MAX))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(
+        response.into_json::<serde_json::Value>().unwrap(),
+        serde_json::json!({
+            "type": "validation",
+            "reason": format!("there is no report {}", i64::MAX),
+            "resource": "report",
+            "id": i64::MAX.to_string(),
+        })
+    );
+
+    let response = client.get(format!("/guild/{}", guild)).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let body = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(body["resource"], "guild");
+    assert_eq!(body["id"], guild.to_string());
+}
+
+#[test]
+fn gsr_export_large() {
+    const SLAPS: usize = 20_000;
+    let harness = Harness::new();
+    let guild = unique_id();
+    block_on(
+        sqlx::query(
+            "INSERT INTO slaps (guild, sentence, offender, reason)
+            SELECT $1, n, n, 'spam, again' FROM generate_series(1, $2) AS n",
+        )
+        .bind(guild as i64)
+        .bind(SLAPS as i32)
+        .execute(harness.pool()),
+    )
+    .unwrap();
+
+    let response = harness
+        .client()
+        .get(format!("/slaps/{}/export.csv", guild))
+        .header(Header::new("Accept-Encoding", "gzip"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    // a sized body would have been built in memory first
+    assert_eq!(response.headers().get_one("Content-Length"), None);
+    assert_eq!(response.headers().get_one("Content-Encoding"), None);
+    // read line by line, as a client would, rather than as one string
+    let mut rows = 0;
+    for line in BufReader::new(response).lines().skip(1) {
+        assert!(line.unwrap().contains(",\"spam, again\","));
+        rows += 1;
+    }
+    assert_eq!(rows, SLAPS);
+}
+
+#[test]
+fn guild_timezone() {
+    let client = client();
+    let guild = new_guild(&client);
+    let set_timezone = |timezone: &str| {
+        client
+            .post(format!("/guild/{}/timezone", guild))
+            .header(ContentType::Form)
+            .body(format!("timezone={}", timezone))
+            .dispatch()
+            .status()
+    };
+    let timezone = || {
+        client
+            .get(format!("/guild/{}/timezone", guild))
+            .dispatch()
+            .into_json::<Option<String>>()
+            .unwrap()
+    };
+    assert_eq!(timezone(), None);
+
+    // no daylight saving time, so that the offset is always the same
+    assert_eq!(set_timezone("Asia/Kolkata"), Status::Ok);
+    assert_eq!(timezone().as_deref(), Some("Asia/Kolkata"));
+    for invalid in ["Mars/Olympus_Mons", "UTC%2B2"] {
+        assert_eq!(set_timezone(invalid), Status::UnprocessableEntity);
+    }
+    assert_eq!(timezone().as_deref(), Some("Asia/Kolkata"));
+
+    slap(&client, guild, unique_id(), None);
+    let csv = client
+        .get(format!("/slaps/{}/export.csv", guild))
+        .dispatch()
+        .into_string()
+        .unwrap();
+    let row = csv.lines().nth(1).unwrap();
+    assert!(row.ends_with("+05:30"), "{}", row);
+
+    let response = client
+        .post(format!("/guild/{}/timezone", guild))
+        .header(ContentType::JSON)
+        .body("null")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(timezone(), None);
+    let csv = client
+        .get(format!("/slaps/{}/export.csv", guild))
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert!(csv.lines().nth(1).unwrap().ends_with("+00:00"));
+
+    let response = client
+        .post(format!("/guild/{}/timezone", unique_id()))
+        .header(ContentType::Form)
+        .body("timezone=Europe/Paris")
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[test]
+fn gsr_timeseries() {
+    let harness = Harness::new();
+    let client = harness.client();
+    let guild = new_guild(client);
+    for sentence in [1, 2, 3, 4] {
+        slap_with_sentence(client, guild, unique_id(), sentence);
+    }
+    // the first slap three days ago, the second yesterday, the others today
+    for (sentence, days) in [(1, 3), (2, 1)] {
+        block_on(
+            sqlx::query(
+                "UPDATE slaps SET created_at = now() - make_interval(days => $1)
+                WHERE guild = $2 AND sentence = $3",
+            )
+            .bind(days)
+            .bind(guild as i64)
+            .bind(sentence as i64)
+            .execute(harness.pool()),
+        )
+        .unwrap();
+    }
+    let timeseries = |days: &str| {
+        client
+            .get(format!("/slaps/{}/timeseries?days={}", guild, days))
+            .dispatch()
+    };
+
+    let series = timeseries("4")
+        .into_json::<Vec<serde_json::Value>>()
+        .unwrap();
+    let buckets = series
+        .iter()
+        .map(|day| {
+            (
+                day["count"].as_i64().unwrap(),
+                day["total_sentence"].as_i64().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(buckets, [(1, 1), (0, 0), (1, 2), (2, 7)]);
+    let today = Utc::now().naive_utc().date();
+    assert_eq!(series[3]["date"], today.to_string());
+    assert_eq!(series[0]["date"], (today - Duration::days(3)).to_string());
+
+    let series = client
+        .get(format!("/slaps/{}/timeseries", guild))
+        .dispatch()
+        .into_json::<Vec<serde_json::Value>>()
+        .unwrap();
+    assert_eq!(series.len(), 30);
+    for days in ["0", "367", "-1", "many"] {
+        assert_eq!(timeseries(days).status(), Status::BadRequest, "{}", days);
+    }
+}
+
+#[test]
+fn include_removed() {
+    // an isolated database, so that the growth only counts the slaps of the test
+    let harness = Harness::new();
+    let client = harness.client();
+    let guild = new_guild(&client);
+    let offender = unique_id();
+    let report = slap(&client, guild, offender, None)
+        .into_json::<serde_json::Value>()
+        .unwrap()["id"]
+        .as_i64()
+        .unwrap();
+    slap(&client, guild, offender, None);
+    let response = client
+        .post(format!("/slaps/{}/{}/pardon", guild, report))
+        .header(ContentType::JSON)
+        .header(admin())
+        .body(serde_json::json!({"reason": "mistaken identity"}).to_string())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let count = |path: &str, query: &str| {
+        client
+            .get(format!("/slaps/{}/{}?{}", guild, path, query))
+            .dispatch()
+            .into_json::<usize>()
+            .unwrap()
+    };
+
+    let member_len = format!("{}/len", offender);
+    for (path, query) in [
+        ("len", ""),
+        (member_len.as_str(), ""),
+        ("count", ""),
+        ("recent_count", "minutes=60&"),
+    ] {
+        assert_eq!(count(path, query), 1, "{}", path);
+        let removed = format!("{}include_removed=true", query);
+        assert_eq!(count(path, &removed), 2, "{}", path);
+        let kept = format!("{}include_removed=false", query);
+        assert_eq!(count(path, &kept), 1, "{}", path);
+        let pardoned = format!("{}include_pardoned=true", query);
+        assert_eq!(count(path, &pardoned), 2, "{}", path);
+    }
+
+    let listed = |path: &str, query: &str| {
+        let page = client
+            .get(format!("/slaps/{}/{}?{}", guild, path, query))
+            .dispatch()
+            .into_json::<serde_json::Value>()
+            .unwrap();
+        let reports = page["data"].as_array().unwrap().len();
+        (reports, page["total"].as_u64().unwrap() as usize)
+    };
+    let member_reports = format!("{}/reports", offender);
+    for path in ["reports", member_reports.as_str()] {
+        assert_eq!(listed(path, ""), (1, 1), "{}", path);
+        assert_eq!(listed(path, "include_removed=true"), (2, 2), "{}", path);
+        assert_eq!(listed(path, "include_removed=false"), (1, 1), "{}", path);
+        assert_eq!(listed(path, "include_pardoned=true"), (2, 2), "{}", path);
+    }
+
+    let today = |query: &str| {
+        client
+            .get(format!("/slaps/{}/timeseries?days=1{}", guild, query))
+            .dispatch()
+            .into_json::<Vec<serde_json::Value>>()
+            .unwrap()[0]["count"]
+            .as_i64()
+            .unwrap()
+    };
+    assert_eq!(today(""), 1);
+    assert_eq!(today("&include_removed=true"), 2);
+    assert_eq!(today("&include_pardoned=true"), 2);
+
+    let since = (Utc::now() - Duration::hours(1)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let growth = |query: &str| {
+        let series = client
+            .get(format!("/admin/slaps/growth?since={}{}", since, query))
+            .header(admin())
+            .dispatch()
+            .into_json::<Vec<serde_json::Value>>()
+            .unwrap();
+        series.last().unwrap()["total"].as_i64().unwrap()
+    };
+    assert_eq!(growth(""), 1);
+    assert_eq!(growth("&include_removed=true"), 2);
+    assert_eq!(growth("&include_pardoned=true"), 2);
+}
+
+#[test]
+fn validation_errors() {
+    let client = client();
+    let fields = |response: LocalResponse<'_>| {
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body = response.into_json::<serde_json::Value>().unwrap();
+        assert_eq!(body["type"], "validation");
+        body["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| {
+                assert!(field["error"].is_string());
+                field["field"].as_str().unwrap().to_owned()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let id = unique_id();
+    let response = client
+        .post("/guild/new")
+        .header(ContentType::Form)
+        .body(format!(
+            "id={}&advertise=maybe&welcome_message={}",
+            id,
+            "a".repeat(2001)
+        ))
+        .dispatch();
+    assert_eq!(fields(response), ["advertise", "welcome_message"]);
+    let exists = client.get(format!("/guild/{}/exists", id)).dispatch();
+    assert_eq!(exists.into_json::<bool>(), Some(false));
+
+    let response = client
+        .put(format!("/guild/{}", unique_id()))
+        .header(ContentType::Form)
+        .body(format!(
+            "advertise=true&admin_channel=0&goodbye_message={}",
+            "b".repeat(2001)
+        ))
+        .dispatch();
+    assert_eq!(fields(response), ["admin_channel", "goodbye_message"]);
+}
+
+#[test]
+fn admin_config() {
+    let client = client_with(|figment| figment.merge(("max_page_size", 123)));
+    let response = client.get("/admin/config").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert_eq!(
+        response.headers().get_one("WWW-Authenticate"),
+        Some("Bearer")
+    );
+    let error = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(error["type"], "validation");
+    assert!(error["reason"].as_str().unwrap().contains("Bearer"));
+    let response = client
+        .get("/admin/config")
+        .header(Header::new("Authorization", "Bearer wrong"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert_eq!(
+        response.into_json::<serde_json::Value>().unwrap()["type"],
+        "validation"
+    );
+
+    let response = client.get("/admin/config").header(admin()).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let config = response.into_json::<serde_json::Value>().unwrap();
+    assert_eq!(config["max_page_size"], 123);
+    assert_eq!(config["default_page_size"], 50);
+    assert_eq!(config["advertise_enabled"], true);
+    assert!(config["slap_webhook_url"].is_null(), "{}", config);
+    assert!(!config.to_string().contains(ADMIN_TOKEN), "{}", config);
+
+    // the URL of the webhook is never answered
+    let client = Harness::with(|rocket| {
+        rocket.manage(Webhook::new(Some("http://127.0.0.1:1/slaps".into())))
+    });
+    let config = client
+        .get("/admin/config")
+        .header(admin())
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(config["slap_webhook_url"], "[redacted]");
+    assert!(!config.to_string().contains("http"), "{}", config);
+}
+
+#[test]
+fn guild_delete() {
+    let client = client();
+    let (guild, other) = (new_guild(&client), new_guild(&client));
+    let role = unique_id();
+    grant_privilege(&client, guild, role, "admin");
+    grant_privilege(&client, guild, role, "event");
+    grant_privilege(&client, other, role, "admin");
+    for _ in 0..3 {
+        slap(&client, guild, unique_id(), None);
+    }
+    slap(&client, other, unique_id(), None);
+    let delete = |query: &str| {
+        client
+            .delete(format!("/guild/{}{}", guild, query))
+            .header(admin())
+            .dispatch()
+    };
+    let exists = |guild: u64| {
+        client
+            .get(format!("/guild/{}/exists", guild))
+            .dispatch()
+            .into_json::<bool>()
+            .unwrap()
+    };
+    let summary = serde_json::json!({"slaps": 3, "privileges": 2});
+
+    let response = client.delete(format!("/guild/{}", guild)).dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert_eq!(
+        response.into_json::<serde_json::Value>().unwrap()["type"],
+        "validation"
+    );
+
+    let response = delete("?dry_run=true");
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<serde_json::Value>().unwrap(), summary);
+    assert!(exists(guild));
+    assert_eq!(gsr_len(&client, guild), 3);
+
+    let response = delete("");
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<serde_json::Value>().unwrap(), summary);
+    assert!(!exists(guild));
+    assert_eq!(gsr_len(&client, guild), 0);
+    let privileges = client
+        .post(format!("/guild/{}/privileges/resolve", guild))
+        .header(ContentType::JSON)
+        .body(serde_json::json!([role]).to_string())
+        .dispatch()
+        .into_json::<Vec<String>>()
+        .unwrap();
+    assert!(privileges.is_empty(), "{:?}", privileges);
+
+    // other guilds are left as they are
+    assert!(exists(other));
+    assert_eq!(gsr_len(&client, other), 1);
+
+    for query in ["", "?dry_run=true"] {
+        assert_eq!(delete(query).status(), Status::NotFound, "{}", query);
+    }
+    let audit = client
+        .get(format!("/guild/{}/audit", guild))
+        .dispatch()
+        .into_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(audit["data"][0]["summary"], "deleted the guild");
+}
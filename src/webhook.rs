@@ -0,0 +1,55 @@
+//! Outbound notifications of the slaps created through the linker.
+
+use crate::{id::Snowflake, slaps::Slap};
+use reqwest::Client;
+use serde::Serialize;
+use std::env;
+
+/// Body `POST`ed to the webhook for every new slap.
+#[derive(Debug, Serialize)]
+struct SlapCreated<'a> {
+    guild: Snowflake,
+    report: &'a Slap,
+}
+
+/// Where new slaps are sent to, `SLAP_WEBHOOK_URL`. Nothing is sent when unset.
+pub struct Webhook(Option<(Client, String)>);
+
+impl Webhook {
+    pub fn new(url: Option<String>) -> Self {
+        Webhook(url.map(|url| (Client::new(), url)))
+    }
+
+    pub fn from_env() -> Self {
+        Webhook::new(env::var("SLAP_WEBHOOK_URL").ok())
+    }
+
+    /// Whether there is a webhook to send slaps to.
+    pub fn is_set(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Sends `report` of `guild` to the webhook in the background.
+    ///
+    /// Delivery failures are only logged: they must not fail the slap itself.
+    pub fn notify(&self, guild: u64, report: &Slap) {
+        let (client, url) = match &self.0 {
+            Some(webhook) => webhook,
+            None => return,
+        };
+        let request = client.post(url).json(&SlapCreated {
+            guild: Snowflake(guild),
+            report,
+        });
+        rocket::tokio::spawn(async move {
+            let delivery = request.send().await.and_then(|res| res.error_for_status());
+            if let Err(err) = delivery {
+                log::warn!(
+                    "couldn't deliver a slap of {} to the webhook: {}",
+                    guild,
+                    err
+                );
+            }
+        });
+    }
+}